@@ -0,0 +1,167 @@
+//! Compares how long the recursive `Node::compute`, row by row `Node::compute_batch` and compiled programs
+//! take to compute whole cell arrays
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use failure::{format_err, Fallible};
+use log::info;
+use mutagen::{Generatable, Traversable};
+
+use crate::{
+    constants::*,
+    datatype::{
+        colors::{ByteColor, FloatColor},
+        continuous::UNFloat,
+    },
+    node::{color_nodes::FloatColorNodes, Node, Simplify},
+    profile::mutagen_state,
+    updatestate::{CoordinateSet, UpdateState},
+    util::DeterministicRng,
+    vm::Program,
+    History,
+};
+
+/// Generates `trees` root nodes and times computing every cell `passes` times with each of them, every way.
+/// Fails if the ways ever compute different colors.
+pub fn run(trees: usize, passes: usize) -> Fallible<()> {
+    if passes == 0 {
+        return Err(format_err!("Can't time fewer than 1 pass"));
+    }
+
+    let history = History::new(
+        CONSTS.cell_array_width,
+        CONSTS.cell_array_height,
+        CONSTS.cell_array_history_length,
+    );
+    let mut rng = DeterministicRng::new();
+
+    let mut recursive_total = Duration::default();
+    let mut batched_total = Duration::default();
+    let mut compiled_total = Duration::default();
+
+    for tree_index in 0..trees {
+        let tree = FloatColorNodes::generate_rng(&mut rng, mutagen_state()).simplify();
+        let program = Program::compile(&tree);
+
+        let start = Instant::now();
+        let mut recursive = Vec::new();
+        for _ in 0..passes {
+            recursive = compute_cells(&history, |state| tree.compute(state));
+        }
+        let recursive_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut batched = Vec::new();
+        for _ in 0..passes {
+            batched = compute_rows(&history, &tree);
+        }
+        let batched_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut compiled = Vec::new();
+        for _ in 0..passes {
+            let mut registers = program.registers();
+            compiled = compute_cells(&history, |state| program.run(&mut registers, state));
+        }
+        let compiled_time = start.elapsed();
+
+        if recursive != batched {
+            return Err(format_err!(
+                "Tree {} computed differently in batches: {:?}",
+                tree_index,
+                tree
+            ));
+        }
+
+        if recursive != compiled {
+            return Err(format_err!(
+                "Tree {} computed differently once compiled: {:?}",
+                tree_index,
+                tree
+            ));
+        }
+
+        info!(
+            "Tree {}: {} nodes, {} instructions, recursive {:?}, batched {:?} ({:.2}x), compiled {:?} ({:.2}x)",
+            tree_index,
+            tree.node_count(),
+            program.instruction_count(),
+            recursive_time / passes as u32,
+            batched_time / passes as u32,
+            speedup(recursive_time, batched_time),
+            compiled_time / passes as u32,
+            speedup(recursive_time, compiled_time)
+        );
+
+        recursive_total += recursive_time;
+        batched_total += batched_time;
+        compiled_total += compiled_time;
+    }
+
+    info!(
+        "Total over {} trees: recursive {:?}, batched {:?} ({:.2}x), compiled {:?} ({:.2}x)",
+        trees,
+        recursive_total,
+        batched_total,
+        speedup(recursive_total, batched_total),
+        compiled_total,
+        speedup(recursive_total, compiled_total)
+    );
+
+    Ok(())
+}
+
+/// Computes a color for every cell on a single thread, at the coordinates the simulation would use
+fn compute_cells<F>(history: &History, mut compute: F) -> Vec<ByteColor>
+where
+    F: FnMut(UpdateState) -> FloatColor,
+{
+    let mut colors = Vec::with_capacity(CONSTS.cell_array_width * CONSTS.cell_array_height);
+
+    for y in 0..CONSTS.cell_array_height {
+        for coordinate_set in row(y) {
+            colors.push(ByteColor::from(black_box(compute(UpdateState {
+                coordinate_set,
+                history,
+            }))));
+        }
+    }
+
+    colors
+}
+
+/// Computes a color for every cell like [compute_cells], but a row at a time
+fn compute_rows(history: &History, tree: &FloatColorNodes) -> Vec<ByteColor> {
+    let mut colors = Vec::with_capacity(CONSTS.cell_array_width * CONSTS.cell_array_height);
+    let mut row_colors = Vec::with_capacity(CONSTS.cell_array_width);
+
+    for y in 0..CONSTS.cell_array_height {
+        row_colors.clear();
+        tree.compute_batch(&row(y), history, &mut row_colors);
+        colors.extend(
+            black_box(&row_colors)
+                .iter()
+                .map(|&color| ByteColor::from(color)),
+        );
+    }
+
+    colors
+}
+
+/// The coordinates of every cell in a row
+fn row(y: usize) -> Vec<CoordinateSet> {
+    (0..CONSTS.cell_array_width)
+        .map(|x| CoordinateSet {
+            x: UNFloat::new(x as f32 / CONSTS.cell_array_width as f32).to_signed(),
+            y: UNFloat::new(y as f32 / CONSTS.cell_array_height as f32).to_signed(),
+            t: 0.0,
+        })
+        .collect()
+}
+
+fn speedup(recursive: Duration, compiled: Duration) -> f64 {
+    recursive.as_secs_f64() / compiled.as_secs_f64()
+}
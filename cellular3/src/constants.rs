@@ -1,16 +1,27 @@
-use std::fs;
-
 use lazy_static::lazy_static;
 use serde::Deserialize;
 
+use crate::metric::MutationRule;
+
 lazy_static! {
-    pub static ref CONSTS: Constants = serde_yaml::from_str(
-        &fs::read_to_string("constants.yml").unwrap_or_else(|_e| panic!(
+    pub static ref CONSTS: Constants = serde_yaml::from_str(&read_constants())
+        .unwrap_or_else(|e| panic!("Failed to parse constants.yml: {}", e));
+}
+
+#[cfg(not(test))]
+fn read_constants() -> String {
+    std::fs::read_to_string("constants.yml").unwrap_or_else(|_e| {
+        panic!(
             "Couldn't find constants.yml in {}",
             std::env::current_dir().unwrap().to_string_lossy()
-        ))
-    )
-    .unwrap_or_else(|e| panic!("Failed to parse constants.yml: {}", e));
+        )
+    })
+}
+
+/// Tests run with constants of their own, so they pass on a fresh clone and don't depend on anyone's local settings
+#[cfg(test)]
+fn read_constants() -> String {
+    String::from(include_str!("../constants.test.yml"))
 }
 
 #[derive(Deserialize)]
@@ -35,17 +46,18 @@ pub struct Constants {
     pub noise_y_scale_minimum: f64,
     pub noise_t_scale_minimum: f64,
 
-    pub activity_value_upper_bound: f64,
-    pub activity_value_lower_bound: f64,
-    pub alpha_value_upper_bound: f64,
-    pub alpha_value_lower_bound: f64,
-    pub local_similarity_upper_bound: f64,
-    pub local_similarity_lower_bound: f64,
-    pub global_similarity_upper_bound: f64,
-    pub global_similarity_lower_bound: f64,
+    //the trees are mutated whenever a metric breaks its rule
+    pub mutation_rules: Vec<MutationRule>,
+    //the cell array is reseeded whenever a metric breaks one of these, usually because the cells have died out
+    pub reseed_rules: Vec<MutationRule>,
+    //how many genomes from before earlier mutations are kept to step back through
+    pub genome_undo_length: usize,
 
     pub image_path: String,
 
+    //a YAML file changing how often each kind of node is generated, see profile.rs
+    pub grammar_profile: Option<String>,
+
     //primitive consts
     pub byte_max_value: u64,
     pub byte_possible_values: u64,
@@ -71,4 +83,7 @@ pub struct Constants {
 
     pub min_branch_depth: usize,
     pub max_branch_depth: usize,
+
+    //Most nodes to generate in each root tree, after which only leaves are generated
+    pub tree_node_budget: usize,
 }
@@ -2,5 +2,10 @@ pub mod colors;
 pub mod continuous;
 pub mod discrete;
 pub mod image;
+pub mod kernels;
+pub mod neighbourhoods;
 pub mod noisefunctions;
 pub mod points;
+pub mod reaction_diffusion;
+pub mod reseeders;
+pub mod rules;
@@ -1,6 +1,7 @@
-use mutagen::{Generatable, Mutatable};
+use mutagen::{impl_leaf_crossover, impl_leaf_updatable, Generatable, Mutatable, Traversable};
 use palette::rgb::Rgb;
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::datatype::{continuous::*, discrete::*};
 
@@ -10,7 +11,7 @@ pub fn get_average(c: FloatColor) -> f32 {
     (c.r + c.b + c.g) / 3.0
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NibbleColor {
     pub r: Nibble,
     pub g: Nibble,
@@ -18,7 +19,7 @@ pub struct NibbleColor {
     pub a: Nibble,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ByteColor {
     pub r: u8,
     pub g: u8,
@@ -54,6 +55,18 @@ impl Mutatable for ByteColor {
     }
 }
 
+impl Traversable for ByteColor {
+    fn data_value(&self) -> Option<String> {
+        Some(format!(
+            "#{:02x}{:02x}{:02x}{:02x}",
+            self.r, self.g, self.b, self.a
+        ))
+    }
+}
+
+impl_leaf_updatable!(ByteColor);
+impl_leaf_crossover!(ByteColor);
+
 impl From<image::Rgba<u8>> for ByteColor {
     fn from(c: image::Rgba<u8>) -> Self {
         Self {
@@ -140,7 +153,7 @@ pub fn get_hue_unfloat(c: FloatColor) -> UNFloat {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BitColor {
     Black,
     Red,
@@ -333,6 +346,15 @@ impl Mutatable for BitColor {
     }
 }
 
+impl Traversable for BitColor {
+    fn data_value(&self) -> Option<String> {
+        Some(format!("{:?}", self))
+    }
+}
+
+impl_leaf_updatable!(BitColor);
+impl_leaf_crossover!(BitColor);
+
 impl From<ByteColor> for BitColor {
     fn from(other: ByteColor) -> Self {
         Self::from_components([other.r > 127, other.g > 127, other.b > 127])
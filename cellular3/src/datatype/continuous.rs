@@ -1,12 +1,14 @@
-use mutagen::{Generatable, Mutatable};
-use std::f32::consts::PI;
+use mutagen::{impl_leaf_crossover, impl_leaf_updatable, Generatable, Mutatable, Traversable};
+use std::{convert::TryFrom, f32::consts::PI};
 
-use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{datatype::discrete::*, util::*};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Generatable, Mutatable, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(try_from = "f32", into = "f32")]
 pub struct UNFloat {
+    #[mutagen(range = 0.0..1.0)]
     value: f32,
 }
 
@@ -16,12 +18,7 @@ impl UNFloat {
     }
 
     pub fn new(value: f32) -> Self {
-        assert!(
-            value >= 0.0 && value <= 1.0,
-            "Invalid UNFloat value: {}",
-            value
-        );
-        Self::new_unchecked(value)
+        Self::try_from(value).unwrap_or_else(|error| panic!("{}", error))
     }
 
     pub fn new_from_range(value: f32, min: f32, max: f32) -> Self {
@@ -46,20 +43,37 @@ impl UNFloat {
     }
 }
 
-impl Generatable for UNFloat {
-    fn generate_rng<R: Rng + ?Sized>(rng: &mut R, _state: mutagen::State) -> Self {
-        Self::new_unchecked(rng.gen_range(0.0, 1.0))
+impl TryFrom<f32> for UNFloat {
+    type Error = String;
+
+    fn try_from(value: f32) -> Result<Self, Self::Error> {
+        if (0.0..=1.0).contains(&value) {
+            Ok(Self::new_unchecked(value))
+        } else {
+            Err(format!("Invalid UNFloat value: {}", value))
+        }
+    }
+}
+
+impl From<UNFloat> for f32 {
+    fn from(value: UNFloat) -> Self {
+        value.into_inner()
     }
 }
 
-impl Mutatable for UNFloat {
-    fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        *self = Self::generate_rng(rng, state);
+impl Traversable for UNFloat {
+    fn data_value(&self) -> Option<String> {
+        Some(self.into_inner().to_string())
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+impl_leaf_updatable!(UNFloat);
+impl_leaf_crossover!(UNFloat);
+
+#[derive(Generatable, Mutatable, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(try_from = "f32", into = "f32")]
 pub struct SNFloat {
+    #[mutagen(range = -1.0..1.0)]
     value: f32,
 }
 
@@ -69,13 +83,7 @@ impl SNFloat {
     }
 
     pub fn new(value: f32) -> Self {
-        assert!(
-            value >= -1.0 && value <= 1.0,
-            "Invalid SNFloat value: {}",
-            value
-        );
-
-        Self::new_unchecked(value)
+        Self::try_from(value).unwrap_or_else(|error| panic!("{}", error))
     }
 
     pub fn new_from_range(value: f32, min: f32, max: f32) -> Self {
@@ -112,20 +120,37 @@ impl SNFloat {
     }
 }
 
-impl Generatable for SNFloat {
-    fn generate_rng<R: Rng + ?Sized>(rng: &mut R, _state: mutagen::State) -> Self {
-        Self::new_unchecked(rng.gen_range(-1.0, 1.0))
+impl TryFrom<f32> for SNFloat {
+    type Error = String;
+
+    fn try_from(value: f32) -> Result<Self, Self::Error> {
+        if (-1.0..=1.0).contains(&value) {
+            Ok(Self::new_unchecked(value))
+        } else {
+            Err(format!("Invalid SNFloat value: {}", value))
+        }
+    }
+}
+
+impl From<SNFloat> for f32 {
+    fn from(value: SNFloat) -> Self {
+        value.into_inner()
     }
 }
 
-impl Mutatable for SNFloat {
-    fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        *self = Self::generate_rng(rng, state);
+impl Traversable for SNFloat {
+    fn data_value(&self) -> Option<String> {
+        Some(self.into_inner().to_string())
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+impl_leaf_updatable!(SNFloat);
+impl_leaf_crossover!(SNFloat);
+
+#[derive(Generatable, Mutatable, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(try_from = "f32", into = "f32")]
 pub struct Angle {
+    #[mutagen(range = 0.0..2.0 * PI)]
     value: f32,
 }
 
@@ -136,6 +161,12 @@ impl Angle {
 
     pub fn new(value: f32) -> Self {
         let normalised = value - 2.0 * PI * (value / (2.0 * PI)).floor();
+        // Tiny negative angles round up to a full turn, which is the same as no turn at all
+        let normalised = if normalised >= 2.0 * PI {
+            0.0
+        } else {
+            normalised
+        };
 
         debug_assert!(
             normalised >= 0.0 && normalised < 2.0 * PI,
@@ -164,21 +195,38 @@ impl Angle {
     }
 }
 
-impl Generatable for Angle {
-    fn generate_rng<R: Rng + ?Sized>(rng: &mut R, _state: mutagen::State) -> Self {
-        Angle::new_unchecked(rng.gen_range(0.0, 2.0 * PI))
+// Any finite angle is normalised to a single turn, the same as Angle::new does
+impl TryFrom<f32> for Angle {
+    type Error = String;
+
+    fn try_from(value: f32) -> Result<Self, Self::Error> {
+        if value.is_finite() {
+            Ok(Self::new(value))
+        } else {
+            Err(format!("Invalid Angle value: {}", value))
+        }
+    }
+}
+
+impl From<Angle> for f32 {
+    fn from(value: Angle) -> Self {
+        value.into_inner()
     }
 }
 
-impl Mutatable for Angle {
-    fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        *self = Self::generate_rng(rng, state);
+impl Traversable for Angle {
+    fn data_value(&self) -> Option<String> {
+        Some(self.into_inner().to_string())
     }
 }
 
+impl_leaf_updatable!(Angle);
+impl_leaf_crossover!(Angle);
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{dsl, node::continuous_nodes::UNFloatNodes};
 
     #[test]
     fn test_angles() {
@@ -186,4 +234,21 @@ mod tests {
             Angle::new(i as f32);
         }
     }
+
+    #[test]
+    fn test_deserialize_checks_range() {
+        let value: UNFloat = serde_yaml::from_str("0.25").unwrap();
+        assert_eq!(value.into_inner(), 0.25);
+        assert!(serde_yaml::from_str::<UNFloat>("3.0").is_err());
+        assert!(serde_yaml::from_str::<UNFloat>("-0.5").is_err());
+        assert!(serde_yaml::from_str::<SNFloat>("-1.5").is_err());
+        assert!(serde_yaml::from_str::<SNFloat>(".nan").is_err());
+        assert!(serde_yaml::from_str::<Angle>(".inf").is_err());
+
+        let angle: Angle = serde_yaml::from_str("-1.0").unwrap();
+        assert!((angle.into_inner() - (2.0 * PI - 1.0)).abs() < 1e-6);
+
+        // Trees written by hand go through the same checks
+        assert!(dsl::from_str::<UNFloatNodes>("Constant(value: 3.0)").is_err());
+    }
 }
@@ -1,11 +1,13 @@
-use std::num::Wrapping;
+use std::{convert::TryFrom, num::Wrapping};
 
-use mutagen::{Generatable, Mutatable};
+use mutagen::{impl_leaf_crossover, impl_leaf_updatable, Generatable, Mutatable, Traversable};
 
 use crate::constants::*;
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Generatable, Mutatable, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Boolean {
     pub value: bool,
 }
@@ -16,20 +18,19 @@ impl Boolean {
     }
 }
 
-impl Generatable for Boolean {
-    fn generate_rng<R: Rng + ?Sized>(rng: &mut R, _state: mutagen::State) -> Self {
-        Boolean { value: rng.gen() }
+impl Traversable for Boolean {
+    fn data_value(&self) -> Option<String> {
+        Some(self.into_inner().to_string())
     }
 }
 
-impl Mutatable for Boolean {
-    fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        *self = Self::generate_rng(rng, state);
-    }
-}
+impl_leaf_updatable!(Boolean);
+impl_leaf_crossover!(Boolean);
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Generatable, Mutatable, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
 pub struct Nibble {
+    #[mutagen(range = 0..CONSTS.nibble_possible_values)]
     pub value: u8,
 }
 
@@ -69,19 +70,36 @@ impl Nibble {
     }
 }
 
-impl Generatable for Nibble {
-    fn generate_rng<R: Rng + ?Sized>(rng: &mut R, _state: mutagen::State) -> Self {
-        Nibble::new(rng.gen())
+// Unlike Nibble::new, which wraps around, anything too big is refused
+impl TryFrom<u8> for Nibble {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        if value < CONSTS.nibble_possible_values {
+            Ok(Self { value })
+        } else {
+            Err(format!("Invalid Nibble value: {}", value))
+        }
     }
 }
 
-impl Mutatable for Nibble {
-    fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        *self = Self::generate_rng(rng, state);
+impl From<Nibble> for u8 {
+    fn from(value: Nibble) -> Self {
+        value.into_inner()
+    }
+}
+
+impl Traversable for Nibble {
+    fn data_value(&self) -> Option<String> {
+        Some(self.into_inner().to_string())
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+impl_leaf_updatable!(Nibble);
+impl_leaf_crossover!(Nibble);
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Byte {
     pub value: Wrapping<u8>,
 }
@@ -134,7 +152,17 @@ impl Mutatable for Byte {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Traversable for Byte {
+    fn data_value(&self) -> Option<String> {
+        Some(self.into_inner().to_string())
+    }
+}
+
+impl_leaf_updatable!(Byte);
+impl_leaf_crossover!(Byte);
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct UInt {
     pub value: Wrapping<u32>,
 }
@@ -187,7 +215,17 @@ impl Mutatable for UInt {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Traversable for UInt {
+    fn data_value(&self) -> Option<String> {
+        Some(self.into_inner().to_string())
+    }
+}
+
+impl_leaf_updatable!(UInt);
+impl_leaf_crossover!(UInt);
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct SInt {
     pub value: Wrapping<i32>,
 }
@@ -239,3 +277,28 @@ impl Mutatable for SInt {
         *self = Self::generate_rng(rng, state);
     }
 }
+
+impl Traversable for SInt {
+    fn data_value(&self) -> Option<String> {
+        Some(self.into_inner().to_string())
+    }
+}
+
+impl_leaf_updatable!(SInt);
+impl_leaf_crossover!(SInt);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_checks_nibble_range() {
+        let largest = CONSTS.nibble_possible_values - 1;
+
+        assert_eq!(
+            serde_yaml::from_str::<Nibble>(&largest.to_string()).unwrap(),
+            Nibble::new(largest)
+        );
+        assert!(serde_yaml::from_str::<Nibble>(&(largest + 1).to_string()).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    cell::RefCell,
     fmt::{self, Debug, Formatter},
     fs::File,
     io::{BufRead, BufReader, Cursor, Seek},
@@ -10,14 +11,17 @@ use failure::{format_err, Fallible};
 use image::{gif, imageops, AnimationDecoder, FilterType, ImageFormat, RgbaImage};
 use lazy_static::lazy_static;
 use log::{debug, error, warn};
-use mutagen::{Generatable, Mutatable};
+use mutagen::{impl_leaf_crossover, impl_leaf_updatable, Generatable, Mutatable, Traversable};
 use rand::prelude::*;
 use reqwest::blocking::Client as HttpClient;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{
     constants::*,
     datatype::{colors::ByteColor, continuous::*},
     preloader::{Generator, Preloader},
+    replay::{Replay, REPLAY},
+    updatestate::CoordinateSet,
     util::{self, DeterministicRng},
 };
 
@@ -27,6 +31,7 @@ lazy_static! {
     static ref ALL_IMAGES: Vec<PathBuf> = util::collect_filenames(&CONSTS.image_path);
     static ref FALLBACK_IMAGE: Image = Image::load(
         String::from("<FALLBACK>"),
+        ImageSource::Fallback,
         Cursor::new(FALLBACK_IMAGE_DATA),
         ImageFormat::PNG,
     )
@@ -37,9 +42,12 @@ lazy_static! {
 }
 
 thread_local! {
-    pub static IMAGE_PRELOADER: Preloader<Image> = Preloader::new(32, RandomImageLoader::new());
+    pub static IMAGE_PRELOADER: RefCell<Preloader<Image>> =
+        RefCell::new(Preloader::new(PRELOADED_IMAGES, default_image_loader()));
 }
 
+const PRELOADED_IMAGES: usize = 32;
+
 const FALLBACK_IMAGE_DATA: &[u8] =
     include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/fallback_image.png"));
 
@@ -72,17 +80,42 @@ impl Generator for RandomImageLoader {
     }
 }
 
-fn download_random_image(client: &mut HttpClient) -> Fallible<Image> {
-    let mut buf = Vec::new();
+/// Hands out the fallback image every time
+struct FallbackImageLoader;
+
+impl Generator for FallbackImageLoader {
+    type Output = Image;
+
+    fn generate(&mut self) -> Self::Output {
+        FALLBACK_IMAGE.clone()
+    }
+}
 
-    let mut response = client
-        .get(&format!(
+/// Where the preloader gets new images from, unless a test swaps in its own.
+/// Tests shouldn't depend on the image files on disk or on the network, so they only get the fallback image by default.
+fn default_image_loader() -> Box<dyn Generator<Output = Image> + Send> {
+    if cfg!(test) {
+        Box::new(FallbackImageLoader)
+    } else {
+        Box::new(RandomImageLoader::new())
+    }
+}
+
+fn download_random_image(client: &mut HttpClient) -> Fallible<Image> {
+    download_image(
+        client,
+        &format!(
             "https://picsum.photos/{}/{}",
             CONSTS.initial_window_width.floor() as usize,
             CONSTS.initial_window_height.floor() as usize,
-        ))
-        .send()?
-        .error_for_status()?;
+        ),
+    )
+}
+
+fn download_image(client: &mut HttpClient, url: &str) -> Fallible<Image> {
+    let mut buf = Vec::new();
+
+    let mut response = client.get(url).send()?.error_for_status()?;
 
     response.copy_to(&mut buf)?;
 
@@ -98,7 +131,10 @@ fn download_random_image(client: &mut HttpClient) -> Fallible<Image> {
 
     debug!("Downloaded image: {}", name);
 
-    Ok(Image::load(name, Cursor::new(&buf), format)?)
+    // Picsum redirects to a stable url for the specific image it picked, so that's the one to keep
+    let source = ImageSource::Url(url.as_str().to_owned());
+
+    Ok(Image::load(name, source, Cursor::new(&buf), format)?)
 }
 
 fn load_random_image_file<R: Rng + ?Sized>(rng: &mut R) -> Image {
@@ -123,17 +159,31 @@ pub struct Image(Arc<ImageData>);
 
 pub struct ImageData {
     name: String,
+    source: ImageSource,
     frames: Vec<RgbaImage>,
 }
 
+/// Where an image was loaded from, which is all that gets saved when an image is serialized
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImageSource {
+    File(PathBuf),
+    Url(String),
+    Fallback,
+}
+
 impl Image {
-    pub fn new(name: String, frames: Vec<RgbaImage>) -> Self {
-        Self(Arc::new(ImageData { name, frames }))
+    pub fn new(name: String, source: ImageSource, frames: Vec<RgbaImage>) -> Self {
+        Self(Arc::new(ImageData {
+            name,
+            source,
+            frames,
+        }))
     }
 
     pub fn load_file<P: AsRef<Path>>(path: P) -> image::ImageResult<Self> {
         Ok(Self::new(
             format!("{} (Local file)", path.as_ref().to_string_lossy()),
+            ImageSource::File(path.as_ref().to_owned()),
             load_frames(
                 BufReader::new(File::open(&path)?),
                 ImageFormat::from_path(&path)?,
@@ -143,10 +193,24 @@ impl Image {
 
     pub fn load<R: BufRead + Seek>(
         name: String,
+        source: ImageSource,
         reader: R,
         format: ImageFormat,
     ) -> image::ImageResult<Self> {
-        Ok(Self::new(name, load_frames(reader, format)?))
+        Ok(Self::new(name, source, load_frames(reader, format)?))
+    }
+
+    /// Reloads an image from wherever it originally came from
+    pub fn load_source(source: &ImageSource) -> Fallible<Self> {
+        match source {
+            ImageSource::File(path) => Ok(Self::load_file(path)?),
+            ImageSource::Url(url) => download_image(&mut HttpClient::new(), url),
+            ImageSource::Fallback => Ok(FALLBACK_IMAGE.clone()),
+        }
+    }
+
+    pub fn source(&self) -> &ImageSource {
+        &self.0.source
     }
 
     pub fn get_pixel_wrapped(&self, x: u32, y: u32, t: u32) -> ByteColor {
@@ -178,6 +242,39 @@ impl Image {
             t_value as u32,
         )
     }
+
+    /// Samples the image at a whole batch of coordinates like [Image::get_pixel_normalised],
+    /// only looking up a new frame when the tic changes between cells
+    pub fn get_pixels_normalised<'a>(
+        &'a self,
+        coordinate_sets: &'a [CoordinateSet],
+    ) -> impl Iterator<Item = ByteColor> + 'a {
+        let frame_count = self.0.frames.len();
+        let mut frame: Option<(usize, &RgbaImage)> = None;
+
+        coordinate_sets.iter().map(move |coordinate_set| {
+            let t_value = ((coordinate_set.t as usize % frame_count) + frame_count) % frame_count;
+
+            let image = match frame {
+                Some((t, image)) if t == t_value => image,
+                _ => {
+                    let image = &self.0.frames[t_value];
+                    frame = Some((t_value, image));
+                    image
+                }
+            };
+
+            let (image_width, image_height) = image.dimensions();
+
+            (*image.get_pixel(
+                (coordinate_set.x.to_unsigned().into_inner() * image_width as f32) as u32
+                    % image_width,
+                (coordinate_set.y.to_unsigned().into_inner() * image_height as f32) as u32
+                    % image_height,
+            ))
+            .into()
+        })
+    }
 }
 
 fn load_frames<R: BufRead + Seek>(
@@ -218,12 +315,37 @@ impl Debug for Image {
     }
 }
 
+impl Serialize for Image {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.source.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Image {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = ImageSource::deserialize(deserializer)?;
+
+        // A missing image shouldn't make the rest of a saved tree unusable
+        Ok(Self::load_source(&source).unwrap_or_else(|e| {
+            error!("Failed to load image {:?}: {}", source, e);
+            FALLBACK_IMAGE.clone()
+        }))
+    }
+}
+
 impl Generatable for Image {
     fn generate_rng<R: Rng + ?Sized>(_rng: &mut R, _state: mutagen::State) -> Self {
+        REPLAY.with(|r| next_image(&mut r.borrow_mut()))
+    }
+}
+
+/// Takes whichever image the preloader has ready, which depends on timing, so replays need to be told
+fn next_image(replay: &mut Replay) -> Image {
+    replay.image(|| {
         IMAGE_PRELOADER
-            .with(|p| p.try_get_next())
+            .with(|p| p.borrow().try_get_next())
             .unwrap_or_else(|| FALLBACK_IMAGE.clone())
-    }
+    })
 }
 
 impl Mutatable for Image {
@@ -231,3 +353,111 @@ impl Mutatable for Image {
         *self = Self::generate_rng(rng, state);
     }
 }
+
+impl Traversable for Image {
+    fn data_value(&self) -> Option<String> {
+        Some(self.0.name.clone())
+    }
+}
+
+impl_leaf_updatable!(Image);
+impl_leaf_crossover!(Image);
+
+// Shared with the replay test in simulation.rs, which needs images that only a replay can hand back
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{thread, time::Duration};
+
+    use super::*;
+
+    //Generating never waits for the preloader, so the tests keep trying until it has something ready
+    const ATTEMPTS: usize = 500;
+    const ATTEMPT_INTERVAL: Duration = Duration::from_millis(10);
+
+    /// Loads the fallback image from its file, so it's saved as a file rather than as the fallback
+    pub(crate) struct FileImageLoader;
+
+    impl Generator for FileImageLoader {
+        type Output = Image;
+
+        fn generate(&mut self) -> Self::Output {
+            Image::load_file(Path::new(env!("CARGO_MANIFEST_DIR")).join("fallback_image.png"))
+                .unwrap()
+        }
+    }
+
+    /// Makes up images that can't be loaded again, to show where an image came from
+    pub(crate) struct BlankImageLoader;
+
+    impl Generator for BlankImageLoader {
+        type Output = Image;
+
+        fn generate(&mut self) -> Self::Output {
+            Image::new(
+                String::from("Blank"),
+                ImageSource::Url(String::from("blank")),
+                vec![RgbaImage::new(1, 1)],
+            )
+        }
+    }
+
+    pub(crate) fn set_image_loader<G: Generator<Output = Image> + Send + 'static>(loader: G) {
+        IMAGE_PRELOADER.with(|p| *p.borrow_mut() = Preloader::new(PRELOADED_IMAGES, loader));
+    }
+
+    /// Every image handed out up to and including the first one that isn't the fallback
+    pub(crate) fn until_preloaded<F: FnMut() -> Image>(mut next: F) -> Vec<Image> {
+        let mut images = Vec::new();
+
+        for _ in 0..ATTEMPTS {
+            let image = next();
+            let preloaded = image.source() != &ImageSource::Fallback;
+            images.push(image);
+
+            if preloaded {
+                return images;
+            }
+
+            thread::sleep(ATTEMPT_INTERVAL);
+        }
+
+        panic!("The preloader never had an image ready");
+    }
+
+    #[test]
+    fn test_generate_takes_preloaded_images() {
+        set_image_loader(BlankImageLoader);
+
+        let images = until_preloaded(|| {
+            Image::generate_rng(&mut DeterministicRng::new(), mutagen::State::default())
+        });
+
+        assert_eq!(
+            images.last().unwrap().source(),
+            &ImageSource::Url(String::from("blank"))
+        );
+    }
+
+    #[test]
+    fn test_replay_images() {
+        let path = std::env::temp_dir().join(format!("image_replay_{}.log", std::process::id()));
+
+        set_image_loader(FileImageLoader);
+        let mut recording = Replay::record(&path, 0).unwrap();
+        let recorded: Vec<ImageSource> = until_preloaded(|| next_image(&mut recording))
+            .iter()
+            .map(|image| image.source().clone())
+            .collect();
+
+        // A replay hands back the same images in the same order, whatever the preloader has ready
+        set_image_loader(BlankImageLoader);
+        let (mut playing, _) = Replay::play(&path).unwrap();
+        let played: Vec<ImageSource> = recorded
+            .iter()
+            .map(|_| next_image(&mut playing).source().clone())
+            .collect();
+
+        assert_eq!(played, recorded);
+        assert!(matches!(recorded.last(), Some(ImageSource::File(_))));
+    }
+}
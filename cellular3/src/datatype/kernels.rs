@@ -0,0 +1,208 @@
+//! Kernels and growth functions for continuous cellular automata in the style of Lenia
+
+use std::convert::TryFrom;
+
+use mutagen::{impl_leaf_crossover, impl_leaf_updatable, Generatable, Mutatable, Traversable};
+use serde::{Deserialize, Serialize};
+
+use crate::datatype::continuous::*;
+
+//The work for each cell grows with the square of the radius, so it's kept small
+pub const MAX_KERNEL_RADIUS: usize = 12;
+pub const MAX_KERNEL_RINGS: usize = 4;
+
+/// Weights the cells within `radius` of a cell by distance, in concentric rings that each peak halfway across.
+/// The weights are normalised, so convolving with a kernel gives a weighted average of the cells around each one.
+#[derive(Generatable, Mutatable, Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "UncheckedKernel")]
+pub struct Kernel {
+    #[mutagen(range = 2..=MAX_KERNEL_RADIUS)]
+    pub radius: usize,
+    //The peak of each ring, from the innermost out
+    #[mutagen(range = 1..=MAX_KERNEL_RINGS)]
+    pub ring_weights: Vec<UNFloat>,
+}
+
+impl Kernel {
+    /// The offset and weight of every cell the kernel covers, leaving out the ones it gives no weight.
+    /// Empty if every ring has a weight of zero.
+    pub fn taps(&self) -> Vec<(isize, isize, f32)> {
+        let radius = self.radius as isize;
+        let rings = self.ring_weights.len() as f32;
+
+        let mut taps: Vec<(isize, isize, f32)> = (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .filter_map(|(dx, dy)| {
+                let distance = ((dx * dx + dy * dy) as f32).sqrt() / radius as f32;
+
+                if distance >= 1.0 {
+                    return None;
+                }
+
+                let ring = distance * rings;
+                let weight = self.ring_weights[ring as usize].into_inner() * bump(ring.fract());
+
+                if weight > 0.0 {
+                    Some((dx, dy, weight))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let total: f32 = taps.iter().map(|&(_, _, weight)| weight).sum();
+
+        for (_, _, weight) in &mut taps {
+            *weight /= total;
+        }
+
+        taps
+    }
+}
+
+/// A kernel as it's read from a file, before its radius and rings are checked.
+/// Named the same as the kernel itself, since the tree format checks names.
+#[derive(Deserialize)]
+#[serde(rename = "Kernel")]
+struct UncheckedKernel {
+    radius: usize,
+    ring_weights: Vec<UNFloat>,
+}
+
+impl TryFrom<UncheckedKernel> for Kernel {
+    type Error = String;
+
+    fn try_from(kernel: UncheckedKernel) -> Result<Self, Self::Error> {
+        let UncheckedKernel {
+            radius,
+            ring_weights,
+        } = kernel;
+
+        if !(2..=MAX_KERNEL_RADIUS).contains(&radius) {
+            return Err(format!("Invalid kernel radius: {}", radius));
+        }
+
+        // Every cell within the radius falls in a ring, so there has to be at least one
+        if !(1..=MAX_KERNEL_RINGS).contains(&ring_weights.len()) {
+            return Err(format!("Invalid kernel ring count: {}", ring_weights.len()));
+        }
+
+        Ok(Self {
+            radius,
+            ring_weights,
+        })
+    }
+}
+
+impl Traversable for Kernel {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("radius", &self.radius);
+        f("ring_weights", &self.ring_weights);
+    }
+}
+
+impl_leaf_updatable!(Kernel);
+impl_leaf_crossover!(Kernel);
+
+/// A smooth hump over 0 to 1, which is 0 at either end and 1 in the middle
+fn bump(x: f32) -> f32 {
+    if x <= 0.0 || x >= 1.0 {
+        0.0
+    } else {
+        (4.0 - 1.0 / (x * (1.0 - x))).exp()
+    }
+}
+
+/// How a cell grows or shrinks given the weighted average of the cells around it,
+/// which is fastest when the average is at `mu` and turns to shrinking more than about `2 * sigma` away from it
+#[derive(Generatable, Mutatable, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Growth {
+    #[mutagen(range = 0.05..0.5)]
+    pub mu: f32,
+    #[mutagen(range = 0.005..0.1)]
+    pub sigma: f32,
+    //How much of the growth is added each step
+    #[mutagen(range = 0.05..=0.5)]
+    pub dt: f32,
+}
+
+impl Growth {
+    /// The growth for an average of `potential`, from -1 to 1
+    pub fn growth(self, potential: f32) -> f32 {
+        2.0 * (-(potential - self.mu).powi(2) / (2.0 * self.sigma.powi(2))).exp() - 1.0
+    }
+
+    /// The value a cell grows to in one step, kept between 0 and 1
+    pub fn grow(self, value: f32, potential: f32) -> f32 {
+        (value + self.dt * self.growth(potential)).clamp(0.0, 1.0)
+    }
+}
+
+impl Traversable for Growth {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("mu", &self.mu);
+        f("sigma", &self.sigma);
+        f("dt", &self.dt);
+    }
+}
+
+impl_leaf_updatable!(Growth);
+impl_leaf_crossover!(Growth);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl;
+
+    #[test]
+    fn test_kernel_taps() {
+        let kernel = Kernel {
+            radius: 5,
+            ring_weights: vec![UNFloat::new(0.5), UNFloat::new(1.0)],
+        };
+
+        let taps = kernel.taps();
+        let total: f32 = taps.iter().map(|&(_, _, weight)| weight).sum();
+
+        assert!((total - 1.0).abs() < 1e-5);
+        assert!(taps
+            .iter()
+            .all(|&(dx, dy, _)| dx * dx + dy * dy < 25 && (dx, dy) != (0, 0)));
+
+        let weight = |dx, dy| {
+            taps.iter()
+                .find(|&&(x, y, _)| (x, y) == (dx, dy))
+                .map_or(0.0, |&(_, _, weight)| weight)
+        };
+
+        // The same distance in any direction gets the same weight, and the outer ring peaks higher
+        assert_eq!(weight(1, 1), weight(-1, 1));
+        assert_eq!(weight(0, 4), weight(4, 0));
+        assert!(weight(4, 0) > weight(1, 0));
+    }
+
+    #[test]
+    fn test_deserialize_checks_kernel() {
+        let kernel: Kernel = dsl::from_str("(radius: 3, ring_weights: [0.5, 1.0])").unwrap();
+        assert_eq!(kernel.radius, 3);
+        assert_eq!(kernel.ring_weights.len(), 2);
+
+        assert!(dsl::from_str::<Kernel>("(radius: 3, ring_weights: [])").is_err());
+        assert!(dsl::from_str::<Kernel>("(radius: 0, ring_weights: [1.0])").is_err());
+        assert!(serde_yaml::from_str::<Kernel>("radius: 3\nring_weights: []").is_err());
+    }
+
+    #[test]
+    fn test_growth() {
+        let growth = Growth {
+            mu: 0.15,
+            sigma: 0.015,
+            dt: 0.1,
+        };
+
+        assert_eq!(growth.growth(0.15), 1.0);
+        assert!(growth.growth(0.5) < -0.99);
+        assert_eq!(growth.grow(0.95, 0.15), 1.0);
+        assert_eq!(growth.grow(0.05, 0.5), 0.0);
+    }
+}
@@ -1,4 +1,56 @@
-pub struct Neighbourhood {
+use mutagen::{impl_leaf_crossover, impl_leaf_updatable, Generatable, Mutatable, Traversable};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Which cells around a cell count as its neighbours
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Neighbourhood {
+    /// The 8 cells sharing an edge or a corner
     Moore,
+    /// The 4 cells sharing an edge
     VonNeumann,
-}
\ No newline at end of file
+}
+
+impl Neighbourhood {
+    /// The (x, y) offset of each neighbour from the cell
+    pub fn offsets(self) -> &'static [(isize, isize)] {
+        match self {
+            Neighbourhood::Moore => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+            Neighbourhood::VonNeumann => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+        }
+    }
+}
+
+impl Generatable for Neighbourhood {
+    fn generate_rng<R: Rng + ?Sized>(rng: &mut R, _state: mutagen::State) -> Self {
+        if rng.gen() {
+            Neighbourhood::Moore
+        } else {
+            Neighbourhood::VonNeumann
+        }
+    }
+}
+
+impl Mutatable for Neighbourhood {
+    fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
+        *self = Self::generate_rng(rng, state);
+    }
+}
+
+impl Traversable for Neighbourhood {
+    fn data_value(&self) -> Option<String> {
+        Some(format!("{:?}", self))
+    }
+}
+
+impl_leaf_updatable!(Neighbourhood);
+impl_leaf_crossover!(Neighbourhood);
@@ -1,16 +1,18 @@
 use crate::datatype::continuous::UNFloat;
-use mutagen::{Generatable, Mutatable};
+use mutagen::{impl_leaf_crossover, impl_leaf_updatable, Generatable, Mutatable, Traversable};
 use noise::{
     BasicMulti, Billow, Checkerboard, Fbm, HybridMulti, OpenSimplex, RangeFunction, RidgedMulti,
     Seedable, SuperSimplex, Value, Worley,
 };
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BasicMultiFractalNoise {
     pub x_scale: UNFloat,
     pub y_scale: UNFloat,
     pub t_scale: UNFloat,
+    #[serde(rename = "seed", with = "seeded_noise")]
     pub noise: BasicMulti,
 }
 impl Generatable for BasicMultiFractalNoise {
@@ -25,7 +27,7 @@ impl Generatable for BasicMultiFractalNoise {
 }
 impl Mutatable for BasicMultiFractalNoise {
     fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        match random::<u32>() % 4 {
+        match rng.gen_range(0, 4) {
             0 => {
                 self.x_scale = UNFloat::generate_rng(rng, state);
             }
@@ -43,11 +45,23 @@ impl Mutatable for BasicMultiFractalNoise {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Traversable for BasicMultiFractalNoise {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("x_scale", &self.x_scale);
+        f("y_scale", &self.y_scale);
+        f("t_scale", &self.t_scale);
+    }
+}
+
+impl_leaf_updatable!(BasicMultiFractalNoise);
+impl_leaf_crossover!(BasicMultiFractalNoise);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BillowNoise {
     pub x_scale: UNFloat,
     pub y_scale: UNFloat,
     pub t_scale: UNFloat,
+    #[serde(rename = "seed", with = "seeded_noise")]
     pub noise: Billow,
 }
 impl Generatable for BillowNoise {
@@ -62,7 +76,7 @@ impl Generatable for BillowNoise {
 }
 impl Mutatable for BillowNoise {
     fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        match random::<u32>() % 4 {
+        match rng.gen_range(0, 4) {
             0 => {
                 self.x_scale = UNFloat::generate_rng(rng, state);
             }
@@ -80,11 +94,23 @@ impl Mutatable for BillowNoise {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Traversable for BillowNoise {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("x_scale", &self.x_scale);
+        f("y_scale", &self.y_scale);
+        f("t_scale", &self.t_scale);
+    }
+}
+
+impl_leaf_updatable!(BillowNoise);
+impl_leaf_crossover!(BillowNoise);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CheckerboardNoise {
     pub x_scale: UNFloat,
     pub y_scale: UNFloat,
     pub t_scale: UNFloat,
+    #[serde(with = "checkerboard_noise")]
     pub noise: Checkerboard,
 }
 impl Generatable for CheckerboardNoise {
@@ -99,7 +125,7 @@ impl Generatable for CheckerboardNoise {
 }
 impl Mutatable for CheckerboardNoise {
     fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        match random::<u32>() % 5 {
+        match rng.gen_range(0, 5) {
             0 => {
                 self.x_scale = UNFloat::generate_rng(rng, state);
             }
@@ -120,11 +146,23 @@ impl Mutatable for CheckerboardNoise {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Traversable for CheckerboardNoise {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("x_scale", &self.x_scale);
+        f("y_scale", &self.y_scale);
+        f("t_scale", &self.t_scale);
+    }
+}
+
+impl_leaf_updatable!(CheckerboardNoise);
+impl_leaf_crossover!(CheckerboardNoise);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FractalBrownianNoise {
     pub x_scale: UNFloat,
     pub y_scale: UNFloat,
     pub t_scale: UNFloat,
+    #[serde(rename = "seed", with = "seeded_noise")]
     pub noise: Fbm,
 }
 impl Generatable for FractalBrownianNoise {
@@ -139,7 +177,7 @@ impl Generatable for FractalBrownianNoise {
 }
 impl Mutatable for FractalBrownianNoise {
     fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        match random::<u32>() % 4 {
+        match rng.gen_range(0, 4) {
             0 => {
                 self.x_scale = UNFloat::generate_rng(rng, state);
             }
@@ -157,11 +195,23 @@ impl Mutatable for FractalBrownianNoise {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Traversable for FractalBrownianNoise {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("x_scale", &self.x_scale);
+        f("y_scale", &self.y_scale);
+        f("t_scale", &self.t_scale);
+    }
+}
+
+impl_leaf_updatable!(FractalBrownianNoise);
+impl_leaf_crossover!(FractalBrownianNoise);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct HybridMultiFractalNoise {
     pub x_scale: UNFloat,
     pub y_scale: UNFloat,
     pub t_scale: UNFloat,
+    #[serde(rename = "seed", with = "seeded_noise")]
     pub noise: HybridMulti,
 }
 impl Generatable for HybridMultiFractalNoise {
@@ -176,7 +226,7 @@ impl Generatable for HybridMultiFractalNoise {
 }
 impl Mutatable for HybridMultiFractalNoise {
     fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        match random::<u32>() % 4 {
+        match rng.gen_range(0, 4) {
             0 => {
                 self.x_scale = UNFloat::generate_rng(rng, state);
             }
@@ -194,11 +244,23 @@ impl Mutatable for HybridMultiFractalNoise {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Traversable for HybridMultiFractalNoise {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("x_scale", &self.x_scale);
+        f("y_scale", &self.y_scale);
+        f("t_scale", &self.t_scale);
+    }
+}
+
+impl_leaf_updatable!(HybridMultiFractalNoise);
+impl_leaf_crossover!(HybridMultiFractalNoise);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OpenSimplexNoise {
     pub x_scale: UNFloat,
     pub y_scale: UNFloat,
     pub t_scale: UNFloat,
+    #[serde(rename = "seed", with = "seeded_noise")]
     pub noise: OpenSimplex,
 }
 impl Generatable for OpenSimplexNoise {
@@ -213,7 +275,7 @@ impl Generatable for OpenSimplexNoise {
 }
 impl Mutatable for OpenSimplexNoise {
     fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        match random::<u32>() % 4 {
+        match rng.gen_range(0, 4) {
             0 => {
                 self.x_scale = UNFloat::generate_rng(rng, state);
             }
@@ -231,11 +293,23 @@ impl Mutatable for OpenSimplexNoise {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Traversable for OpenSimplexNoise {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("x_scale", &self.x_scale);
+        f("y_scale", &self.y_scale);
+        f("t_scale", &self.t_scale);
+    }
+}
+
+impl_leaf_updatable!(OpenSimplexNoise);
+impl_leaf_crossover!(OpenSimplexNoise);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RidgedMultiFractalNoise {
     pub x_scale: UNFloat,
     pub y_scale: UNFloat,
     pub t_scale: UNFloat,
+    #[serde(with = "ridged_multi_noise")]
     pub noise: RidgedMulti,
 }
 impl Generatable for RidgedMultiFractalNoise {
@@ -250,7 +324,7 @@ impl Generatable for RidgedMultiFractalNoise {
 }
 impl Mutatable for RidgedMultiFractalNoise {
     fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        match random::<u32>() % 5 {
+        match rng.gen_range(0, 5) {
             0 => {
                 self.x_scale = UNFloat::generate_rng(rng, state);
             }
@@ -271,11 +345,23 @@ impl Mutatable for RidgedMultiFractalNoise {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Traversable for RidgedMultiFractalNoise {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("x_scale", &self.x_scale);
+        f("y_scale", &self.y_scale);
+        f("t_scale", &self.t_scale);
+    }
+}
+
+impl_leaf_updatable!(RidgedMultiFractalNoise);
+impl_leaf_crossover!(RidgedMultiFractalNoise);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SuperSimplexNoise {
     pub x_scale: UNFloat,
     pub y_scale: UNFloat,
     pub t_scale: UNFloat,
+    #[serde(rename = "seed", with = "seeded_noise")]
     pub noise: SuperSimplex,
 }
 impl Generatable for SuperSimplexNoise {
@@ -290,7 +376,7 @@ impl Generatable for SuperSimplexNoise {
 }
 impl Mutatable for SuperSimplexNoise {
     fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        match random::<u32>() % 4 {
+        match rng.gen_range(0, 4) {
             0 => {
                 self.x_scale = UNFloat::generate_rng(rng, state);
             }
@@ -308,11 +394,23 @@ impl Mutatable for SuperSimplexNoise {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Traversable for SuperSimplexNoise {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("x_scale", &self.x_scale);
+        f("y_scale", &self.y_scale);
+        f("t_scale", &self.t_scale);
+    }
+}
+
+impl_leaf_updatable!(SuperSimplexNoise);
+impl_leaf_crossover!(SuperSimplexNoise);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ValueNoise {
     pub x_scale: UNFloat,
     pub y_scale: UNFloat,
     pub t_scale: UNFloat,
+    #[serde(rename = "seed", with = "seeded_noise")]
     pub noise: Value,
 }
 impl Generatable for ValueNoise {
@@ -327,7 +425,7 @@ impl Generatable for ValueNoise {
 }
 impl Mutatable for ValueNoise {
     fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        match random::<u32>() % 4 {
+        match rng.gen_range(0, 4) {
             0 => {
                 self.x_scale = UNFloat::generate_rng(rng, state);
             }
@@ -345,11 +443,23 @@ impl Mutatable for ValueNoise {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Traversable for ValueNoise {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("x_scale", &self.x_scale);
+        f("y_scale", &self.y_scale);
+        f("t_scale", &self.t_scale);
+    }
+}
+
+impl_leaf_updatable!(ValueNoise);
+impl_leaf_crossover!(ValueNoise);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WorleyNoise {
     pub x_scale: UNFloat,
     pub y_scale: UNFloat,
     pub t_scale: UNFloat,
+    #[serde(with = "worley_noise")]
     pub noise: Worley,
 }
 
@@ -377,7 +487,7 @@ impl Generatable for WorleyNoise {
 
 impl Mutatable for WorleyNoise {
     fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
-        match random::<u32>() % 5 {
+        match rng.gen_range(0, 5) {
             0 => {
                 self.x_scale = UNFloat::generate_rng(rng, state);
             }
@@ -421,3 +531,170 @@ impl Mutatable for WorleyNoise {
         };
     }
 }
+
+impl Traversable for WorleyNoise {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("x_scale", &self.x_scale);
+        f("y_scale", &self.y_scale);
+        f("t_scale", &self.t_scale);
+    }
+}
+
+impl_leaf_updatable!(WorleyNoise);
+impl_leaf_crossover!(WorleyNoise);
+
+/// Noise functions don't implement serde themselves, so they are stored as the parameters used to build them
+mod seeded_noise {
+    use noise::Seedable;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<N, S>(noise: &N, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        N: Seedable,
+        S: Serializer,
+    {
+        noise.seed().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, N, D>(deserializer: D) -> Result<N, D::Error>
+    where
+        N: Seedable + Default,
+        D: Deserializer<'de>,
+    {
+        Ok(N::default().set_seed(u32::deserialize(deserializer)?))
+    }
+}
+
+mod checkerboard_noise {
+    use noise::Checkerboard;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Params {
+        //The power of two the squares are sized to, as set_size takes it, rather than the size it stores
+        size: usize,
+    }
+
+    pub fn serialize<S: Serializer>(
+        noise: &Checkerboard,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        Params {
+            size: noise.size.trailing_zeros() as usize,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Checkerboard, D::Error> {
+        let params = Params::deserialize(deserializer)?;
+        Ok(Checkerboard::new().set_size(params.size))
+    }
+}
+
+mod ridged_multi_noise {
+    use noise::{RidgedMulti, Seedable};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Params {
+        seed: u32,
+        attenuation: f64,
+    }
+
+    pub fn serialize<S: Serializer>(noise: &RidgedMulti, serializer: S) -> Result<S::Ok, S::Error> {
+        Params {
+            seed: noise.seed(),
+            attenuation: noise.attenuation,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<RidgedMulti, D::Error> {
+        let params = Params::deserialize(deserializer)?;
+        Ok(RidgedMulti::new()
+            .set_seed(params.seed)
+            .set_attenuation(params.attenuation))
+    }
+}
+
+mod worley_noise {
+    use noise::{RangeFunction, Seedable, Worley};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    enum Range {
+        Euclidean,
+        EuclideanSquared,
+        Manhattan,
+        Chebyshev,
+        Quadratic,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Params {
+        seed: u32,
+        range_function: Range,
+        enable_range: bool,
+        displacement: f64,
+    }
+
+    pub fn serialize<S: Serializer>(noise: &Worley, serializer: S) -> Result<S::Ok, S::Error> {
+        Params {
+            seed: noise.seed(),
+            range_function: match noise.range_function {
+                RangeFunction::Euclidean => Range::Euclidean,
+                RangeFunction::EuclideanSquared => Range::EuclideanSquared,
+                RangeFunction::Manhattan => Range::Manhattan,
+                RangeFunction::Chebyshev => Range::Chebyshev,
+                RangeFunction::Quadratic => Range::Quadratic,
+            },
+            enable_range: noise.enable_range,
+            displacement: noise.displacement,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Worley, D::Error> {
+        let params = Params::deserialize(deserializer)?;
+        Ok(Worley::new()
+            .enable_range(params.enable_range)
+            .set_displacement(params.displacement)
+            .set_range_function(match params.range_function {
+                Range::Euclidean => RangeFunction::Euclidean,
+                Range::EuclideanSquared => RangeFunction::EuclideanSquared,
+                Range::Manhattan => RangeFunction::Manhattan,
+                Range::Chebyshev => RangeFunction::Chebyshev,
+                Range::Quadratic => RangeFunction::Quadratic,
+            })
+            .set_seed(params.seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use noise::NoiseFn;
+
+    #[test]
+    fn test_checkerboard_round_trip() {
+        for size in 0..4 {
+            let saved = CheckerboardNoise {
+                x_scale: UNFloat::new(0.5),
+                y_scale: UNFloat::new(0.5),
+                t_scale: UNFloat::new(0.5),
+                noise: Checkerboard::new().set_size(size),
+            };
+            let loaded: CheckerboardNoise =
+                serde_json::from_str(&serde_json::to_string(&saved).unwrap()).unwrap();
+
+            for i in 0..32 {
+                let point = [i as f64 * 0.75, i as f64 * 0.5, 0.25];
+                assert_eq!(loaded.noise.get(point), saved.noise.get(point));
+            }
+        }
+    }
+}
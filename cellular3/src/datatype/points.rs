@@ -1,12 +1,14 @@
 use crate::{
     datatype::{continuous::*},
 };
-use mutagen::{Generatable, Mutatable};
+use mutagen::{impl_leaf_crossover, impl_leaf_updatable, Generatable, Mutatable, Traversable};
 
 use nalgebra::*;
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct SNPoint {
     value: Point2<f32>,
 }
@@ -65,3 +67,13 @@ impl Mutatable for SNPoint {
         *self = Self::generate_rng(rng, state);
     }
 }
+
+impl Traversable for SNPoint {
+    fn data_value(&self) -> Option<String> {
+        let point = self.into_inner();
+        Some(format!("({}, {})", point.x, point.y))
+    }
+}
+
+impl_leaf_updatable!(SNPoint);
+impl_leaf_crossover!(SNPoint);
@@ -0,0 +1,213 @@
+//! The Gray-Scott model of reaction-diffusion, where a chemical v feeds on another chemical u as both spread out
+
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use mutagen::{impl_leaf_updatable, Crossover, Generatable, Mutatable, Traversable};
+use ndarray::{Array2, Zip};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// The rates of a Gray-Scott reaction.
+/// Most of them either die out or fill everything, so the ranges stay around the ones that give spots, stripes and waves.
+#[derive(
+    Generatable, Mutatable, Crossover, Serialize, Deserialize, Debug, Clone, Copy, PartialEq,
+)]
+pub struct Rates {
+    //How fast u is topped back up towards 1
+    #[mutagen(range = 0.01..0.08)]
+    pub feed: f32,
+    //How fast v is taken away, on top of the feed rate
+    #[mutagen(range = 0.04..0.07)]
+    pub kill: f32,
+    #[mutagen(range = 0.6..=1.0)]
+    pub diffusion_u: f32,
+    #[mutagen(range = 0.2..0.6)]
+    pub diffusion_v: f32,
+}
+
+impl Rates {
+    /// Blends each rate from this set's towards `other`'s, by `amount` from 0 to 1
+    pub fn lerp(self, other: Self, amount: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * amount;
+
+        Self {
+            feed: lerp(self.feed, other.feed),
+            kill: lerp(self.kill, other.kill),
+            diffusion_u: lerp(self.diffusion_u, other.diffusion_u),
+            diffusion_v: lerp(self.diffusion_v, other.diffusion_v),
+        }
+    }
+}
+
+impl Traversable for Rates {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("feed", &self.feed);
+        f("kill", &self.kill);
+        f("diffusion_u", &self.diffusion_u);
+        f("diffusion_v", &self.diffusion_v);
+    }
+}
+
+impl_leaf_updatable!(Rates);
+
+//Counts up so every new grid gets an id of its own
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// How much of each chemical is in every cell of a grid, indexed by y and then x, which wraps around at the edges.
+/// Empty until it's seeded.
+#[derive(Clone)]
+pub struct Concentrations {
+    id: u64,
+    u: Array2<f32>,
+    v: Array2<f32>,
+}
+
+impl Default for Concentrations {
+    fn default() -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            u: Array2::zeros((0, 0)),
+            v: Array2::zeros((0, 0)),
+        }
+    }
+}
+
+impl Concentrations {
+    /// Starts the whole grid over with u everywhere, mixing in v wherever `seed` is high, since there's no reaction without both.
+    /// The grid keeps its id.
+    pub fn seed(&mut self, seed: &Array2<f32>) {
+        self.u = seed.mapv(|value| 1.0 - 0.5 * value);
+        self.v = seed.mapv(|value| 0.25 * value);
+    }
+
+    /// Shared by every clone of a grid, and new for every grid that's generated or read from a file,
+    /// so a copy of a tree can find the grid of the same node in another copy
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The height and width of the grid
+    pub fn dim(&self) -> (usize, usize) {
+        self.u.dim()
+    }
+
+    /// The concentrations of u and v at a cell, or all u and no v before the grid's been seeded
+    pub fn get(&self, x: isize, y: isize) -> (f32, f32) {
+        let (height, width) = self.dim();
+
+        if width == 0 || height == 0 {
+            return (1.0, 0.0);
+        }
+
+        let index = [
+            y.rem_euclid(height as isize) as usize,
+            x.rem_euclid(width as isize) as usize,
+        ];
+
+        (self.u[index], self.v[index])
+    }
+
+    /// Runs the reaction forward by one unit of time, using `rates(x, y)` as the rates at each cell
+    pub fn react<F: Fn(usize, usize) -> Rates>(&mut self, rates: F) {
+        let (u, v) = (&self.u, &self.v);
+        let mut next_u = Array2::zeros(u.dim());
+        let mut next_v = Array2::zeros(v.dim());
+
+        Zip::indexed(&mut next_u)
+            .and(&mut next_v)
+            .apply(|(y, x), next_u, next_v| {
+                let rates = rates(x, y);
+                let (current_u, current_v) = (u[[y, x]], v[[y, x]]);
+                let reaction = current_u * current_v * current_v;
+
+                *next_u = (current_u + rates.diffusion_u * laplacian(u, x, y) - reaction
+                    + rates.feed * (1.0 - current_u))
+                    .clamp(0.0, 1.0);
+                *next_v = (current_v + rates.diffusion_v * laplacian(v, x, y) + reaction
+                    - (rates.feed + rates.kill) * current_v)
+                    .clamp(0.0, 1.0);
+            });
+
+        self.u = next_u;
+        self.v = next_v;
+    }
+}
+
+/// How far the cells around one are above it on average, weighting the ones beside it over the ones diagonal to it
+fn laplacian(grid: &Array2<f32>, x: usize, y: usize) -> f32 {
+    let (height, width) = grid.dim();
+    let at = |dx: isize, dy: isize| {
+        grid[[
+            (y as isize + dy).rem_euclid(height as isize) as usize,
+            (x as isize + dx).rem_euclid(width as isize) as usize,
+        ]]
+    };
+
+    0.2 * (at(-1, 0) + at(1, 0) + at(0, -1) + at(0, 1))
+        + 0.05 * (at(-1, -1) + at(1, -1) + at(-1, 1) + at(1, 1))
+        - at(0, 0)
+}
+
+// Too big to print, and only ever filled in while the trees are updated
+impl Debug for Concentrations {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let (height, width) = self.dim();
+        write!(f, "Concentrations({}x{})", width, height)
+    }
+}
+
+impl Generatable for Concentrations {
+    fn generate_rng<R: Rng + ?Sized>(_rng: &mut R, _state: mutagen::State) -> Self {
+        Self::default()
+    }
+}
+
+impl Traversable for Concentrations {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATES: Rates = Rates {
+        feed: 0.0367,
+        kill: 0.0649,
+        diffusion_u: 1.0,
+        diffusion_v: 0.5,
+    };
+
+    #[test]
+    fn test_react() {
+        let mut seed = Array2::zeros((16, 16));
+        seed[[8, 8]] = 1.0;
+
+        let mut concentrations = Concentrations::default();
+        concentrations.seed(&seed);
+        assert_eq!(concentrations.get(8, 8), (0.5, 0.25));
+        assert_eq!(concentrations.get(-8, 24), (0.5, 0.25));
+
+        concentrations.react(|_, _| RATES);
+
+        // Both even out with the cells around the seeded one, and v spreads evenly in every direction
+        let (u, v) = concentrations.get(8, 8);
+        assert!(u > 0.5 && v < 0.25);
+        assert!(concentrations.get(9, 8).1 > 0.0);
+        assert_eq!(concentrations.get(9, 8), concentrations.get(7, 8));
+        assert_eq!(concentrations.get(0, 0), (1.0, 0.0));
+    }
+
+    #[test]
+    fn test_rates_lerp() {
+        let other = Rates {
+            feed: 0.0467,
+            ..RATES
+        };
+
+        assert_eq!(RATES.lerp(other, 0.0), RATES);
+        assert_eq!(RATES.lerp(other, 1.0), other);
+        assert!((RATES.lerp(other, 0.5).feed - 0.0417).abs() < 1e-6);
+        assert_eq!(Concentrations::default().get(3, 4), (1.0, 0.0));
+    }
+}
@@ -1,54 +1,78 @@
-use crate::colors::*;
-use crate::constants::*;
-use crate::get_random_color;
-use ndarray::Array2;
-use rand::prelude::*;
-
-pub trait Reseed {
-    fn reseed(&self, cell_array: &mut Array2<BitColor>) {
-        let cell_array_width = cell_array.dim().0;
-        let cell_array_height = cell_array.dim().1;
-
-        for x in 0..cell_array_width {
-            for y in 0..cell_array_height {
-                cell_array[[x, y]] = self.reseed_cell(x, y);
-            }
-        }
-    }
+//! Patterns that overwrite a whole history step, to bring the cells back to life without changing the trees
+
+use mutagen::{Generatable, Mutatable};
+use ndarray::{Array3, Axis, Zip};
+use noise::NoiseFn;
+
+use crate::{
+    constants::*,
+    datatype::{
+        colors::{BitColor, ByteColor},
+        continuous::*,
+        image::Image,
+        noisefunctions::OpenSimplexNoise,
+    },
+};
 
-    fn mutate(&mut self);
-    fn reseed_cell(&self, x: usize, y: usize) -> BitColor;
-}
+//Largest spacing between the lines of a modulus grid
+const MAX_MODULUS: usize = 32;
 
+#[derive(Generatable, Mutatable, Debug, Clone)]
+#[mutagen(mut_reroll = 0.3)]
 pub enum Reseeder {
+    /// Colors each cell from a table, by whether its column and its row land on the lines of a grid
     Modulus {
+        #[mutagen(range = 1..=MAX_MODULUS)]
         x_mod: usize,
+        #[mutagen(range = 1..=MAX_MODULUS)]
         y_mod: usize,
+        #[mutagen(range = 0..MAX_MODULUS)]
         x_offset: usize,
+        #[mutagen(range = 0..MAX_MODULUS)]
         y_offset: usize,
-        color_table: Array2<BitColor>,
+        color_table: [[BitColor; 2]; 2],
+    },
+    /// Splits a slice of noise into blobs of one color wherever it's above a threshold, and another everywhere else
+    NoiseBlobs {
+        noise: Box<OpenSimplexNoise>,
+        threshold: SNFloat,
+        color: BitColor,
+        background: BitColor,
+    },
+    /// Stretches an image over the whole array
+    ImageStamp { image: Image },
+    /// Scatters cells of one color at random within a circle around the centre, mirroring them across either axis
+    Symmetric {
+        seed: u32,
+        density: UNFloat,
+        radius: UNFloat,
+        mirror_x: bool,
+        mirror_y: bool,
+        color: BitColor,
+        background: BitColor,
     },
 }
 
-impl Reseed for Reseeder {
-    fn reseed_cell(&self, x: usize, y: usize) -> BitColor {
-        match self {
-            Reseeder::Modulus {
-                x_mod,
-                y_mod,
-                x_offset,
-                y_offset,
-                color_table,
-            } => {
-                let x_index = if (x + x_offset) % x_mod == 0 { 1 } else { 0 };
-                let y_index = if (y + y_offset) % y_mod == 0 { 1 } else { 0 };
+impl Reseeder {
+    /// Overwrites every cell of an array indexed by y, x and then color component
+    pub fn reseed(&self, cell_array: &mut Array3<u8>) {
+        let (height, width, _) = cell_array.dim();
 
-                color_table[[x_index, y_index]]
-            }
-        }
+        Zip::indexed(cell_array.lanes_mut(Axis(2))).apply(|(y, x), mut raw| {
+            let color = self.reseed_cell(x, y, width, height);
+
+            raw[0] = color.r;
+            raw[1] = color.g;
+            raw[2] = color.b;
+            raw[3] = color.a;
+        });
     }
 
-    fn mutate(&mut self) {
+    pub fn reseed_cell(&self, x: usize, y: usize, width: usize, height: usize) -> ByteColor {
+        // From -1 to 1 across the middle of each cell, so that mirrored cells come out the same distance from the centre
+        let signed_x = (2 * x + 1) as f32 / width as f32 - 1.0;
+        let signed_y = (2 * y + 1) as f32 / height as f32 - 1.0;
+
         match self {
             Reseeder::Modulus {
                 x_mod,
@@ -57,45 +81,141 @@ impl Reseed for Reseeder {
                 y_offset,
                 color_table,
             } => {
-                let min_cell_array_dim = CELL_ARRAY_WIDTH.min(CELL_ARRAY_HEIGHT);
-
-                if random::<bool>() {
-                    *x_mod = (random::<usize>() % min_cell_array_dim) + 1;
+                // 1 on the lines of the grid, and 0 between them
+                let x_index = match (x + x_offset) % x_mod {
+                    0 => 1,
+                    _ => 0,
+                };
+                let y_index = match (y + y_offset) % y_mod {
+                    0 => 1,
+                    _ => 0,
+                };
+
+                color_table[x_index][y_index].get_color()
+            }
+            Reseeder::NoiseBlobs {
+                noise,
+                threshold,
+                color,
+                background,
+            } => {
+                let value = noise.noise.get([
+                    signed_x as f64
+                        * noise.x_scale.into_inner().powf(2.0) as f64
+                        * CONSTS.noise_x_scale_factor,
+                    signed_y as f64
+                        * noise.y_scale.into_inner().powf(2.0) as f64
+                        * CONSTS.noise_y_scale_factor,
+                    0.0,
+                ]);
+
+                if value > threshold.into_inner() as f64 {
+                    color.get_color()
+                } else {
+                    background.get_color()
                 }
+            }
+            Reseeder::ImageStamp { image } => {
+                image.get_pixel_normalised(SNFloat::new(signed_x), SNFloat::new(signed_y), 0.0)
+            }
+            Reseeder::Symmetric {
+                seed,
+                density,
+                radius,
+                mirror_x,
+                mirror_y,
+                color,
+                background,
+            } => {
+                let x = if *mirror_x { x.min(width - 1 - x) } else { x };
+                let y = if *mirror_y { y.min(height - 1 - y) } else { y };
 
-                if random::<bool>() {
-                    *x_mod = ((*x_mod + 1) % min_cell_array_dim) + 1;
-                }
+                let inside = signed_x.powi(2) + signed_y.powi(2) <= radius.into_inner().powi(2);
 
-                if random::<bool>() {
-                    *x_offset = (random::<usize>() % min_cell_array_dim) + 1;
+                if inside && cell_noise(*seed, x, y) < density.into_inner() {
+                    color.get_color()
+                } else {
+                    background.get_color()
                 }
+            }
+        }
+    }
+}
 
-                if random::<bool>() {
-                    *x_offset = ((*x_offset + 1) % min_cell_array_dim) + 1;
-                }
+/// A number from 0 to 1 that's the same for the same seed and cell every time, but otherwise looks random
+fn cell_noise(seed: u32, x: usize, y: usize) -> f32 {
+    let mut hash = u64::from(seed)
+        ^ (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
 
-                if random::<bool>() {
-                    *y_mod = (random::<usize>() % min_cell_array_dim) + 1;
-                }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
 
-                if random::<bool>() {
-                    *y_mod = ((*y_mod + 1) % min_cell_array_dim) + 1;
-                }
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
 
-                if random::<bool>() {
-                    *y_offset = (random::<usize>() % min_cell_array_dim) + 1;
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                if random::<bool>() {
-                    *y_offset = ((*y_offset + 1) % min_cell_array_dim) + 1;
-                }
+    const WIDTH: usize = 24;
+    const HEIGHT: usize = 16;
 
-                if random::<bool>() {
-                    color_table[[random::<usize>() % 2, random::<usize>() % 2]] =
-                        get_random_color();
-                }
-            }
+    fn reseeded(reseeder: &Reseeder) -> Array3<u8> {
+        let mut cell_array = Array3::zeros((HEIGHT, WIDTH, 4));
+        reseeder.reseed(&mut cell_array);
+        cell_array
+    }
+
+    #[test]
+    fn test_modulus() {
+        let reseeder = Reseeder::Modulus {
+            x_mod: 4,
+            y_mod: 3,
+            x_offset: 1,
+            y_offset: 0,
+            color_table: [
+                [BitColor::Black, BitColor::Red],
+                [BitColor::Green, BitColor::White],
+            ],
+        };
+
+        let cell_array = reseeded(&reseeder);
+
+        // Lines every 4 columns from x = 3, and every 3 rows from y = 0
+        assert_eq!(cell_array[[0, 3, 0]], 255);
+        assert_eq!(cell_array[[0, 3, 1]], 255);
+        assert_eq!(cell_array[[0, 7, 1]], 255);
+        assert_eq!(cell_array[[1, 3, 1]], 255);
+        assert_eq!(cell_array[[1, 3, 0]], 0);
+        assert_eq!(cell_array[[1, 4, 1]], 0);
+        assert_eq!(cell_array[[3, 4, 0]], 255);
+    }
+
+    #[test]
+    fn test_symmetric() {
+        let reseeder = Reseeder::Symmetric {
+            seed: 7,
+            density: UNFloat::new(0.5),
+            radius: UNFloat::new(1.0),
+            mirror_x: true,
+            mirror_y: true,
+            color: BitColor::White,
+            background: BitColor::Black,
+        };
+
+        let cell_array = reseeded(&reseeder);
+
+        for ((y, x, c), &value) in cell_array.indexed_iter() {
+            assert_eq!(value, cell_array[[HEIGHT - 1 - y, WIDTH - 1 - x, c]]);
         }
+
+        // The circle is inside the array, so the corners are always left as the background
+        assert_eq!(cell_array[[0, 0, 0]], 0);
+        assert!(cell_array
+            .index_axis(Axis(2), 0)
+            .iter()
+            .any(|&red| red == 255));
     }
 }
@@ -1,57 +1,138 @@
-use crate::constants::*;
-use rand::prelude::*;
-//One of these for each one-way colour relation
-#[derive(Clone, Copy)]
+use mutagen::{impl_leaf_crossover, impl_leaf_updatable, Generatable, Mutatable, Traversable};
+use serde::{Deserialize, Serialize};
+
+use crate::datatype::colors::BitColor;
+
+//Array lengths have to be known when compiling, so these can't come from constants.yml
+pub const MAX_NEIGHBOUR_ARRAY_COUNT: usize = 9; //Counts zero, up to the 8 neighbours of a Moore neighbourhood
+pub const MAX_COLORS: usize = 8;
+
+//One of these for each colour
+#[derive(Generatable, Mutatable, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Rule {
-    pub life_neighbours: [bool; MAX_NEIGHBOUR_ARRAY_COUNT], //How many neighbours we need to be born
-    pub death_neighbours: [bool; MAX_NEIGHBOUR_ARRAY_COUNT], //How many neighbours we need to be killed
+    pub life_neighbours: [bool; MAX_NEIGHBOUR_ARRAY_COUNT], //How many neighbours of this colour we need to be born
+    pub death_neighbours: [bool; MAX_NEIGHBOUR_ARRAY_COUNT], //How many neighbours of this colour we need to be killed
 }
 
-//One of these per colour
-#[derive(Clone, Copy)]
+impl Rule {
+    pub fn is_born(self, neighbours: usize) -> bool {
+        self.life_neighbours[neighbours]
+    }
+
+    pub fn dies(self, neighbours: usize) -> bool {
+        self.death_neighbours[neighbours]
+    }
+}
+
+/// Written like the B3/S23 notation for Conway's Life, but listing the counts that kill rather than the ones that survive
+impl Traversable for Rule {
+    fn data_value(&self) -> Option<String> {
+        let counts = |neighbours: &[bool]| -> String {
+            neighbours
+                .iter()
+                .enumerate()
+                .filter(|(_, &set)| set)
+                .map(|(count, _)| count.to_string())
+                .collect()
+        };
+
+        Some(format!(
+            "B{}/D{}",
+            counts(&self.life_neighbours),
+            counts(&self.death_neighbours)
+        ))
+    }
+}
+
+impl_leaf_updatable!(Rule);
+impl_leaf_crossover!(Rule);
+
+//One rule per colour, indexed as by BitColor::to_index
+#[derive(Generatable, Mutatable, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RuleSet {
     pub rules: [Rule; MAX_COLORS],
 }
 
-pub fn generate_random_neighbour_list() -> [bool; MAX_NEIGHBOUR_ARRAY_COUNT] {
-    [
-        random::<bool>(),
-        random::<bool>(),
-        random::<bool>(),
-        random::<bool>(),
-        random::<bool>(),
-        random::<bool>(),
-        random::<bool>(),
-        random::<bool>(),
-        random::<bool>(),
-    ]
-}
+impl RuleSet {
+    /// The colour a cell becomes, given its current colour and how many of its neighbours are each colour.
+    /// A cell keeps its colour unless that colour's rule kills it, and is then taken over by whichever colour
+    /// with a rule to be born has the most neighbours, or goes black if none do.
+    pub fn next_color(&self, current: BitColor, neighbours: &[usize; MAX_COLORS]) -> BitColor {
+        let current_index = current.to_index();
+
+        if !self.rules[current_index].dies(neighbours[current_index]) {
+            return current;
+        }
 
-pub fn generate_random_rule() -> Rule {
-    Rule {
-        life_neighbours: generate_random_neighbour_list(),
-        death_neighbours: generate_random_neighbour_list(),
+        (0..MAX_COLORS)
+            .filter(|&index| index != current_index && self.rules[index].is_born(neighbours[index]))
+            .max_by_key(|&index| neighbours[index])
+            .map_or(BitColor::Black, BitColor::from_index)
     }
 }
 
-pub fn generate_random_rule_set() -> RuleSet {
-    RuleSet {
-        rules: [
-            generate_random_rule(),
-            generate_random_rule(),
-            generate_random_rule(),
-            generate_random_rule(),
-            generate_random_rule(),
-            generate_random_rule(),
-            generate_random_rule(),
-            generate_random_rule(),
-        ],
+impl Traversable for RuleSet {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        f("rules", &self.rules);
     }
 }
 
-pub fn mutate_rule_set(rule_set: &mut RuleSet) {
-    rule_set.rules[random::<usize>() % MAX_COLORS].life_neighbours
-        [random::<usize>() % MAX_NEIGHBOUR_ARRAY_COUNT] = random::<bool>();
-    rule_set.rules[random::<usize>() % MAX_COLORS].death_neighbours
-        [random::<usize>() % MAX_NEIGHBOUR_ARRAY_COUNT] = random::<bool>();
+impl_leaf_updatable!(RuleSet);
+impl_leaf_crossover!(RuleSet);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every colour is born with 3 neighbours, and dies unless it has 2 or 3
+    fn life() -> RuleSet {
+        let mut rule = Rule {
+            life_neighbours: [false; MAX_NEIGHBOUR_ARRAY_COUNT],
+            death_neighbours: [true; MAX_NEIGHBOUR_ARRAY_COUNT],
+        };
+        rule.life_neighbours[3] = true;
+        rule.death_neighbours[2] = false;
+        rule.death_neighbours[3] = false;
+
+        RuleSet {
+            rules: [rule; MAX_COLORS],
+        }
+    }
+
+    #[test]
+    fn test_next_color() {
+        let rule_set = life();
+        let mut neighbours = [0; MAX_COLORS];
+
+        neighbours[BitColor::Red.to_index()] = 2;
+        assert_eq!(
+            rule_set.next_color(BitColor::Red, &neighbours),
+            BitColor::Red
+        );
+        assert_eq!(
+            rule_set.next_color(BitColor::Black, &neighbours),
+            BitColor::Black
+        );
+
+        neighbours[BitColor::Blue.to_index()] = 3;
+        assert_eq!(
+            rule_set.next_color(BitColor::Black, &neighbours),
+            BitColor::Blue
+        );
+
+        neighbours[BitColor::Red.to_index()] = 1;
+        assert_eq!(
+            rule_set.next_color(BitColor::Red, &neighbours),
+            BitColor::Blue
+        );
+        assert_eq!(
+            rule_set.next_color(BitColor::Blue, &neighbours),
+            BitColor::Blue
+        );
+    }
+
+    #[test]
+    fn test_rule_data_value() {
+        assert_eq!(life().rules[0].data_value(), Some("B3/D0145678".to_owned()));
+    }
 }
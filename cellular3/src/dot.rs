@@ -0,0 +1,95 @@
+use std::fmt::Write;
+
+use mutagen::Traversable;
+
+/// Renders a tree as a Graphviz DOT graph.
+/// Every node becomes a box labeled with its name and the values of its plain data fields,
+/// and every edge is labeled with the name of the field it comes from.
+pub fn to_dot<T: Traversable + ?Sized>(root: &T) -> String {
+    let mut writer = DotWriter {
+        out: String::new(),
+        next_id: 0,
+    };
+
+    writeln!(writer.out, "digraph tree {{").unwrap();
+    writeln!(writer.out, "    node [shape=box, fontname=monospace];").unwrap();
+    writer.write_node(root);
+    writeln!(writer.out, "}}").unwrap();
+
+    writer.out
+}
+
+struct DotWriter {
+    out: String,
+    next_id: usize,
+}
+
+impl DotWriter {
+    /// Writes a node and everything below it, returning the node's id
+    fn write_node<T: Traversable + ?Sized>(&mut self, node: &T) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut lines = vec![node.node_name().unwrap_or("?").to_owned()];
+        if let Some(value) = node.data_value() {
+            lines.push(value);
+        }
+
+        self.write_fields(id, "", node, &mut lines);
+
+        writeln!(
+            self.out,
+            "    n{} [label=\"{}\\l\"];",
+            id,
+            lines
+                .iter()
+                .map(|line| escape(line))
+                .collect::<Vec<_>>()
+                .join("\\l")
+        )
+        .unwrap();
+
+        id
+    }
+
+    /// Writes an edge for every node below `value`, and adds a line to `lines` for every piece of plain data.
+    /// Plain data that has fields of its own gets flattened, so a noise function's scale shows up as `noise.x_scale`.
+    fn write_fields<T: Traversable + ?Sized>(
+        &mut self,
+        parent_id: usize,
+        prefix: &str,
+        value: &T,
+        lines: &mut Vec<String>,
+    ) {
+        value.visit_children(&mut |field, child| {
+            let path = if prefix.is_empty() {
+                field.to_owned()
+            } else {
+                format!("{}.{}", prefix, field)
+            };
+
+            if child.node_name().is_some() {
+                let child_id = self.write_node(child);
+
+                writeln!(
+                    self.out,
+                    "    n{} -> n{} [label=\"{}\"];",
+                    parent_id,
+                    child_id,
+                    escape(&path)
+                )
+                .unwrap();
+            } else {
+                if let Some(data) = child.data_value() {
+                    lines.push(format!("{} = {}", path, data));
+                }
+
+                self.write_fields(parent_id, &path, child, lines);
+            }
+        });
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
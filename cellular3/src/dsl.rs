@@ -0,0 +1,225 @@
+//! A compact text format for writing node trees by hand, such as
+//! `HSV(h: FromGametic, s: Constant(value: 0.8), v: Constant(value: 1.0), a: Constant(value: 1.0))`.
+//!
+//! Enum variants and structs are written as a name followed by their fields in parentheses,
+//! and the parentheses are left out when there are no fields.
+//! Lists are written as `[a, b]`, maps as `{key: value}`, and strings in double quotes.
+//!
+//! Anything that can be serialized can be printed, and anything that can be deserialized can be parsed,
+//! which covers every node enum as well as whole genomes.
+
+mod de;
+mod parse;
+mod print;
+mod ser;
+
+use std::fmt::{self, Display, Formatter};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Parses a value written in the tree format
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Error> {
+    let value = parse::parse(s)?;
+    T::deserialize(de::ValueDeserializer::new(&value))
+}
+
+/// Pretty-prints a value in the tree format, so that [from_str] reads it back unchanged
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    Ok(print::print(&value.serialize(ser::ValueSerializer)?))
+}
+
+/// A line and column in the text being parsed, both counted from 1
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug)]
+pub struct Error {
+    message: String,
+    // Where in the text the error happened, which is missing for errors raised while printing
+    position: Option<Position>,
+    // The type of node being parsed when the error happened
+    node_type: Option<&'static str>,
+}
+
+impl Error {
+    fn new<T: Display>(message: T) -> Self {
+        Self {
+            message: message.to_string(),
+            position: None,
+            node_type: None,
+        }
+    }
+
+    fn at(position: Position, message: String) -> Self {
+        Self {
+            position: Some(position),
+            ..Self::new(message)
+        }
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    pub fn node_type(&self) -> Option<&'static str> {
+        self.node_type
+    }
+
+    /// Fills in the position if the error doesn't know where it happened yet.
+    /// Errors from deeper in a tree are more precise, so they keep theirs.
+    fn or_at(mut self, position: Position) -> Self {
+        if self.position.is_none() {
+            self.position = Some(position);
+        }
+
+        self
+    }
+
+    /// Fills in the node type if the error doesn't know which node it happened in yet
+    fn or_in(mut self, node_type: &'static str) -> Self {
+        if self.node_type.is_none() {
+            self.node_type = Some(node_type);
+        }
+
+        self
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if let Some(position) = self.position {
+            write!(f, "{}: ", position)?;
+        }
+
+        if let Some(node_type) = self.node_type {
+            write!(f, "invalid {}: ", node_type)?;
+        }
+
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl serde::de::Error for Error {
+    fn custom<T: Display>(message: T) -> Self {
+        Self::new(message)
+    }
+}
+
+impl serde::ser::Error for Error {
+    fn custom<T: Display>(message: T) -> Self {
+        Self::new(message)
+    }
+}
+
+/// A parsed value along with where it started in the text
+#[derive(Clone, Debug, PartialEq)]
+struct Spanned {
+    value: Value,
+    position: Position,
+}
+
+impl From<Value> for Spanned {
+    // Printed values don't come from any text, so they're all placed at the default position
+    fn from(value: Value) -> Self {
+        Self {
+            value,
+            position: Position::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    /// `()`
+    Unit,
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    Str(String),
+    /// `[a, b]`
+    List(Vec<Spanned>),
+    /// `{key: value}`
+    Map(Vec<(Spanned, Spanned)>),
+    /// `Name`, `Name(a, b)` or `Name(field: a)`, where the name may also be left out when there are parentheses
+    Call {
+        name: Option<String>,
+        args: Option<Vec<Arg>>,
+    },
+}
+
+impl Value {
+    /// How the value is described in error messages
+    fn kind(&self) -> String {
+        match self {
+            Value::Unit => "()".to_owned(),
+            Value::Bool(_) => "a boolean".to_owned(),
+            Value::Int(_) => "an integer".to_owned(),
+            Value::Float(_) => "a number".to_owned(),
+            Value::Str(_) => "a string".to_owned(),
+            Value::List(_) => "a list".to_owned(),
+            Value::Map(_) => "a map".to_owned(),
+            Value::Call {
+                name: Some(name), ..
+            } => format!("`{}`", name),
+            Value::Call { name: None, .. } => "a struct".to_owned(),
+        }
+    }
+}
+
+/// A value in parentheses, which is named when it's a struct field
+#[derive(Clone, Debug, PartialEq)]
+struct Arg {
+    name: Option<String>,
+    value: Spanned,
+}
+
+#[cfg(test)]
+mod tests {
+    use mutagen::{Generatable, Mutatable};
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{genome::Genome, node::color_nodes::FloatColorNodes, util::DeterministicRng};
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = DeterministicRng::seed_from_u64(0);
+
+        for _ in 0..20 {
+            let mut genome = Genome::generate_rng(&mut rng, mutagen::State::default());
+            genome
+                .root_node
+                .mutate_rng(&mut rng, mutagen::State::default());
+
+            let printed = to_string(&genome).unwrap();
+            let parsed: Genome = from_str(&printed).unwrap_or_else(|e| panic!("{}", e));
+
+            assert_eq!(printed, to_string(&parsed).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_error_position() {
+        let e = from_str::<FloatColorNodes>("Grayscale(\n    child: Bogus,\n)").unwrap_err();
+
+        assert_eq!(
+            e.position(),
+            Some(Position {
+                line: 2,
+                column: 12
+            })
+        );
+        assert_eq!(e.node_type(), Some("UNFloatNodes"));
+    }
+}
@@ -0,0 +1,512 @@
+use std::{convert::TryFrom, fmt::Display};
+
+use serde::de::{
+    self, value::StrDeserializer, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess,
+    SeqAccess, VariantAccess, Visitor,
+};
+
+use super::{Arg, Error, Position, Spanned, Value};
+
+/// Reads a parsed [Value] into anything deserializable
+#[derive(Clone, Copy)]
+pub(super) struct ValueDeserializer<'a> {
+    value: &'a Spanned,
+}
+
+impl<'a> ValueDeserializer<'a> {
+    pub(super) fn new(value: &'a Spanned) -> Self {
+        Self { value }
+    }
+
+    fn error<T: Display>(&self, message: T) -> Error {
+        Error::at(self.value.position, message.to_string())
+    }
+
+    fn invalid(&self, expected: &str) -> Error {
+        self.error(format!(
+            "expected {}, found {}",
+            expected,
+            self.value.value.kind()
+        ))
+    }
+
+    /// Places any error from `f` at this value, unless it already knows where it happened
+    fn positioned<T, F>(self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(Self) -> Result<T, Error>,
+    {
+        let position = self.value.position;
+        f(self).map_err(|e| e.or_at(position))
+    }
+
+    fn deserialize_int<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.positioned(|de| match de.value.value {
+            Value::Int(i) => {
+                if let Ok(i) = i64::try_from(i) {
+                    visitor.visit_i64(i)
+                } else if let Ok(u) = u64::try_from(i) {
+                    visitor.visit_u64(u)
+                } else {
+                    Err(de.error(format!("integer {} is out of range", i)))
+                }
+            }
+            _ => Err(de.invalid("an integer")),
+        })
+    }
+
+    fn deserialize_float<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.positioned(|de| match &de.value.value {
+            Value::Float(f) => visitor.visit_f64(*f),
+            Value::Int(i) => visitor.visit_f64(*i as f64),
+            // Printing a float that isn't finite gives a bare name rather than a number
+            Value::Call {
+                name: Some(name),
+                args: None,
+            } => match name.parse() {
+                Ok(f) => visitor.visit_f64(f),
+                Err(_) => Err(de.invalid("a number")),
+            },
+            _ => Err(de.invalid("a number")),
+        })
+    }
+
+    /// The fields of a struct or variant, checking that they're all named or all unnamed as expected
+    fn args(&self, named: bool) -> Result<&'a [Arg], Error> {
+        let value: &'a Spanned = self.value;
+
+        let args = match &value.value {
+            Value::Call {
+                args: Some(args), ..
+            } => args.as_slice(),
+            _ => &[],
+        };
+
+        if let Some(arg) = args.iter().find(|arg| arg.name.is_some() != named) {
+            return Err(Error::at(
+                arg.value.position,
+                if named {
+                    "expected a field name before the value".to_owned()
+                } else {
+                    "unexpected field name".to_owned()
+                },
+            ));
+        }
+
+        Ok(args)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.positioned(|de| match &de.value.value {
+            Value::Unit => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::Int(_) => de.deserialize_int(visitor),
+            Value::Float(f) => visitor.visit_f64(*f),
+            Value::Str(s) => visitor.visit_str(s),
+            Value::List(items) => visitor.visit_seq(Seq::new(items)),
+            Value::Map(entries) => visitor.visit_map(Map::new(entries)),
+            Value::Call { args: None, .. } => visitor.visit_unit(),
+            Value::Call { args: Some(_), .. } => {
+                if de.args(true).is_ok() {
+                    visitor.visit_map(Fields::new(de.args(true)?))
+                } else {
+                    visitor.visit_seq(Seq::from_args(de.args(false)?))
+                }
+            }
+        })
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.positioned(|de| match de.value.value {
+            Value::Bool(b) => visitor.visit_bool(b),
+            _ => Err(de.invalid("a boolean")),
+        })
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_int(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_int(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_int(visitor)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_int(visitor)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_int(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_int(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_int(visitor)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_int(visitor)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_float(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_float(visitor)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.positioned(|de| match &de.value.value {
+            Value::Str(s) => visitor.visit_str(s),
+            _ => Err(de.invalid("a string")),
+        })
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    /// Accepts `None` and `Some(value)`, as well as a bare value, which is read as if it was wrapped in `Some`
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.positioned(|de| match &de.value.value {
+            Value::Call {
+                name: Some(name),
+                args: None,
+            } if name == "None" => visitor.visit_none(),
+            Value::Call {
+                name: Some(name),
+                args: Some(args),
+            } if name == "Some" => match args.as_slice() {
+                [Arg { name: None, value }] => visitor.visit_some(ValueDeserializer::new(value)),
+                _ => Err(de.error("expected exactly one value in `Some`")),
+            },
+            _ => visitor.visit_some(de),
+        })
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.positioned(|de| match de.value.value {
+            Value::Unit => visitor.visit_unit(),
+            _ => Err(de.invalid("()")),
+        })
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.positioned(|de| match &de.value.value {
+            Value::Unit => visitor.visit_unit(),
+            Value::Call {
+                name: Some(found),
+                args: None,
+            } if found == name => visitor.visit_unit(),
+            _ => Err(de.invalid(&format!("`{}`", name))),
+        })
+        .map_err(|e| e.or_in(name))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.positioned(|de| match &de.value.value {
+            Value::List(items) => visitor.visit_seq(Seq::new(items)),
+            _ => Err(de.invalid("a list")),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.positioned(|de| match &de.value.value {
+            Value::Map(entries) => visitor.visit_map(Map::new(entries)),
+            _ => Err(de.invalid("a map")),
+        })
+    }
+
+    /// Accepts `Name(field: value)`, where the name is optional but has to match if it's there
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.positioned(|de| match &de.value.value {
+            Value::Call {
+                name: Some(found), ..
+            } if found != name => Err(de.invalid(&format!("`{}`", name))),
+            Value::Call { .. } | Value::Unit => visitor.visit_map(Fields::new(de.args(true)?)),
+            _ => Err(de.invalid(&format!("`{}`", name))),
+        })
+        .map_err(|e| e.or_in(name))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.positioned(|de| match &de.value.value {
+            Value::Call {
+                name: Some(variant),
+                ..
+            } => {
+                if variants.contains(&variant.as_str()) {
+                    visitor.visit_enum(Enum { de, variant })
+                } else {
+                    Err(de.error(format!(
+                        "unknown variant `{}`, expected one of {}",
+                        variant,
+                        variants
+                            .iter()
+                            .map(|v| format!("`{}`", v))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )))
+                }
+            }
+            _ => Err(de.invalid(&format!("a {} variant", name))),
+        })
+        .map_err(|e| e.or_in(name))
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.positioned(|de| match &de.value.value {
+            Value::Str(s) => visitor.visit_str(s),
+            Value::Call {
+                name: Some(name),
+                args: None,
+            } => visitor.visit_str(name),
+            _ => Err(de.invalid("a name")),
+        })
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// The items of a list, or the unnamed fields of a variant
+struct Seq<'a> {
+    items: Box<dyn Iterator<Item = &'a Spanned> + 'a>,
+}
+
+impl<'a> Seq<'a> {
+    fn new(items: &'a [Spanned]) -> Self {
+        Self {
+            items: Box::new(items.iter()),
+        }
+    }
+
+    fn from_args(args: &'a [Arg]) -> Self {
+        Self {
+            items: Box::new(args.iter().map(|arg| &arg.value)),
+        }
+    }
+}
+
+impl<'de> SeqAccess<'de> for Seq<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        self.items
+            .next()
+            .map(|item| seed.deserialize(ValueDeserializer::new(item)))
+            .transpose()
+    }
+}
+
+/// The entries of a map
+struct Map<'a> {
+    entries: std::slice::Iter<'a, (Spanned, Spanned)>,
+    value: Option<&'a Spanned>,
+}
+
+impl<'a> Map<'a> {
+    fn new(entries: &'a [(Spanned, Spanned)]) -> Self {
+        Self {
+            entries: entries.iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for Map<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(ValueDeserializer::new(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error::new("map value read before its key"))?;
+
+        seed.deserialize(ValueDeserializer::new(value))
+    }
+}
+
+/// The named fields of a struct or variant, read as a map from field name to value
+struct Fields<'a> {
+    args: std::slice::Iter<'a, Arg>,
+    value: Option<&'a Spanned>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(args: &'a [Arg]) -> Self {
+        Self {
+            args: args.iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for Fields<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.args.next() {
+            Some(arg) => {
+                self.value = Some(&arg.value);
+
+                let name: StrDeserializer<Error> =
+                    arg.name.as_deref().unwrap_or("").into_deserializer();
+
+                seed.deserialize(name)
+                    .map(Some)
+                    .map_err(|e| e.or_at(field_position(&arg.value)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error::new("field value read before its name"))?;
+
+        seed.deserialize(ValueDeserializer::new(value))
+    }
+}
+
+// Field names aren't kept with positions of their own, so errors about them point at their values
+fn field_position(value: &Spanned) -> Position {
+    value.position
+}
+
+struct Enum<'a> {
+    de: ValueDeserializer<'a>,
+    variant: &'a str,
+}
+
+impl<'de> EnumAccess<'de> for Enum<'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let name: StrDeserializer<Error> = self.variant.into_deserializer();
+        let variant = seed.deserialize(name)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Enum<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match &self.de.value.value {
+            Value::Call { args: None, .. } => Ok(()),
+            Value::Call {
+                args: Some(args), ..
+            } if args.is_empty() => Ok(()),
+            _ => Err(self
+                .de
+                .error(format!("`{}` doesn't take any fields", self.variant))),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        match self.de.args(false)? {
+            [arg] => seed.deserialize(ValueDeserializer::new(&arg.value)),
+            _ => Err(self
+                .de
+                .error(format!("expected exactly one value in `{}`", self.variant))),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(Seq::from_args(self.de.args(false)?))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_map(Fields::new(self.de.args(true)?))
+    }
+}
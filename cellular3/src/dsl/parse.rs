@@ -0,0 +1,274 @@
+use std::{iter::Peekable, str::CharIndices};
+
+use super::{Arg, Error, Position, Spanned, Value};
+
+/// Parses a whole text into a single value
+pub(super) fn parse(s: &str) -> Result<Spanned, Error> {
+    let mut parser = Parser {
+        chars: s.char_indices().peekable(),
+        position: Position { line: 1, column: 1 },
+    };
+
+    let value = parser.value()?;
+
+    parser.skip_whitespace();
+    if let Some(c) = parser.peek() {
+        return Err(parser.error(format!("expected end of input, found `{}`", c)));
+    }
+
+    Ok(value)
+}
+
+struct Parser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+    position: Position,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let (_, c) = self.chars.next()?;
+
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn error(&self, message: String) -> Error {
+        Error::at(self.position, message)
+    }
+
+    /// Skips whitespace and `//` comments
+    fn skip_whitespace(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.next();
+                }
+                Some('/') => {
+                    let mut lookahead = self.chars.clone();
+                    lookahead.next();
+
+                    if lookahead.peek().map(|&(_, c)| c) != Some('/') {
+                        return;
+                    }
+
+                    while matches!(self.peek(), Some(c) if c != '\n') {
+                        self.next();
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Consumes `expected` after any whitespace, or fails describing what was found instead
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.next();
+                Ok(())
+            }
+            Some(c) => Err(self.error(format!("expected `{}`, found `{}`", expected, c))),
+            None => Err(self.error(format!("expected `{}`, found end of input", expected))),
+        }
+    }
+
+    /// Consumes `c` if it comes next after any whitespace
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+
+        if self.peek() == Some(c) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value(&mut self) -> Result<Spanned, Error> {
+        self.skip_whitespace();
+        let position = self.position;
+
+        let value = match self.peek() {
+            Some('(') => self.call(None)?,
+            Some('[') => Value::List(self.list(']', Self::value)?),
+            Some('{') => Value::Map(self.list('}', Self::map_entry)?),
+            Some('"') => Value::Str(self.string()?),
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => self.number()?,
+            Some(c) if is_ident_start(c) => {
+                let ident = self.ident();
+
+                match ident.as_str() {
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    _ => {
+                        self.skip_whitespace();
+
+                        if self.peek() == Some('(') {
+                            self.call(Some(ident))?
+                        } else {
+                            Value::Call {
+                                name: Some(ident),
+                                args: None,
+                            }
+                        }
+                    }
+                }
+            }
+            Some(c) => return Err(self.error(format!("expected a value, found `{}`", c))),
+            None => return Err(self.error("expected a value, found end of input".to_owned())),
+        };
+
+        Ok(Spanned { value, position })
+    }
+
+    /// Parses the parenthesized part of a call, where `()` on its own is the unit value
+    fn call(&mut self, name: Option<String>) -> Result<Value, Error> {
+        let args = self.list(')', Self::arg)?;
+
+        if name.is_none() && args.is_empty() {
+            Ok(Value::Unit)
+        } else {
+            Ok(Value::Call {
+                name,
+                args: Some(args),
+            })
+        }
+    }
+
+    fn arg(&mut self) -> Result<Arg, Error> {
+        self.skip_whitespace();
+
+        // Only a name followed by a colon is a field name, anything else is the start of the value
+        if matches!(self.peek(), Some(c) if is_ident_start(c)) {
+            let mut lookahead = Parser {
+                chars: self.chars.clone(),
+                position: self.position,
+            };
+
+            let name = lookahead.ident();
+
+            if lookahead.eat(':') {
+                *self = lookahead;
+
+                return Ok(Arg {
+                    name: Some(name),
+                    value: self.value()?,
+                });
+            }
+        }
+
+        Ok(Arg {
+            name: None,
+            value: self.value()?,
+        })
+    }
+
+    fn map_entry(&mut self) -> Result<(Spanned, Spanned), Error> {
+        let key = self.value()?;
+        self.expect(':')?;
+        let value = self.value()?;
+
+        Ok((key, value))
+    }
+
+    /// Parses items separated by commas up to `close`, allowing a trailing comma.
+    /// Expects to be positioned on the opening bracket.
+    fn list<T, F>(&mut self, close: char, mut item: F) -> Result<Vec<T>, Error>
+    where
+        F: FnMut(&mut Self) -> Result<T, Error>,
+    {
+        self.next();
+        let mut items = Vec::new();
+
+        loop {
+            if self.eat(close) {
+                return Ok(items);
+            }
+
+            items.push(item(self)?);
+
+            if !self.eat(',') {
+                self.expect(close)?;
+                return Ok(items);
+            }
+        }
+    }
+
+    fn ident(&mut self) -> String {
+        let mut ident = String::new();
+
+        while let Some(c) = self.peek().filter(|&c| is_ident_continue(c)) {
+            ident.push(c);
+            self.next();
+        }
+
+        ident
+    }
+
+    fn number(&mut self) -> Result<Value, Error> {
+        let position = self.position;
+        let mut text = String::new();
+
+        while let Some(c) = self
+            .peek()
+            .filter(|&c| is_ident_continue(c) || c == '-' || c == '+' || c == '.')
+        {
+            text.push(c);
+            self.next();
+        }
+
+        // Integers are kept exact, so that they can still be read into integer fields
+        if let Ok(i) = text.parse() {
+            return Ok(Value::Int(i));
+        }
+
+        text.parse()
+            .map(Value::Float)
+            .map_err(|_| Error::at(position, format!("invalid number `{}`", text)))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.next();
+        let mut s = String::new();
+
+        loop {
+            let position = self.position;
+
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.next() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('\\') => s.push('\\'),
+                    Some('"') => s.push('"'),
+                    Some(c) => {
+                        return Err(Error::at(position, format!("invalid escape `\\{}`", c)))
+                    }
+                    None => return Err(self.error("unterminated string".to_owned())),
+                },
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated string".to_owned())),
+            }
+        }
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident_continue(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
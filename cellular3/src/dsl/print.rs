@@ -0,0 +1,140 @@
+use super::{Arg, Spanned, Value};
+
+/// Lines longer than this get broken up, with one item per line
+const MAX_WIDTH: usize = 100;
+const INDENT: usize = 4;
+
+pub(super) fn print(value: &Value) -> String {
+    let mut out = String::new();
+    print_value(&mut out, value, 0);
+    out.push('\n');
+    out
+}
+
+fn print_value(out: &mut String, value: &Value, indent: usize) {
+    let inline = inline(value);
+
+    // The indent is only a guess at where the value starts, since it may come after a field name
+    if indent + inline.len() <= MAX_WIDTH {
+        out.push_str(&inline);
+        return;
+    }
+
+    match value {
+        Value::List(items) => print_block(out, "[", "]", items, indent, |out, item, indent| {
+            print_value(out, &item.value, indent)
+        }),
+        Value::Map(entries) => print_block(
+            out,
+            "{",
+            "}",
+            entries,
+            indent,
+            |out, (key, value), indent| {
+                print_value(out, &key.value, indent);
+                out.push_str(": ");
+                print_value(out, &value.value, indent);
+            },
+        ),
+        Value::Call {
+            name,
+            args: Some(args),
+        } => {
+            let open = format!("{}(", name.as_deref().unwrap_or(""));
+            print_block(out, &open, ")", args, indent, |out, arg, indent| {
+                if let Some(name) = &arg.name {
+                    out.push_str(name);
+                    out.push_str(": ");
+                }
+
+                print_value(out, &arg.value.value, indent)
+            })
+        }
+        // Anything else can't be broken up
+        _ => out.push_str(&inline),
+    }
+}
+
+fn print_block<T, F>(out: &mut String, open: &str, close: &str, items: &[T], indent: usize, f: F)
+where
+    F: Fn(&mut String, &T, usize),
+{
+    out.push_str(open);
+    out.push('\n');
+
+    for item in items {
+        push_indent(out, indent + INDENT);
+        f(out, item, indent + INDENT);
+        out.push_str(",\n");
+    }
+
+    push_indent(out, indent);
+    out.push_str(close);
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    out.extend(std::iter::repeat(' ').take(indent));
+}
+
+/// Prints a value on a single line
+fn inline(value: &Value) -> String {
+    match value {
+        Value::Unit => "()".to_owned(),
+        Value::Bool(b) => b.to_string(),
+        Value::Int(i) => i.to_string(),
+        // Debug always includes a decimal point, which keeps whole numbers from reading back as integers
+        Value::Float(f) => format!("{:?}", f),
+        Value::Str(s) => escape(s),
+        Value::List(items) => format!("[{}]", join(items.iter().map(inline_spanned))),
+        Value::Map(entries) => format!(
+            "{{{}}}",
+            join(entries.iter().map(|(key, value)| format!(
+                "{}: {}",
+                inline_spanned(key),
+                inline_spanned(value)
+            )))
+        ),
+        Value::Call { name, args } => {
+            let name = name.as_deref().unwrap_or("");
+
+            match args {
+                Some(args) => format!("{}({})", name, join(args.iter().map(inline_arg))),
+                None => name.to_owned(),
+            }
+        }
+    }
+}
+
+/// Quotes a string, escaping only what the parser needs escaped
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+fn inline_spanned(value: &Spanned) -> String {
+    inline(&value.value)
+}
+
+fn inline_arg(arg: &Arg) -> String {
+    match &arg.name {
+        Some(name) => format!("{}: {}", name, inline_spanned(&arg.value)),
+        None => inline_spanned(&arg.value),
+    }
+}
+
+fn join<I: Iterator<Item = String>>(items: I) -> String {
+    items.collect::<Vec<_>>().join(", ")
+}
@@ -0,0 +1,211 @@
+//! Evolves a population of genomes, scoring each by how well a short headless run keeps to the mutation rules
+
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{BufWriter, Write},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use failure::{format_err, Fallible};
+use log::{error, info};
+use mutagen::{Crossover, Frozen, Generatable, Mutatable, Traversable};
+use rand::prelude::*;
+
+use crate::{
+    constants::*, genome::Genome, opts::EvolutionOpts, profile::mutagen_state,
+    simulation::Simulation, util::DeterministicRng,
+};
+
+#[derive(Clone)]
+pub struct Individual {
+    pub genome: Genome,
+    pub fitness: f64,
+}
+
+impl Individual {
+    fn new(genome: Genome, opts: &EvolutionOpts) -> Self {
+        Self {
+            fitness: score(&genome, opts.scoring_steps),
+            genome,
+        }
+    }
+}
+
+pub struct Population {
+    // Always sorted from best to worst
+    individuals: Vec<Individual>,
+    generation: usize,
+    opts: EvolutionOpts,
+    // The subtrees every mutation leaves alone, as the simulation does
+    frozen: Frozen,
+    rng: DeterministicRng,
+    log: BufWriter<File>,
+}
+
+impl Population {
+    /// Scores a first generation made of `first` and freshly generated genomes.
+    /// `first` stands in for the first generated genome, so it's usually the one a simulation would have started with.
+    pub fn new(first: Genome, opts: &EvolutionOpts, frozen: Frozen) -> Fallible<Self> {
+        if opts.population == 0 || opts.tournament_size == 0 || opts.scoring_steps == 0 {
+            return Err(format_err!(
+                "Population, tournament size and scoring steps all need to be at least 1"
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&opts.crossover_chance) {
+            return Err(format_err!(
+                "Crossover chance {} isn't between 0 and 1",
+                opts.crossover_chance
+            ));
+        }
+
+        if opts.elites > opts.population {
+            return Err(format_err!(
+                "Can't keep {} elites out of a population of {}",
+                opts.elites,
+                opts.population
+            ));
+        }
+
+        let mut rng = DeterministicRng::new();
+        let mut genomes: Vec<_> = (0..opts.population)
+            .map(|_| Genome::generate_rng(&mut rng, mutagen_state()))
+            .collect();
+        genomes[0] = first;
+
+        let mut log = BufWriter::new(File::create(&opts.evolution_log)?);
+        writeln!(
+            log,
+            "generation,best_fitness,mean_fitness,worst_fitness,best_node_count"
+        )?;
+
+        let mut population = Self {
+            individuals: genomes
+                .into_iter()
+                .map(|genome| Individual::new(genome, opts))
+                .collect(),
+            generation: 0,
+            opts: opts.clone(),
+            frozen,
+            rng,
+            log,
+        };
+
+        population.finish_generation()?;
+
+        Ok(population)
+    }
+
+    pub fn best(&self) -> &Individual {
+        &self.individuals[0]
+    }
+
+    /// Breeds and scores the next generation, keeping the elites as they are
+    pub fn next_generation(&mut self) -> Fallible<()> {
+        let mut next = self.individuals[..self.opts.elites].to_vec();
+
+        while next.len() < self.opts.population {
+            let mut child = if self.rng.gen_bool(self.opts.crossover_chance) {
+                let (a, b) = (self.select().clone(), self.select().clone());
+                a.crossover_rng(b, &mut self.rng, mutagen_state())
+            } else {
+                self.select().clone()
+            };
+
+            child.mutate_rng(&mut self.rng, mutagen_state().with_frozen(&self.frozen));
+
+            next.push(Individual::new(child, &self.opts));
+        }
+
+        self.individuals = next;
+        self.generation += 1;
+
+        self.finish_generation()
+    }
+
+    /// Picks the best of a few individuals chosen at random
+    fn select(&mut self) -> &Genome {
+        let candidates = self.individuals.len();
+
+        // The individuals are sorted, so the lowest index is the fittest
+        let winner = (0..self.opts.tournament_size)
+            .map(|_| self.rng.gen_range(0, candidates))
+            .min()
+            .unwrap();
+
+        &self.individuals[winner].genome
+    }
+
+    /// Sorts the generation and logs it
+    fn finish_generation(&mut self) -> Fallible<()> {
+        self.individuals
+            .sort_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap_or(Ordering::Equal));
+
+        let best = self.best().fitness;
+        let worst = self.individuals.last().unwrap().fitness;
+        let mean =
+            self.individuals.iter().map(|i| i.fitness).sum::<f64>() / self.individuals.len() as f64;
+        let best_node_count = self.best().genome.node_count();
+
+        info!(
+            "Generation {}: best {:.4}, mean {:.4}, worst {:.4}, best has {} nodes",
+            self.generation, best, mean, worst, best_node_count
+        );
+
+        writeln!(
+            self.log,
+            "{},{},{},{},{}",
+            self.generation, best, mean, worst, best_node_count
+        )?;
+        self.log.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Runs a genome for a few steps without a window, scoring from 0 to 1 how close its metrics stay to their rules
+pub fn score(genome: &Genome, steps: usize) -> f64 {
+    let mut simulation = Simulation::from_genome(genome.clone());
+    simulation.auto_mutate = false;
+    simulation.auto_reseed = false;
+
+    let mut total = 0.0;
+
+    for _ in 0..steps {
+        simulation.step();
+
+        total += CONSTS
+            .mutation_rules
+            .iter()
+            .zip(simulation.metric_averages())
+            .map(|(rule, &value)| 1.0 - rule.distance(value).min(1.0))
+            .sum::<f64>()
+            / CONSTS.mutation_rules.len().max(1) as f64;
+    }
+
+    total / steps as f64
+}
+
+/// Evolves a population on another thread, sending back the best genome of every generation
+pub fn spawn(first: Genome, opts: EvolutionOpts, frozen: Frozen) -> Receiver<Genome> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let result = Population::new(first, &opts, frozen).and_then(|mut population| loop {
+            // Stop once the window has closed and there's no one left to show the genomes to
+            if sender.send(population.best().genome.clone()).is_err() {
+                return Ok(());
+            }
+
+            population.next_generation()?;
+        });
+
+        if let Err(e) = result {
+            error!("Evolution stopped: {}", e);
+        }
+    });
+
+    receiver
+}
@@ -0,0 +1,323 @@
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use failure::Fallible;
+use log::debug;
+use mutagen::{Crossover, Generatable, Mutatable, NodeBudget, Traversable, Updatable};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::*,
+    dsl,
+    node::{
+        color_nodes::*, continuous_nodes::*, discrete_nodes::*, point_nodes::*, tree_summary,
+        Simplify,
+    },
+};
+
+/// The full set of root trees that drive a simulation, and the unit that gets saved to and loaded from disk
+#[derive(Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone)]
+pub struct Genome {
+    //The root node for the tree that computes the next screen state
+    pub root_node: Box<FloatColorNodes>,
+    pub root_angle_node: Box<SNFloatNodes>,
+    pub root_translation_node: Box<SNPointNodes>,
+    pub root_offset_node: Box<SNPointNodes>,
+    pub root_from_scale_node: Box<SNPointNodes>,
+    pub root_to_scale_node: Box<SNPointNodes>,
+
+    pub apply_angle_node: Box<BooleanNodes>,
+    pub apply_translation_node: Box<BooleanNodes>,
+    pub apply_offset_node: Box<BooleanNodes>,
+    pub apply_scale_node: Box<BooleanNodes>,
+}
+
+/// A genome along with the simulation state needed to resume it where it was saved.
+/// Saving only needs to borrow the genome, so it can be saved straight out of a running simulation.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedGenome<G = Genome> {
+    pub genome: G,
+    pub current_t: usize,
+    // Seeds can be larger than YAML integers, so they're stored the same way as in last_seed.txt
+    #[serde(with = "seed_string")]
+    pub seed: u128,
+}
+
+impl<G: Serialize> SavedGenome<G> {
+    /// Saves as JSON if the path ends in `.json`, and as YAML otherwise
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Fallible<()> {
+        let path = path.as_ref();
+
+        let data = if is_json(path) {
+            serde_json::to_string_pretty(self)?
+        } else {
+            serde_yaml::to_string(self)?
+        };
+
+        fs::write(path, data)?;
+
+        Ok(())
+    }
+
+    /// Saves to a new timestamped file in `dir`, returning its path
+    pub fn save_to_dir<P: AsRef<Path>>(&self, dir: P) -> Fallible<PathBuf> {
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.as_ref().join(format!(
+            "genome_{}.yml",
+            chrono::Local::now().format("%Y-%m-%d_%H-%M-%S%.3f")
+        ));
+
+        self.save(&path)?;
+
+        Ok(path)
+    }
+}
+
+impl SavedGenome {
+    /// Loads a file written by [`save`](SavedGenome::save), using the same rule to pick the format
+    pub fn load<P: AsRef<Path>>(path: P) -> Fallible<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)?;
+
+        Ok(if is_json(path) {
+            serde_json::from_str(&data)?
+        } else {
+            serde_yaml::from_str(&data)?
+        })
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new("json"))
+}
+
+/// A genome written by hand, where any root that's left out gets generated instead.
+/// It's read under the same name as a full genome, so a printed genome can be trimmed down to the roots worth keeping.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields, rename = "Genome")]
+pub struct PartialGenome {
+    pub root_node: Option<Box<FloatColorNodes>>,
+    pub root_angle_node: Option<Box<SNFloatNodes>>,
+    pub root_translation_node: Option<Box<SNPointNodes>>,
+    pub root_offset_node: Option<Box<SNPointNodes>>,
+    pub root_from_scale_node: Option<Box<SNPointNodes>>,
+    pub root_to_scale_node: Option<Box<SNPointNodes>>,
+
+    pub apply_angle_node: Option<Box<BooleanNodes>>,
+    pub apply_translation_node: Option<Box<BooleanNodes>>,
+    pub apply_offset_node: Option<Box<BooleanNodes>>,
+    pub apply_scale_node: Option<Box<BooleanNodes>>,
+
+    /// Paths of subtrees for mutation to leave alone, written as for --freeze
+    pub frozen: Vec<String>,
+}
+
+impl PartialGenome {
+    /// Loads a genome written in the [tree format](crate::dsl)
+    pub fn load<P: AsRef<Path>>(path: P) -> Fallible<Self> {
+        Ok(dsl::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Generates every missing root, each as the top of its own tree
+    pub fn complete_rng<R: Rng + ?Sized>(self, rng: &mut R, state: mutagen::State) -> Genome {
+        Genome {
+            root_node: self.root_node.unwrap_or_else(|| generate_root(rng, state)),
+
+            root_angle_node: self
+                .root_angle_node
+                .unwrap_or_else(|| generate_root(rng, state)),
+            root_translation_node: self
+                .root_translation_node
+                .unwrap_or_else(|| generate_root(rng, state)),
+            root_offset_node: self
+                .root_offset_node
+                .unwrap_or_else(|| generate_root(rng, state)),
+            root_from_scale_node: self
+                .root_from_scale_node
+                .unwrap_or_else(|| generate_root(rng, state)),
+            root_to_scale_node: self
+                .root_to_scale_node
+                .unwrap_or_else(|| generate_root(rng, state)),
+
+            apply_angle_node: self
+                .apply_angle_node
+                .unwrap_or_else(|| generate_root(rng, state)),
+            apply_translation_node: self
+                .apply_translation_node
+                .unwrap_or_else(|| generate_root(rng, state)),
+            apply_offset_node: self
+                .apply_offset_node
+                .unwrap_or_else(|| generate_root(rng, state)),
+            apply_scale_node: self
+                .apply_scale_node
+                .unwrap_or_else(|| generate_root(rng, state)),
+        }
+    }
+}
+
+/// Generates a root as the top of its own tree, with its own budget of nodes
+fn generate_root<T: Generatable, R: Rng + ?Sized>(rng: &mut R, state: mutagen::State) -> Box<T> {
+    let budget = NodeBudget::new(CONSTS.tree_node_budget);
+    Box::new(T::generate_rng(rng, state.with_budget(&budget)))
+}
+
+// Not derived, because every root is generated as the top of its own tree rather than one level down
+impl Generatable for Genome {
+    fn generate_rng<R: Rng + ?Sized>(rng: &mut R, state: mutagen::State) -> Self {
+        PartialGenome::default().complete_rng(rng, state)
+    }
+}
+
+// Not derived, because the root node is mutated every time along with one of the others
+impl Mutatable for Genome {
+    fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: mutagen::State) {
+        mutate_root(&mut self.root_node, "root_node", rng, state);
+
+        match rng.gen_range(0, 9) {
+            0 => mutate_root(&mut self.root_angle_node, "root_angle_node", rng, state),
+            1 => mutate_root(
+                &mut self.root_translation_node,
+                "root_translation_node",
+                rng,
+                state,
+            ),
+            2 => mutate_root(&mut self.root_offset_node, "root_offset_node", rng, state),
+            3 => mutate_root(
+                &mut self.root_from_scale_node,
+                "root_from_scale_node",
+                rng,
+                state,
+            ),
+            4 => mutate_root(
+                &mut self.root_to_scale_node,
+                "root_to_scale_node",
+                rng,
+                state,
+            ),
+            5 => mutate_root(&mut self.apply_angle_node, "apply_angle_node", rng, state),
+            6 => mutate_root(
+                &mut self.apply_translation_node,
+                "apply_translation_node",
+                rng,
+                state,
+            ),
+            7 => mutate_root(&mut self.apply_offset_node, "apply_offset_node", rng, state),
+            8 => mutate_root(&mut self.apply_scale_node, "apply_scale_node", rng, state),
+            _ => {
+                panic!();
+            }
+        }
+    }
+}
+
+/// Mutates the root in the field called `name`, unless it's been frozen.
+/// Each root is the top of its own tree, so moving into one doesn't deepen the state.
+fn mutate_root<T: Mutatable + Traversable, R: Rng + ?Sized>(
+    root: &mut T,
+    name: &str,
+    rng: &mut R,
+    state: mutagen::State,
+) {
+    let state = state.enter(name);
+
+    if state.is_frozen() {
+        debug!("{}: frozen", name);
+    } else {
+        // Anything the mutation generates has to fit in what's left of the budget after the nodes already there
+        let budget = NodeBudget::with_used(CONSTS.tree_node_budget, root.node_count());
+        root.mutate_rng(rng, state.with_budget(&budget));
+        debug!("{}: {}", name, tree_summary(root));
+    }
+}
+
+impl Simplify for Genome {
+    fn simplify(self) -> Self {
+        Self {
+            root_node: self.root_node.simplify(),
+
+            root_angle_node: self.root_angle_node.simplify(),
+            root_translation_node: self.root_translation_node.simplify(),
+            root_offset_node: self.root_offset_node.simplify(),
+            root_from_scale_node: self.root_from_scale_node.simplify(),
+            root_to_scale_node: self.root_to_scale_node.simplify(),
+
+            apply_angle_node: self.apply_angle_node.simplify(),
+            apply_translation_node: self.apply_translation_node.simplify(),
+            apply_offset_node: self.apply_offset_node.simplify(),
+            apply_scale_node: self.apply_scale_node.simplify(),
+        }
+    }
+}
+
+impl Genome {
+    /// Updates every root but the root node, as the derived update would, for when the root node is updated separately
+    pub fn update_transforms(&mut self, state: mutagen::State) {
+        self.root_angle_node.update(state.deepen());
+        self.root_translation_node.update(state.deepen());
+        self.root_offset_node.update(state.deepen());
+        self.root_from_scale_node.update(state.deepen());
+        self.root_to_scale_node.update(state.deepen());
+
+        self.apply_angle_node.update(state.deepen());
+        self.apply_translation_node.update(state.deepen());
+        self.apply_offset_node.update(state.deepen());
+        self.apply_scale_node.update(state.deepen());
+    }
+}
+
+mod seed_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(seed: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&seed.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{profile::mutagen_state, util::DeterministicRng};
+
+    const CROSSOVERS: usize = 64;
+
+    fn with_angle_node(tree: &str, rng: &mut DeterministicRng) -> Genome {
+        PartialGenome {
+            root_angle_node: Some(Box::new(dsl::from_str(tree).unwrap())),
+            ..PartialGenome::default()
+        }
+        .complete_rng(rng, mutagen_state())
+    }
+
+    #[test]
+    fn test_crossover_mixes_subtrees() {
+        let mut rng = DeterministicRng::seed_from_u64(0);
+        let a = with_angle_node(
+            "Multiply(child_a: Constant(value: 0.1), child_b: Constant(value: 0.2))",
+            &mut rng,
+        );
+        let b = with_angle_node(
+            "Multiply(child_a: Constant(value: 0.3), child_b: Constant(value: 0.4))",
+            &mut rng,
+        );
+        let parents = [a.clone(), b.clone()].map(|genome| format!("{:?}", genome.root_angle_node));
+
+        // A child whose angle tree matches neither parent's must have taken one child of the Multiply from each
+        assert!((0..CROSSOVERS).any(|_| {
+            let child = a
+                .clone()
+                .crossover_rng(b.clone(), &mut rng, mutagen_state());
+            !parents.contains(&format!("{:?}", child.root_angle_node))
+        }));
+    }
+}
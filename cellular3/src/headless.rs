@@ -0,0 +1,68 @@
+use std::{fs, path::Path};
+
+use failure::Fallible;
+use log::info;
+use mutagen::Frozen;
+
+use crate::{evolution::Population, genome::Genome, opts::Opts, simulation::*, HistoryStep};
+
+/// Runs the simulation without a window, writing every completed step to a numbered PNG file
+pub fn run(opts: &Opts) -> Fallible<()> {
+    let mut simulation = init_simulation(opts)?;
+
+    fs::create_dir_all(&opts.output_dir)?;
+
+    if opts.evolution.evolve {
+        return evolve(opts, simulation.genome.clone(), simulation.frozen.clone());
+    }
+
+    for frame in 0..opts.frames {
+        simulation.step();
+
+        let path = opts.output_dir.join(format!("frame_{:06}.png", frame));
+        save_history_step(simulation.last_history_step(), &path)?;
+
+        info!("Wrote frame {} to {}", frame, path.to_string_lossy());
+    }
+
+    Ok(())
+}
+
+/// Evolves a generation for every frame, writing how the best genome of each looks at the end of its scoring run
+fn evolve(opts: &Opts, first: Genome, frozen: Frozen) -> Fallible<()> {
+    let mut population = Population::new(first, &opts.evolution, frozen)?;
+
+    for frame in 0..opts.frames {
+        if frame > 0 {
+            population.next_generation()?;
+        }
+
+        let mut simulation = Simulation::from_genome(population.best().genome.clone());
+        simulation.auto_mutate = false;
+
+        for _ in 0..opts.evolution.scoring_steps {
+            simulation.step();
+        }
+
+        let path = opts.output_dir.join(format!("frame_{:06}.png", frame));
+        save_history_step(simulation.last_history_step(), &path)?;
+
+        info!("Wrote generation {} to {}", frame, path.to_string_lossy());
+    }
+
+    Ok(())
+}
+
+pub fn save_history_step<P: AsRef<Path>>(step: &HistoryStep, path: P) -> Fallible<()> {
+    let (height, width, _) = step.cell_array.dim();
+
+    image::save_buffer(
+        path,
+        step.cell_array.as_slice().unwrap(),
+        width as u32,
+        height as u32,
+        image::ColorType::RGBA(8),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,203 @@
+//! Lets a person steer the trees by eye, breeding from whichever of a grid of mutated variants they pick
+
+use std::path::{Path, PathBuf};
+
+use failure::{format_err, Fallible};
+use log::info;
+use mutagen::{Frozen, Mutatable};
+
+use crate::{
+    constants::*,
+    genome::{Genome, SavedGenome},
+    profile::mutagen_state,
+    simulation::Simulation,
+    util::{DeterministicRng, RNG_SEED},
+};
+
+pub struct Grid {
+    // Number of variants along each side
+    size: usize,
+    parent: Genome,
+    // Every earlier parent, oldest first, so choices can be undone
+    ancestors: Vec<Genome>,
+    // Subtrees that the variants all keep from the parent
+    frozen: Frozen,
+    // Indexed by row and then column
+    variants: Vec<Simulation>,
+    rng: DeterministicRng,
+}
+
+impl Grid {
+    /// Breeds a first set of `size` by `size` variants from `parent`
+    pub fn new(parent: Genome, size: usize, frozen: Frozen) -> Fallible<Self> {
+        if size == 0 {
+            return Err(format_err!(
+                "The grid needs at least one variant along each side"
+            ));
+        }
+
+        let mut grid = Self {
+            size,
+            parent,
+            ancestors: Vec::new(),
+            frozen,
+            variants: Vec::new(),
+            rng: DeterministicRng::new(),
+        };
+
+        grid.reroll();
+
+        Ok(grid)
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn parent(&self) -> &Genome {
+        &self.parent
+    }
+
+    pub fn variants(&self) -> &[Simulation] {
+        &self.variants
+    }
+
+    pub fn variants_mut(&mut self) -> &mut [Simulation] {
+        &mut self.variants
+    }
+
+    /// Replaces every variant with a new mutation of the parent
+    pub fn reroll(&mut self) {
+        // Each thumbnail gets its share of the usual cell array, so the grid costs about as much as a single simulation
+        let width = (CONSTS.cell_array_width / self.size).max(1);
+        let height = (CONSTS.cell_array_height / self.size).max(1);
+
+        let parent = &self.parent;
+        let rng = &mut self.rng;
+        let frozen = &self.frozen;
+
+        self.variants = (0..self.size * self.size)
+            .map(|_| {
+                let mut genome = parent.clone();
+                genome.mutate_rng(rng, mutagen_state().with_frozen(frozen));
+
+                let mut simulation = Simulation::thumbnail(genome, width, height);
+                // The person choosing is the only thing that decides when to mutate
+                simulation.auto_mutate = false;
+                simulation
+            })
+            .collect();
+    }
+
+    /// Makes the variant at `index` the parent of the next set of variants
+    pub fn choose(&mut self, index: usize) {
+        let chosen = self.variants[index].genome.clone();
+
+        info!("Chose variant {} as the next parent", index);
+
+        self.set_parent(chosen);
+    }
+
+    /// Breeds from a new parent, keeping the old one to undo back to
+    pub fn set_parent(&mut self, genome: Genome) {
+        self.ancestors
+            .push(std::mem::replace(&mut self.parent, genome));
+        self.reroll();
+    }
+
+    /// Goes back to the previous parent, returning false if there wasn't one
+    pub fn undo(&mut self) -> bool {
+        match self.ancestors.pop() {
+            Some(parent) => {
+                self.parent = parent;
+                self.reroll();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Which variant is under a point in a window of the given size, if any
+    pub fn variant_at(
+        &self,
+        x: f32,
+        y: f32,
+        window_width: f32,
+        window_height: f32,
+    ) -> Option<usize> {
+        if x < 0.0 || y < 0.0 || x >= window_width || y >= window_height {
+            return None;
+        }
+
+        let column = (x / window_width * self.size as f32) as usize;
+        let row = (y / window_height * self.size as f32) as usize;
+
+        Some(row.min(self.size - 1) * self.size + column.min(self.size - 1))
+    }
+
+    /// Computes one slice of the next history step of every variant, as `Simulation::update_slice` does
+    pub fn update_slice(&mut self, slice_index: usize) {
+        for variant in &mut self.variants {
+            variant.update_slice(slice_index);
+        }
+    }
+
+    pub fn finish_step(&mut self) {
+        for variant in &mut self.variants {
+            variant.finish_step();
+        }
+    }
+
+    /// Saves the parent to a new timestamped file in `dir`, returning its path
+    pub fn save_parent<P: AsRef<Path>>(&self, dir: P) -> Fallible<PathBuf> {
+        SavedGenome {
+            genome: &self.parent,
+            current_t: 0,
+            seed: *RNG_SEED.lock().unwrap(),
+        }
+        .save_to_dir(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mutagen::Generatable;
+
+    use super::*;
+
+    fn describe(genome: &Genome) -> String {
+        format!("{:?}", genome)
+    }
+
+    #[test]
+    fn test_choose_and_undo() {
+        let first = Genome::generate_rng(&mut DeterministicRng::new(), mutagen::State::default());
+        let mut grid = Grid::new(first.clone(), 2, Frozen::new()).unwrap();
+
+        assert_eq!(grid.variants().len(), 4);
+        assert!(!grid.undo());
+
+        let chosen = describe(&grid.variants()[3].genome);
+        grid.choose(3);
+        assert_eq!(describe(grid.parent()), chosen);
+
+        assert!(grid.undo());
+        assert_eq!(describe(grid.parent()), describe(&first));
+        assert!(!grid.undo());
+    }
+
+    #[test]
+    fn test_variant_at() {
+        let grid = Grid::new(
+            Genome::generate_rng(&mut DeterministicRng::new(), mutagen::State::default()),
+            3,
+            Frozen::new(),
+        )
+        .unwrap();
+
+        assert_eq!(grid.variant_at(10.0, 10.0, 300.0, 300.0), Some(0));
+        assert_eq!(grid.variant_at(150.0, 10.0, 300.0, 300.0), Some(1));
+        assert_eq!(grid.variant_at(299.0, 299.0, 300.0, 300.0), Some(8));
+        assert_eq!(grid.variant_at(300.0, 10.0, 300.0, 300.0), None);
+    }
+}
@@ -1,46 +1,49 @@
-use std::{
-    fs,
-    iter::Sum,
-    ops::{Add, AddAssign, Div},
-};
+use std::sync::mpsc::Receiver;
 
 use ggez::{
     conf::{WindowMode, WindowSetup},
-    event::{self, EventHandler, KeyCode},
-    graphics::{self, Color as GgColor, DrawParam, Image as GgImage, Rect, WHITE},
+    event::{self, EventHandler, KeyCode, KeyMods, MouseButton},
+    graphics::{self, Color as GgColor, DrawParam, Image as GgImage, Rect},
     input::keyboard,
     timer, Context, ContextBuilder, GameResult,
 };
+use failure::{format_err, Fallible};
 use log::{error, info};
-use mutagen::{Generatable, Mutatable};
-use ndarray::{s, Array3, ArrayView1, ArrayView3, ArrayViewMut1, Axis};
-use rand::prelude::*;
-use rayon::prelude::*;
+use mutagen::Traversable;
+use ndarray::{s, Array3, ArrayView1, ArrayView3};
 use structopt::StructOpt;
 
 use crate::{
     constants::*,
-    datatype::{
-        colors::{get_average, ByteColor},
-        continuous::*,
-        image::IMAGE_PRELOADER,
-        points::*,
-    },
-    node::{
-        color_nodes::*, continuous_nodes::*, discrete_nodes::*, point_nodes::*, Node,
-    },
-    opts::Opts,
-    updatestate::*,
-    util::{DeterministicRng, RNG_SEED},
+    datatype::{colors::ByteColor, image::IMAGE_PRELOADER, points::*},
+    genome::{Genome, SavedGenome},
+    interactive::Grid,
+    opts::{Command, Opts},
+    replay::{Replay, REPLAY},
+    simulation::*,
+    updatestate::CoordinateSet,
+    util::RNG_SEED,
 };
 
+mod bench;
 mod constants;
 mod datatype;
+mod dot;
+mod dsl;
+mod evolution;
+mod genome;
+mod headless;
+mod interactive;
+mod metric;
 mod node;
 mod opts;
 mod preloader;
+mod profile;
+mod replay;
+mod simulation;
 mod updatestate;
 mod util;
+mod vm;
 
 fn main() {
     std::env::set_var("RUST_BACKTRACE", "full");
@@ -48,6 +51,28 @@ fn main() {
     setup_logging();
 
     let opts = Opts::from_args();
+
+    if let Some(command) = &opts.command {
+        match run_command(command, &opts) {
+            Ok(_) => info!("Exited cleanly."),
+            Err(e) => error!("Error occurred: {}", e),
+        }
+
+        return;
+    }
+
+    if opts.headless {
+        // Eagerly initialize the image preloader rather than waiting for the first time it's used
+        IMAGE_PRELOADER.with(|_| ());
+
+        match headless::run(&opts) {
+            Ok(_) => info!("Exited cleanly."),
+            Err(e) => error!("Error occurred: {}", e),
+        }
+
+        return;
+    }
+
     let (mut ctx, mut event_loop) = ContextBuilder::new("cellular3", "CodeBunny")
         .window_mode(
             WindowMode::default()
@@ -68,6 +93,46 @@ fn main() {
     }
 }
 
+fn run_command(command: &Command, opts: &Opts) -> Fallible<()> {
+    match command {
+        Command::Dot { genome, output } => {
+            let saved = SavedGenome::load(genome)?;
+            let output = output
+                .clone()
+                .unwrap_or_else(|| genome.with_extension("dot"));
+
+            std::fs::write(&output, dot::to_dot(&saved.genome))?;
+
+            info!("Wrote graph to {}", output.to_string_lossy());
+        }
+        Command::Print { genome, output } => {
+            let trees = dsl::to_string(&SavedGenome::load(genome)?.genome)?;
+
+            match output {
+                Some(output) => std::fs::write(output, trees)?,
+                None => print!("{}", trees),
+            }
+        }
+        Command::Paths { genome } => {
+            let paths = SavedGenome::load(genome)?.genome.node_paths();
+
+            // The genome itself has an empty path, and freezing all of it isn't much use
+            for (path, name) in paths.into_iter().skip(1) {
+                println!("{} {}", path, name);
+            }
+        }
+        Command::Bench { trees, passes } => {
+            if let Some(seed) = opts.seed {
+                *RNG_SEED.lock().unwrap() = seed;
+            }
+
+            bench::run(*trees, *passes)?;
+        }
+    }
+
+    Ok(())
+}
+
 fn setup_logging() {
     let image_error_dispatch = fern::Dispatch::new()
         .level(log::LevelFilter::Off)
@@ -95,7 +160,8 @@ fn setup_logging() {
 #[derive(Debug)]
 pub struct HistoryStep {
     cell_array: Array3<u8>,
-    computed_texture: GgImage,
+    //Only present when running with a window
+    computed_texture: Option<GgImage>,
 
     rotation: f32,
     translation: SNPoint,
@@ -109,28 +175,34 @@ pub struct HistoryStep {
     apply_scale: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct History {
     history_steps: Vec<HistoryStep>,
 }
 
+impl HistoryStep {
+    fn new(array_width: usize, array_height: usize) -> Self {
+        Self {
+            cell_array: init_cell_array(array_width, array_height),
+            computed_texture: None,
+            rotation: 0.0,
+            translation: SNPoint::zero(),
+            offset: SNPoint::zero(),
+            from_scale: SNPoint::zero(),
+            to_scale: SNPoint::zero(),
+            apply_rotation: false,
+            apply_translation: false,
+            apply_offset: false,
+            apply_scale: false,
+        }
+    }
+}
+
 impl History {
-    fn new(ctx: &mut Context, array_width: usize, array_height: usize, size: usize) -> Self {
+    fn new(array_width: usize, array_height: usize, size: usize) -> Self {
         Self {
             history_steps: (0..size)
-                .map(|_| HistoryStep {
-                    cell_array: init_cell_array(array_width, array_height),
-                    computed_texture: GgImage::solid(ctx, 1, WHITE).unwrap(),
-                    rotation: 0.0,
-                    translation: SNPoint::zero(),
-                    offset: SNPoint::zero(),
-                    from_scale: SNPoint::zero(),
-                    to_scale: SNPoint::zero(),
-                    apply_rotation: false,
-                    apply_translation: false,
-                    apply_offset: false,
-                    apply_scale: false,
-                })
+                .map(|_| HistoryStep::new(array_width, array_height))
                 .collect(),
         }
     }
@@ -149,37 +221,67 @@ impl History {
             a: raw[3],
         }
     }
+
+    /// The width and height of every step's cell array
+    fn size(&self) -> (usize, usize) {
+        let (height, width, _) = self.history_steps[0].cell_array.dim();
+        (width, height)
+    }
+
+    /// The cell under a set of coordinates, whose x and y run from -1 to 1 across the array.
+    /// Can be one past the last cell on either axis, for the nodes that read from it to wrap.
+    fn cell_at(&self, coordinate_set: CoordinateSet) -> (isize, isize) {
+        let (width, height) = self.size();
+
+        // Rounded rather than truncated, since cell centres map back to whole numbers only up to float error
+        (
+            ((coordinate_set.x.into_inner() + 1.0) * 0.5 * width as f32).round() as isize,
+            ((coordinate_set.y.into_inner() + 1.0) * 0.5 * height as f32).round() as isize,
+        )
+    }
+
+    /// The cell array of the step before `t`, indexed by y, x and then color component
+    fn previous_cell_array(&self, t: usize) -> &Array3<u8> {
+        let h_len = self.history_steps.len();
+        &self.history_steps[(t + h_len - 1) % h_len].cell_array
+    }
+
+    /// The cell at `x`, `y` in the step before `t`, which is the last one finished while step `t` is computed.
+    /// Coordinates wrap around the edges of the array in both directions.
+    fn get_previous(&self, x: isize, y: isize, t: usize) -> ByteColor {
+        let (width, height) = self.size();
+
+        self.get(
+            x.rem_euclid(width as isize) as usize,
+            y.rem_euclid(height as isize) as usize,
+            t + self.history_steps.len() - 1,
+        )
+    }
+
+    #[cfg(test)]
+    fn set(&mut self, x: usize, y: usize, t: usize, color: ByteColor) {
+        let h_len = self.history_steps.len();
+        let mut raw = self.history_steps[t % h_len]
+            .cell_array
+            .slice_mut(s![y, x, ..]);
+
+        raw[0] = color.r;
+        raw[1] = color.g;
+        raw[2] = color.b;
+        raw[3] = color.a;
+    }
 }
 
 struct MyGame {
     //Screen bounds
     bounds: Rect,
 
-    history: History,
-    next_history_step: HistoryStep,
-
-    //rule_sets: [RuleSet; MAX_COLORS],
-
-    //The rolling total used to calculate the average per update instead of per slice
-    rolling_update_stat_total: UpdateStat,
-    //The average update stat over time, calculated by averaging rolling total and itself once an update
-    average_update_stat: UpdateStat,
-    //The root node for the tree that computes the next screen state
-    root_node: Box<FloatColorNodes>,
-    root_angle_node: Box<SNFloatNodes>,
-    root_translation_node: Box<SNPointNodes>,
-    root_offset_node: Box<SNPointNodes>,
-    root_from_scale_node: Box<SNPointNodes>,
-    root_to_scale_node: Box<SNPointNodes>,
-
-    apply_angle_node: Box<BooleanNodes>,
-    apply_translation_node: Box<BooleanNodes>,
-    apply_offset_node: Box<BooleanNodes>,
-    apply_scale_node: Box<BooleanNodes>,
-
-    tree_dirty: bool,
-    current_t: usize,
-    rng: DeterministicRng,
+    simulation: Simulation,
+    //The best genome of each generation, when evolving
+    evolution: Option<Receiver<Genome>>,
+    //The variants to choose from, which take the place of the simulation in interactive mode
+    grid: Option<Grid>,
+
     opts: Opts,
 }
 
@@ -188,166 +290,73 @@ impl MyGame {
         // Load/create resources such as images here.
         let (pixels_x, pixels_y) = ggez::graphics::size(ctx);
 
-        if let Some(seed) = opts.seed {
-            info!("Manually setting RNG seed");
-            *RNG_SEED.lock().unwrap() = seed;
-        }
+        let mut simulation = init_simulation(&opts)
+            .unwrap_or_else(|e| panic!("Failed to initialize simulation: {}", e));
 
-        fs::write("last_seed.txt", &RNG_SEED.lock().unwrap().to_string()).unwrap();
+        let evolution = if opts.evolution.evolve {
+            // Selection decides which trees to keep, so the simulation only shows them
+            simulation.tree_dirty = false;
+            simulation.auto_mutate = false;
 
-        let mut rng = DeterministicRng::new();
+            Some(evolution::spawn(
+                simulation.genome.clone(),
+                opts.evolution.clone(),
+                simulation.frozen.clone(),
+            ))
+        } else {
+            None
+        };
+
+        let grid = if opts.interactive {
+            Some(
+                Grid::new(
+                    simulation.genome.clone(),
+                    opts.grid_size,
+                    simulation.frozen.clone(),
+                )
+                .unwrap_or_else(|e| panic!("Failed to initialize grid: {}", e)),
+            )
+        } else {
+            None
+        };
 
         MyGame {
             bounds: Rect::new(0.0, 0.0, pixels_x, pixels_y),
-
-            next_history_step: HistoryStep {
-                cell_array: init_cell_array(CONSTS.cell_array_width, CONSTS.cell_array_height),
-                computed_texture: GgImage::solid(ctx, 1, WHITE).unwrap(),
-                rotation: 0.0,
-                translation: SNPoint::zero(),
-                offset: SNPoint::zero(),
-                from_scale: SNPoint::zero(),
-                to_scale: SNPoint::zero(),
-                apply_rotation: false,
-                apply_translation: false,
-                apply_offset: false,
-                apply_scale: false,
-            },
-            history: History::new(
-                ctx,
-                CONSTS.cell_array_width,
-                CONSTS.cell_array_height,
-                CONSTS.cell_array_history_length,
-            ),
-            rolling_update_stat_total: UpdateStat {
-                activity_value: 0.0,
-                alpha_value: 0.0,
-                local_similarity_value: 0.0,
-                global_similarity_value: 0.0,
-            },
-            average_update_stat: UpdateStat {
-                activity_value: 0.0,
-                alpha_value: 0.0,
-                local_similarity_value: 0.0,
-                global_similarity_value: 0.0,
-            },
-
-            // root_node: Box::new(FloatColorNodes::HSVMandelbrot{
-            //     child_power: Box::new(UNFloatNodes::generate_rng(
-            //         &mut rng,
-            //         mutagen::State::default(),
-            //     )),
-            //     child_offset: Box::new(SNPointNodes::generate_rng(
-            //         &mut rng,
-            //         mutagen::State::default(),
-            //     ))
-            // }),
-
-            root_node: Box::new(FloatColorNodes::generate_rng(
-                &mut rng,
-                mutagen::State::default(),
-            )),
-
-            root_angle_node: Box::new(SNFloatNodes::generate_rng(
-                &mut rng,
-                mutagen::State::default(),
-            )),
-            root_translation_node: Box::new(SNPointNodes::generate_rng(
-                &mut rng,
-                mutagen::State::default(),
-            )),
-            root_offset_node: Box::new(SNPointNodes::generate_rng(
-                &mut rng,
-                mutagen::State::default(),
-            )),
-            root_from_scale_node: Box::new(SNPointNodes::generate_rng(
-                &mut rng,
-                mutagen::State::default(),
-            )),
-            root_to_scale_node: Box::new(SNPointNodes::generate_rng(
-                &mut rng,
-                mutagen::State::default(),
-            )),
-
-            apply_angle_node: Box::new(BooleanNodes::generate_rng(
-                &mut rng,
-                mutagen::State::default(),
-            )),
-            apply_translation_node: Box::new(BooleanNodes::generate_rng(
-                &mut rng,
-                mutagen::State::default(),
-            )),
-            apply_offset_node: Box::new(BooleanNodes::generate_rng(
-                &mut rng,
-                mutagen::State::default(),
-            )),
-            apply_scale_node: Box::new(BooleanNodes::generate_rng(
-                &mut rng,
-                mutagen::State::default(),
-            )),
-
-            tree_dirty: true,
-            current_t: 0,
-            rng,
+            simulation,
+            evolution,
+            grid,
             opts,
         }
     }
-}
 
-#[derive(Default, Clone, Copy)]
-struct UpdateStat {
-    //Update stats are used to determine an approximation of the entropy of the current state
-    //Update stats contain two values:
-    //-Active cell count
-    //--If the active cell count is high, we have a lot of change
-    //--If the active cell count is low, we have a small amount of change
-    //-Neighbour similarity
-    //--If all neighbours are similar, we have close to a flat color
-    //--If all neighbours are distinct, we have visual noise
-    activity_value: f32,
-    alpha_value: f32,
-    local_similarity_value: f32,
-    global_similarity_value: f32,
-}
-
-impl Add<UpdateStat> for UpdateStat {
-    type Output = UpdateStat;
-
-    fn add(self, other: UpdateStat) -> UpdateStat {
-        UpdateStat {
-            activity_value: self.activity_value + other.activity_value,
-            alpha_value: self.alpha_value + other.alpha_value,
-            local_similarity_value: self.local_similarity_value + other.local_similarity_value,
-            global_similarity_value: self.global_similarity_value + other.global_similarity_value,
-        }
+    fn is_replaying_or_recording(&self) -> bool {
+        REPLAY.with(|r| match *r.borrow() {
+            Replay::Off => false,
+            Replay::Recording(_) | Replay::Playing(_) => true,
+        })
     }
-}
 
-impl Div<f32> for UpdateStat {
-    type Output = UpdateStat;
-
-    fn div(self, other: f32) -> UpdateStat {
-        UpdateStat {
-            activity_value: self.activity_value / other,
-            alpha_value: self.alpha_value / other,
-            local_similarity_value: self.local_similarity_value / other,
-            global_similarity_value: self.global_similarity_value / other,
+    /// Loads the most recently saved genome, relying on the timestamped filenames to sort by age
+    fn load_latest_genome(&mut self) -> Fallible<()> {
+        let path = util::collect_filenames(&self.opts.genome_dir)
+            .pop()
+            .ok_or_else(|| {
+                format_err!(
+                    "No saved genomes in {}",
+                    self.opts.genome_dir.to_string_lossy()
+                )
+            })?;
+
+        let saved = SavedGenome::load(&path)?;
+
+        match &mut self.grid {
+            Some(grid) => grid.set_parent(saved.genome),
+            None => self.simulation.load_genome(saved),
         }
-    }
-}
 
-impl Sum for UpdateStat {
-    fn sum<I>(iter: I) -> Self
-    where
-        I: Iterator<Item = Self>,
-    {
-        iter.fold(UpdateStat::default(), |a, b| a + b)
-    }
-}
+        info!("Loaded genome from {}", path.to_string_lossy());
 
-impl AddAssign<UpdateStat> for UpdateStat {
-    fn add_assign(&mut self, other: UpdateStat) {
-        *self = *self + other;
+        Ok(())
     }
 }
 
@@ -357,286 +366,153 @@ fn lerp(a: f32, b: f32, value: f32) -> f32 {
 
 impl EventHandler for MyGame {
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        if keyboard::is_key_pressed(ctx, KeyCode::Space) {
-            self.tree_dirty = true;
-        }
+        if let Some(grid) = &mut self.grid {
+            grid.update_slice(timer::ticks(ctx) % CONSTS.tics_per_update);
+
+            if timer::ticks(ctx) % CONSTS.tics_per_update == 0 {
+                grid.finish_step();
 
-        let current_t = self.current_t;
-
-        let slice_height = CONSTS.cell_array_height / CONSTS.tics_per_update;
-        let slice_y = (timer::ticks(ctx) % CONSTS.tics_per_update) * slice_height;
-        let slice_y_range = slice_y..slice_y + slice_height;
-
-        let mut new_update_slice =
-            self.next_history_step
-                .cell_array
-                .slice_mut(s![slice_y_range, .., ..]);
-        let new_update_iter = new_update_slice.lanes_mut(Axis(2));
-
-        let history = &self.history;
-
-        //let rule_sets = self.rule_sets;
-
-        let root_node = &self.root_node;
-
-        let update_step = |y, x, mut new: ArrayViewMut1<u8>| {
-            let total_cells = CONSTS.cell_array_width * CONSTS.cell_array_height;
-            // let neighbour_result =
-            //     get_alive_neighbours(cell_array_view, x as i32, y as i32 + slice_y);
-
-            let compute_result = root_node.compute(UpdateState {
-                coordinate_set: CoordinateSet {
-                    x: UNFloat::new(x as f32 / CONSTS.cell_array_width as f32).to_signed(),
-                    y: UNFloat::new(
-                        (y + slice_y as usize) as f32 / CONSTS.cell_array_height as f32,
-                    )
-                    .to_signed(),
-                    t: current_t as f32,
-                },
-                history,
-            }); //get_next_color(rule_sets, *current, neighbour_result.0);
-
-            let new_color = ByteColor::from(compute_result);
-
-            new[0] = new_color.r;
-            new[1] = new_color.g;
-            new[2] = new_color.b;
-            new[3] = new_color.a;
-
-            let current_color = history.get(x, y, current_t);
-            let older_color = history.get(x, y, usize::max(current_t, 1) - 1);
-
-            let local_offset = (thread_rng().gen_range(-1, 2), thread_rng().gen_range(-1, 2));
-            let local_color = history.get(
-                (x as i32 + local_offset.0)
-                    .max(0)
-                    .min(CONSTS.cell_array_width as i32 - 1) as usize,
-                (y as i32 + local_offset.1).min(CONSTS.cell_array_height as i32 - 1) as usize,
-                current_t,
-            );
-            let global_color = history.get(
-                random::<usize>() % CONSTS.cell_array_width,
-                random::<usize>() % CONSTS.cell_array_height,
-                current_t,
-            );
-
-            UpdateStat {
-                activity_value: (get_average(older_color.into())
-                    - get_average(current_color.into()))
-                .abs()
-                    / total_cells as f32,
-                alpha_value: (current_color.a as f32 / 256.0) / total_cells as f32,
-                local_similarity_value: (1.0
-                    - (get_average(local_color.into()) - get_average(current_color.into())).abs())
-                    / total_cells as f32,
-                global_similarity_value: (1.0
-                    - (get_average(global_color.into()) - get_average(current_color.into())).abs())
-                    / total_cells as f32,
+                for variant in grid.variants_mut() {
+                    let last_step = variant.last_history_step_mut();
+                    last_step.computed_texture =
+                        Some(compute_texture(ctx, last_step.cell_array.view()));
+                }
             }
-        };
 
-        let zip = ndarray::Zip::indexed(new_update_iter);
+            timer::yield_now();
 
-        let slice_update_stat: UpdateStat = if CONSTS.parallelize {
-            zip.into_par_iter()
-                .map(|((y, x), new)| update_step(y, x, new))
-                .sum()
-        } else {
-            let mut stat = UpdateStat::default();
-            zip.apply(|(y, x), new| stat += update_step(y, x, new));
-            stat
-        };
+            return Ok(());
+        }
+
+        if keyboard::is_key_pressed(ctx, KeyCode::Space) {
+            self.simulation.tree_dirty = true;
+        }
 
-        self.rolling_update_stat_total += slice_update_stat;
+        self.simulation
+            .update_slice(timer::ticks(ctx) % CONSTS.tics_per_update);
 
         if timer::ticks(ctx) % CONSTS.tics_per_update == 0 {
-            self.average_update_stat =
-                (self.average_update_stat + self.rolling_update_stat_total) / 2.0;
-
             dbg!(timer::fps(ctx));
 
-            self.rolling_update_stat_total = UpdateStat {
-                activity_value: 0.0,
-                alpha_value: 0.0,
-                local_similarity_value: 0.0,
-                global_similarity_value: 0.0,
-            };
+            self.simulation.finish_step();
 
-            if self.tree_dirty
-                || dbg!(f64::from(self.average_update_stat.activity_value)) < CONSTS.activity_value_lower_bound
-                || dbg!(f64::from(self.average_update_stat.alpha_value)) < CONSTS.alpha_value_lower_bound
-                || dbg!(f64::from(self.average_update_stat.local_similarity_value)) > CONSTS.local_similarity_upper_bound
-                || dbg!(f64::from(self.average_update_stat.global_similarity_value)) >= CONSTS.global_similarity_upper_bound
-                // || self.average_update_stat.activity_value > 0.5
+            let last_step = self.simulation.last_history_step_mut();
+            last_step.computed_texture = Some(compute_texture(ctx, last_step.cell_array.view()));
+
+            // Only the newest generation's best is worth showing if a few have finished since the last step.
+            // Evolving can't be combined with recording or replaying, since the log has no way to hold a whole genome.
+            if let Some(best) = self
+                .evolution
+                .as_ref()
+                .and_then(|evolution| evolution.try_iter().last())
             {
-                info!("====TIC: {} MUTATING TREE====", self.current_t);
-                self.root_node
-                    .mutate_rng(&mut self.rng, mutagen::State::default());
-                info!("{:#?}", &self.root_node);
-
-                match thread_rng().gen::<usize>() % 9 {
-                    0 => {
-                        self.root_angle_node
-                            .mutate_rng(&mut self.rng, mutagen::State::default());
-                        info!("{:#?}", &self.root_angle_node);
-                    }
-                    1 => {
-                        self.root_translation_node
-                            .mutate_rng(&mut self.rng, mutagen::State::default());
-                        info!("{:#?}", &self.root_translation_node);
-                    }
-                    2 => {
-                        self.root_offset_node
-                            .mutate_rng(&mut self.rng, mutagen::State::default());
-                        info!("{:#?}", &self.root_offset_node);
-                    }
-                    3 => {
-                        self.root_from_scale_node
-                            .mutate_rng(&mut self.rng, mutagen::State::default());
-                        info!("{:#?}", &self.root_from_scale_node);
-                    }
-                    4 => {
-                        self.root_to_scale_node
-                            .mutate_rng(&mut self.rng, mutagen::State::default());
-                        info!("{:#?}", &self.root_to_scale_node);
-                    }
-                    5 => {
-                        self.apply_angle_node
-                            .mutate_rng(&mut self.rng, mutagen::State::default());
-                        info!("{:#?}", &self.apply_angle_node);
-                    }
-                    6 => {
-                        self.apply_translation_node
-                            .mutate_rng(&mut self.rng, mutagen::State::default());
-                        info!("{:#?}", &self.apply_translation_node);
+                self.simulation.set_genome(best);
+            }
+        }
+
+        timer::yield_now();
+
+        Ok(())
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+        repeat: bool,
+    ) {
+        if repeat {
+            return;
+        }
+
+        if let Some(grid) = &mut self.grid {
+            match keycode {
+                KeyCode::R => grid.reroll(),
+                KeyCode::Z => {
+                    if !grid.undo() {
+                        info!("No earlier parent to undo to");
                     }
-                    7 => {
-                        self.apply_offset_node
-                            .mutate_rng(&mut self.rng, mutagen::State::default());
-                        info!("{:#?}", &self.apply_offset_node);
+                }
+                _ => {}
+            }
+        } else {
+            match keycode {
+                KeyCode::Z | KeyCode::Y if self.is_replaying_or_recording() => {
+                    info!("Can't step through genomes while recording or replaying, since the log only holds mutations")
+                }
+                KeyCode::Z => {
+                    if !self.simulation.undo_mutation() {
+                        info!("No earlier genome to step back to");
                     }
-                    8 => {
-                        self.apply_scale_node
-                            .mutate_rng(&mut self.rng, mutagen::State::default());
-                        info!("{:#?}", &self.apply_scale_node);
+                }
+                KeyCode::Y => {
+                    if !self.simulation.redo_mutation() {
+                        info!("No later genome to step forward to");
                     }
-                    _ => {
-                        panic!();
+                }
+                KeyCode::R => self.simulation.reseed_requested = true,
+                KeyCode::K if self.evolution.is_none() => {
+                    self.simulation.auto_mutate = !self.simulation.auto_mutate;
+
+                    if self.simulation.auto_mutate {
+                        info!("Unlocked, so breaking a mutation rule mutates the trees again");
+                    } else {
+                        info!("Locked, so breaking a mutation rule no longer mutates the trees");
                     }
                 }
-                self.tree_dirty = false;
+                _ => {}
             }
+        }
+
+        match keycode {
+            KeyCode::S => {
+                let saved = match &self.grid {
+                    Some(grid) => grid.save_parent(&self.opts.genome_dir),
+                    None => self.simulation.save_genome(&self.opts.genome_dir),
+                };
 
-            self.next_history_step.rotation = self
-                .root_angle_node
-                .compute(UpdateState {
-                    coordinate_set: CoordinateSet {
-                        x: SNFloat::new(0.0),
-                        y: SNFloat::new(0.0),
-                        t: self.current_t as f32,
-                    },
-                    history: &self.history,
-                })
-                .into_inner();
-            self.next_history_step.translation = self.root_translation_node.compute(UpdateState {
-                coordinate_set: CoordinateSet {
-                    x: SNFloat::new(0.0),
-                    y: SNFloat::new(0.0),
-                    t: self.current_t as f32,
-                },
-                history: &self.history,
-            });
-            self.next_history_step.offset = self.root_offset_node.compute(UpdateState {
-                coordinate_set: CoordinateSet {
-                    x: SNFloat::new(0.0),
-                    y: SNFloat::new(0.0),
-                    t: self.current_t as f32,
-                },
-                history: &self.history,
-            });
-            self.next_history_step.from_scale = self.root_from_scale_node.compute(UpdateState {
-                coordinate_set: CoordinateSet {
-                    x: SNFloat::new(0.0),
-                    y: SNFloat::new(0.0),
-                    t: self.current_t as f32,
-                },
-                history: &self.history,
-            });
-            self.next_history_step.to_scale = self.root_to_scale_node.compute(UpdateState {
-                coordinate_set: CoordinateSet {
-                    x: SNFloat::new(0.0),
-                    y: SNFloat::new(0.0),
-                    t: self.current_t as f32,
-                },
-                history: &self.history,
-            });
-
-            self.next_history_step.apply_rotation = self
-                .apply_angle_node
-                .compute(UpdateState {
-                    coordinate_set: CoordinateSet {
-                        x: SNFloat::new(0.0),
-                        y: SNFloat::new(0.0),
-                        t: self.current_t as f32,
-                    },
-                    history: &self.history,
-                })
-                .into_inner();
-            self.next_history_step.apply_translation = self
-                .apply_translation_node
-                .compute(UpdateState {
-                    coordinate_set: CoordinateSet {
-                        x: SNFloat::new(0.0),
-                        y: SNFloat::new(0.0),
-                        t: self.current_t as f32,
-                    },
-                    history: &self.history,
-                })
-                .into_inner();
-            self.next_history_step.apply_offset = self
-                .apply_offset_node
-                .compute(UpdateState {
-                    coordinate_set: CoordinateSet {
-                        x: SNFloat::new(0.0),
-                        y: SNFloat::new(0.0),
-                        t: self.current_t as f32,
-                    },
-                    history: &self.history,
-                })
-                .into_inner();
-            self.next_history_step.apply_scale = self
-                .apply_scale_node
-                .compute(UpdateState {
-                    coordinate_set: CoordinateSet {
-                        x: SNFloat::new(0.0),
-                        y: SNFloat::new(0.0),
-                        t: self.current_t as f32,
-                    },
-                    history: &self.history,
-                })
-                .into_inner();
-
-            self.next_history_step.computed_texture =
-                compute_texture(ctx, self.next_history_step.cell_array.view());
-
-            // Rotate the buffers by swapping
-            let h_len = self.history.history_steps.len();
-            std::mem::swap(
-                &mut self.history.history_steps[current_t % h_len],
-                &mut self.next_history_step,
-            );
-
-            self.current_t += 1;
+                match saved {
+                    Ok(path) => info!("Saved genome to {}", path.to_string_lossy()),
+                    Err(e) => error!("Failed to save genome: {}", e),
+                }
+            }
+            KeyCode::L if self.is_replaying_or_recording() => {
+                info!("Can't load genomes while recording or replaying, since the log only holds mutations")
+            }
+            KeyCode::L => {
+                if let Err(e) = self.load_latest_genome() {
+                    error!("Failed to load genome: {}", e);
+                }
+            }
+            // Overriding this handler replaces ggez's default, which quits on escape
+            KeyCode::Escape => event::quit(ctx),
+            _ => {}
         }
+    }
 
-        timer::yield_now();
+    fn mouse_button_down_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if button != MouseButton::Left {
+            return;
+        }
 
-        Ok(())
+        if let Some(grid) = &mut self.grid {
+            if let Some(index) = grid.variant_at(x, y, self.bounds.w, self.bounds.h) {
+                grid.choose(index);
+            }
+        }
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
         graphics::clear(ctx, graphics::BLACK);
 
+        if let Some(grid) = &self.grid {
+            draw_grid(ctx, grid, self.bounds)?;
+            graphics::present(ctx)?;
+
+            return Ok(());
+        }
+
         let base_params = DrawParam::new().dest([0.0, 0.0]).scale([
             self.bounds.w as f32 / CONSTS.cell_array_width as f32,
             self.bounds.h as f32 / CONSTS.cell_array_height as f32,
@@ -656,9 +532,15 @@ impl EventHandler for MyGame {
                     //.powf(CONSTS.lerp_aggressiveness)
                     ;
 
-            let hist_len = self.history.history_steps.len();
-            let history_index = (self.current_t + i + hist_len - lerp_len) % hist_len;
-            let history_step = &self.history.history_steps[history_index];
+            let hist_len = self.simulation.history.history_steps.len();
+            let history_index = (self.simulation.current_t + i + hist_len - lerp_len) % hist_len;
+            let history_step = &self.simulation.history.history_steps[history_index];
+
+            let computed_texture = match &history_step.computed_texture {
+                Some(texture) => texture,
+                // Steps from before the first update have nothing to draw yet
+                None => continue,
+            };
 
             let dest_offset_x = if history_step.apply_translation {
                 (CONSTS.initial_window_width
@@ -710,7 +592,7 @@ impl EventHandler for MyGame {
 
             ggez::graphics::draw(
                 ctx,
-                &history_step.computed_texture,
+                computed_texture,
                 base_params
                     .color(GgColor::new(1.0, 1.0, 1.0, 1.0 - ((alpha * 2.0) - 1.0).abs()))
                     .dest([
@@ -734,6 +616,37 @@ impl EventHandler for MyGame {
     }
 }
 
+/// Draws the latest step of each variant into its own cell of the window, without any of the lerping between steps
+fn draw_grid(ctx: &mut Context, grid: &Grid, bounds: Rect) -> GameResult<()> {
+    let cell_width = bounds.w / grid.size() as f32;
+    let cell_height = bounds.h / grid.size() as f32;
+
+    for (index, variant) in grid.variants().iter().enumerate() {
+        let history_step = variant.last_history_step();
+
+        let computed_texture = match &history_step.computed_texture {
+            Some(texture) => texture,
+            // New variants have nothing to draw until their first step finishes
+            None => continue,
+        };
+
+        let (height, width, _) = history_step.cell_array.dim();
+
+        ggez::graphics::draw(
+            ctx,
+            computed_texture,
+            DrawParam::new()
+                .dest([
+                    (index % grid.size()) as f32 * cell_width,
+                    (index / grid.size()) as f32 * cell_height,
+                ])
+                .scale([cell_width / width as f32, cell_height / height as f32]),
+        )?;
+    }
+
+    Ok(())
+}
+
 fn init_cell_array(width: usize, height: usize) -> Array3<u8> {
     Array3::from_shape_fn(
         (height, width, 4),
@@ -0,0 +1,321 @@
+//! Measurements of a whole completed history step, used to tell when the trees have stopped producing anything interesting
+
+use std::collections::HashMap;
+
+use image::{png::PNGEncoder, ColorType};
+use ndarray::{Array2, Axis};
+use serde::Deserialize;
+
+use crate::{
+    datatype::colors::{get_average, ByteColor, FloatColor},
+    HistoryStep,
+};
+
+/// How many bits of each of red, green and blue to keep when sorting colors into a histogram
+const HISTOGRAM_BITS: u32 = 3;
+/// How far apart the brightness of two neighbouring cells has to be to count as an edge
+const EDGE_THRESHOLD: f32 = 0.1;
+
+pub trait Metric {
+    /// Measures `step`, given the step before it for metrics that compare the two.
+    /// Every built in metric comes out between 0 and 1.
+    fn measure(&self, step: &HistoryStep, previous: &HistoryStep) -> f64;
+}
+
+/// How much the cells changed brightness since the previous step, on average
+pub struct Activity;
+
+impl Metric for Activity {
+    fn measure(&self, step: &HistoryStep, previous: &HistoryStep) -> f64 {
+        let (brightness, previous_brightness) = (brightness(step), brightness(previous));
+
+        mean(
+            brightness
+                .iter()
+                .zip(previous_brightness.iter())
+                .map(|(a, b)| (a - b).abs()),
+        )
+    }
+}
+
+/// How opaque the cells are, on average
+pub struct Alpha;
+
+impl Metric for Alpha {
+    fn measure(&self, step: &HistoryStep, _previous: &HistoryStep) -> f64 {
+        mean(cells(step).map(|color| color.a as f32 / 256.0))
+    }
+}
+
+/// How alike each cell is to the cells to its right and below it, on average
+pub struct LocalSimilarity;
+
+impl Metric for LocalSimilarity {
+    fn measure(&self, step: &HistoryStep, _previous: &HistoryStep) -> f64 {
+        mean(neighbour_differences(&brightness(step)).map(|difference| 1.0 - difference))
+    }
+}
+
+/// How alike each cell is to the average of the whole step, on average
+pub struct GlobalSimilarity;
+
+impl Metric for GlobalSimilarity {
+    fn measure(&self, step: &HistoryStep, _previous: &HistoryStep) -> f64 {
+        let brightness = brightness(step);
+        let average = brightness.mean().unwrap_or(0.0);
+
+        mean(brightness.iter().map(|value| 1.0 - (value - average).abs()))
+    }
+}
+
+/// The entropy of a histogram of the colors in the step, where 0 is a single flat color
+/// and 1 is every color in the histogram equally often
+pub struct ColorEntropy;
+
+impl Metric for ColorEntropy {
+    fn measure(&self, step: &HistoryStep, _previous: &HistoryStep) -> f64 {
+        let shift = 8 - HISTOGRAM_BITS;
+        let mut histogram: HashMap<(u8, u8, u8), usize> = HashMap::new();
+
+        for color in cells(step) {
+            *histogram
+                .entry((color.r >> shift, color.g >> shift, color.b >> shift))
+                .or_insert(0) += 1;
+        }
+
+        let total = histogram.values().sum::<usize>() as f64;
+        let entropy: f64 = histogram
+            .values()
+            .map(|&count| {
+                let p = count as f64 / total;
+                -p * p.log2()
+            })
+            .sum();
+
+        entropy / (3 * HISTOGRAM_BITS) as f64
+    }
+}
+
+/// The fraction of cells whose brightness jumps by more than `EDGE_THRESHOLD` from their right or lower neighbour
+pub struct EdgeDensity;
+
+impl Metric for EdgeDensity {
+    fn measure(&self, step: &HistoryStep, _previous: &HistoryStep) -> f64 {
+        mean(neighbour_differences(&brightness(step)).map(|difference| {
+            if difference > EDGE_THRESHOLD {
+                1.0
+            } else {
+                0.0
+            }
+        }))
+    }
+}
+
+/// How big the step comes out as a PNG compared to its raw size, which is small for flat or repetitive images and large for noise
+pub struct CompressionRatio;
+
+impl Metric for CompressionRatio {
+    fn measure(&self, step: &HistoryStep, _previous: &HistoryStep) -> f64 {
+        let (height, width, _) = step.cell_array.dim();
+        let raw: Vec<u8> = step.cell_array.iter().copied().collect();
+        let mut png = Vec::new();
+
+        match PNGEncoder::new(&mut png).encode(
+            &raw,
+            width as u32,
+            height as u32,
+            ColorType::RGBA(8),
+        ) {
+            Ok(()) => (png.len() as f64 / raw.len() as f64).min(1.0),
+            // Encoding into memory can't run out of space, so this would be a bug in the encoder
+            Err(e) => panic!("Failed to encode a history step as a PNG: {}", e),
+        }
+    }
+}
+
+/// Names the built in metrics, for choosing them in constants.yml
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Activity,
+    Alpha,
+    LocalSimilarity,
+    GlobalSimilarity,
+    ColorEntropy,
+    EdgeDensity,
+    CompressionRatio,
+}
+
+impl MetricKind {
+    pub fn metric(self) -> &'static dyn Metric {
+        match self {
+            MetricKind::Activity => &Activity,
+            MetricKind::Alpha => &Alpha,
+            MetricKind::LocalSimilarity => &LocalSimilarity,
+            MetricKind::GlobalSimilarity => &GlobalSimilarity,
+            MetricKind::ColorEntropy => &ColorEntropy,
+            MetricKind::EdgeDensity => &EdgeDensity,
+            MetricKind::CompressionRatio => &CompressionRatio,
+        }
+    }
+}
+
+/// Keeps a metric between two bounds, mutating the trees whenever it strays outside them.
+/// The same rules can reseed the cell array instead, when they're listed as reseed rules.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct MutationRule {
+    pub metric: MetricKind,
+    pub lower_bound: f64,
+    pub upper_bound: f64,
+}
+
+impl MutationRule {
+    pub fn allows(&self, value: f64) -> bool {
+        value >= self.lower_bound && value <= self.upper_bound
+    }
+
+    /// How far outside the bounds a value is, or 0 if it's inside them
+    pub fn distance(&self, value: f64) -> f64 {
+        (self.lower_bound - value)
+            .max(value - self.upper_bound)
+            .max(0.0)
+    }
+}
+
+fn cells(step: &HistoryStep) -> impl Iterator<Item = ByteColor> + '_ {
+    step.cell_array
+        .lanes(Axis(2))
+        .into_iter()
+        .map(|raw| ByteColor {
+            r: raw[0],
+            g: raw[1],
+            b: raw[2],
+            a: raw[3],
+        })
+}
+
+/// The brightness of every cell from 0 to 1, indexed by y and then x
+fn brightness(step: &HistoryStep) -> Array2<f32> {
+    step.cell_array.map_axis(Axis(2), |raw| {
+        get_average(FloatColor::from(ByteColor {
+            r: raw[0],
+            g: raw[1],
+            b: raw[2],
+            a: raw[3],
+        }))
+    })
+}
+
+/// How far the brightness of each cell is from its right and lower neighbours, where it has them
+fn neighbour_differences(brightness: &Array2<f32>) -> impl Iterator<Item = f32> + '_ {
+    let (height, width) = brightness.dim();
+
+    brightness.indexed_iter().flat_map(move |((y, x), &value)| {
+        let right = if x + 1 < width {
+            Some((value - brightness[[y, x + 1]]).abs())
+        } else {
+            None
+        };
+        let below = if y + 1 < height {
+            Some((value - brightness[[y + 1, x]]).abs())
+        } else {
+            None
+        };
+
+        right.into_iter().chain(below)
+    })
+}
+
+fn mean<I: Iterator<Item = f32>>(values: I) -> f64 {
+    let (total, count) = values.fold((0.0, 0usize), |(total, count), value| {
+        (total + f64::from(value), count + 1)
+    });
+
+    if count == 0 {
+        0.0
+    } else {
+        total / count as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 32;
+    const HEIGHT: usize = 16;
+
+    fn step_from<F: Fn(usize, usize) -> ByteColor>(color: F) -> HistoryStep {
+        let mut step = HistoryStep::new(WIDTH, HEIGHT);
+
+        for ((y, x), mut raw) in step
+            .cell_array
+            .lanes_mut(Axis(2))
+            .into_iter()
+            .enumerate()
+            .map(|(i, raw)| ((i / WIDTH, i % WIDTH), raw))
+        {
+            let color = color(x, y);
+            raw[0] = color.r;
+            raw[1] = color.g;
+            raw[2] = color.b;
+            raw[3] = color.a;
+        }
+
+        step
+    }
+
+    fn gray(value: u8) -> ByteColor {
+        ByteColor {
+            r: value,
+            g: value,
+            b: value,
+            a: 255,
+        }
+    }
+
+    #[test]
+    fn test_flat_step() {
+        let step = step_from(|_, _| gray(128));
+
+        assert_eq!(Activity.measure(&step, &step), 0.0);
+        assert_eq!(LocalSimilarity.measure(&step, &step), 1.0);
+        assert_eq!(GlobalSimilarity.measure(&step, &step), 1.0);
+        assert_eq!(ColorEntropy.measure(&step, &step), 0.0);
+        assert_eq!(EdgeDensity.measure(&step, &step), 0.0);
+    }
+
+    #[test]
+    fn test_checkerboard_step() {
+        let step = step_from(|x, y| gray(if (x + y) % 2 == 0 { 0 } else { 255 }));
+        let inverted = step_from(|x, y| gray(if (x + y) % 2 == 0 { 255 } else { 0 }));
+
+        // Colors only reach 255 / 256 of full brightness
+        assert!(Activity.measure(&step, &inverted) > 0.99);
+        assert!(LocalSimilarity.measure(&step, &step) < 0.01);
+        assert!((ColorEntropy.measure(&step, &step) - 1.0 / 9.0).abs() < 1e-9);
+        assert_eq!(EdgeDensity.measure(&step, &step), 1.0);
+    }
+
+    #[test]
+    fn test_rule_distance() {
+        let rule = MutationRule {
+            metric: MetricKind::Activity,
+            lower_bound: 0.25,
+            upper_bound: 0.75,
+        };
+
+        assert_eq!(rule.distance(0.5), 0.0);
+        assert_eq!(rule.distance(0.75), 0.0);
+        assert_eq!(rule.distance(0.0), 0.25);
+        assert_eq!(rule.distance(1.0), 0.25);
+        assert!(rule.allows(0.25) && !rule.allows(0.8));
+    }
+
+    #[test]
+    fn test_compression_ratio() {
+        let flat = step_from(|_, _| gray(128));
+        let noisy = step_from(|x, y| gray(((x * 7919 + y * 104_729) % 251) as u8));
+
+        assert!(CompressionRatio.measure(&flat, &flat) < CompressionRatio.measure(&noisy, &noisy));
+    }
+}
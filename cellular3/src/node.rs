@@ -1,24 +1,141 @@
+pub mod automata_nodes;
 pub mod color_blend_nodes;
 pub mod color_nodes;
 pub mod continuous_nodes;
 pub mod coord_map_nodes;
 pub mod discrete_nodes;
+pub mod lenia_nodes;
 pub mod noise_nodes;
 pub mod point_nodes;
+pub mod reaction_diffusion_nodes;
 
-use crate::updatestate::UpdateState;
+use std::{cell::RefCell, collections::HashMap};
+
+use itertools::Itertools;
+use mutagen::Traversable;
+
+use crate::{
+    datatype::{continuous::SNFloat, reaction_diffusion::Concentrations},
+    updatestate::{CoordinateSet, UpdateState},
+    History,
+};
 
 pub trait Node {
     type Output;
 
     fn compute(&self, state: UpdateState) -> Self::Output;
+
+    /// Computes a whole row or tile of cells at once, pushing an output onto `out` for each coordinate set in order.
+    /// Nodes that can share work between cells override this, and everything else computes each cell on its own.
+    fn compute_batch(
+        &self,
+        coordinate_sets: &[CoordinateSet],
+        history: &History,
+        out: &mut Vec<Self::Output>,
+    ) {
+        compute_each(self, coordinate_sets, history, out);
+    }
+}
+
+/// The default [Node::compute_batch], for overrides to fall back on for the nodes they don't batch themselves
+pub fn compute_each<T: Node + ?Sized>(
+    node: &T,
+    coordinate_sets: &[CoordinateSet],
+    history: &History,
+    out: &mut Vec<T::Output>,
+) {
+    out.extend(coordinate_sets.iter().map(|&coordinate_set| {
+        node.compute(UpdateState {
+            coordinate_set,
+            history,
+        })
+    }));
+}
+
+/// Computes a batch into a new buffer, for nodes that need their children's outputs before computing their own
+pub fn batch<T: Node + ?Sized>(
+    node: &T,
+    coordinate_sets: &[CoordinateSet],
+    history: &History,
+) -> Vec<T::Output> {
+    let mut out = Vec::with_capacity(coordinate_sets.len());
+    node.compute_batch(coordinate_sets, history, &mut out);
+    out
+}
+
+/// What the simulation passes down as the context when it updates the program and then the genome.
+/// The history is moved in for the update rather than borrowed, since a context can't hold any references.
+#[derive(Default)]
+pub struct UpdateContext {
+    pub history: History,
+    /// The tic of the next step to be computed
+    pub t: usize,
+    /// Set while updating the genome after the program compiled from it, so nodes that work over whole grids
+    /// take what their copy in the program worked out instead of doing it all again
+    pub following: bool,
+    /// The grid of every reaction-diffusion node updated so far, by [Concentrations::id]
+    pub reacted: RefCell<HashMap<u64, Concentrations>>,
+}
+
+/// What nodes that keep state between steps compute their inputs with when they're updated,
+/// which is the centre of the cell array at the next step to be computed, as for the roots of the genome other than the root node.
+/// None unless an [UpdateContext] was passed down, as the simulation does.
+pub fn update_state<'a>(state: &mutagen::State<'a>) -> Option<UpdateState<'a>> {
+    let context = state.context::<UpdateContext>()?;
+
+    Some(UpdateState {
+        coordinate_set: CoordinateSet {
+            x: SNFloat::new(0.0),
+            y: SNFloat::new(0.0),
+            t: context.t as f32,
+        },
+        history: &context.history,
+    })
+}
+
+/// Rewrites a tree into one that computes the same output with less work,
+/// by folding constant subtrees and dropping nodes that leave their input unchanged
+pub trait Simplify {
+    fn simplify(self) -> Self;
+}
+
+impl<T: Simplify> Simplify for Box<T> {
+    fn simplify(self) -> Self {
+        Box::new((*self).simplify())
+    }
+}
+
+/// How many of the most common kinds of node to list in a [tree_summary]
+const SUMMARY_KINDS: usize = 5;
+
+/// A one line description of a tree's size and the kinds of node it's mostly made of, for logging
+pub fn tree_summary<T: Traversable + ?Sized>(node: &T) -> String {
+    let kinds = node
+        .node_histogram()
+        .into_iter()
+        .sorted_by(|(a_name, a_count), (b_name, b_count)| {
+            b_count.cmp(a_count).then(a_name.cmp(b_name))
+        })
+        .take(SUMMARY_KINDS)
+        .map(|(name, count)| format!("{} x{}", name, count))
+        .join(", ");
+
+    format!(
+        "{} nodes, depth {}, mostly {}",
+        node.node_count(),
+        node.max_depth(),
+        kinds
+    )
 }
 
 mod mutagen_functions {
     use crate::{constants::*, util::*};
 
     pub fn leaf_node_weight(state: &mutagen::State) -> f64 {
-        if state.depth < CONSTS.min_leaf_depth || state.depth > CONSTS.max_leaf_depth {
+        if state.budget_exhausted() {
+            // Leaves are all that's left to close off the open branches with
+            1.0
+        } else if state.depth < CONSTS.min_leaf_depth || state.depth > CONSTS.max_leaf_depth {
             0.0
         } else {
             map_range(
@@ -29,8 +146,16 @@ mod mutagen_functions {
         }
     }
 
+    /// For leaves that work through the whole cell array every step, which would slow everything down if they were as common as the rest
+    pub fn expensive_leaf_node_weight(state: &mutagen::State) -> f64 {
+        leaf_node_weight(state) * 0.1
+    }
+
     pub fn pipe_node_weight(state: &mutagen::State) -> f64 {
-        if state.depth < CONSTS.min_pipe_depth || state.depth > CONSTS.max_pipe_depth {
+        if state.budget_exhausted()
+            || state.depth < CONSTS.min_pipe_depth
+            || state.depth > CONSTS.max_pipe_depth
+        {
             0.0
         } else {
             1.0 - map_range(
@@ -42,7 +167,10 @@ mod mutagen_functions {
     }
 
     pub fn branch_node_weight(state: &mutagen::State) -> f64 {
-        if state.depth < CONSTS.min_branch_depth || state.depth > CONSTS.max_branch_depth {
+        if state.budget_exhausted()
+            || state.depth < CONSTS.min_branch_depth
+            || state.depth > CONSTS.max_branch_depth
+        {
             0.0
         } else {
             1.0 - map_range(
@@ -56,3 +184,334 @@ mod mutagen_functions {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use mutagen::{Generatable, Mutatable, Traversable, Updatable};
+    use rand::SeedableRng;
+
+    use super::{
+        automata_nodes::*, batch, color_blend_nodes::*, color_nodes::*, continuous_nodes::*,
+        coord_map_nodes::*, discrete_nodes::*, lenia_nodes::*, mutagen_functions::*,
+        noise_nodes::*, point_nodes::*, reaction_diffusion_nodes::*, Node, Simplify, UpdateContext,
+    };
+    use crate::{
+        constants::*,
+        datatype::{continuous::*, points::SNPoint},
+        dsl,
+        updatestate::{CoordinateSet, UpdateState},
+        util::DeterministicRng,
+        History,
+    };
+
+    const SEED: u64 = 0x5eed;
+    const MUTATIONS: usize = 50;
+    const BUDGETED_TREES: usize = 200;
+    /// The most child nodes any variant has, such as `FloatColorNodes::RGB`
+    const MAX_CHILD_NODES: usize = 4;
+    /// The most nodes a leaf can bring with it, such as `SNFloatNodes::NoiseFunction` along with its noise
+    const MAX_LEAF_NODES: usize = 2;
+
+    const SIMPLIFIED_TREES: usize = 50;
+    const BATCHED_TREES: usize = 50;
+    /// Samples per axis, so the grid includes both edges of the coordinate range
+    const SAMPLES: usize = 9;
+    const SAMPLE_TICS: [f32; 3] = [0.0, 7.0, 300.0];
+    /// How far a simplified tree may drift, since undoing an `InvertNormalised` can round differently
+    const TOLERANCE: f32 = 1e-4;
+
+    /// Generates a tree and mutates it repeatedly, recording every version of it along the way
+    fn generate_and_mutate<T: Generatable + Mutatable + Debug>() -> String {
+        let mut rng = DeterministicRng::seed_from_u64(SEED);
+        let mut node = T::generate_rng(&mut rng, mutagen::State::default());
+        let mut out = format!("{:?}", node);
+
+        for _ in 0..MUTATIONS {
+            node.mutate_rng(&mut rng, mutagen::State::default());
+            out.push_str(&format!("{:?}", node));
+        }
+
+        out
+    }
+
+    fn assert_deterministic<T: Generatable + Mutatable + Debug>() {
+        assert_eq!(
+            generate_and_mutate::<T>(),
+            generate_and_mutate::<T>(),
+            "{} isn't reproducible from its seed",
+            std::any::type_name::<T>()
+        );
+    }
+
+    #[test]
+    fn test_deterministic_generation() {
+        assert_deterministic::<AutomataNodes>();
+        assert_deterministic::<LeniaNodes>();
+        assert_deterministic::<ReactionDiffusionNodes>();
+        assert_deterministic::<ColorBlendNodes>();
+        assert_deterministic::<FloatColorNodes>();
+        assert_deterministic::<BitColorNodes>();
+        assert_deterministic::<ByteColorNodes>();
+        assert_deterministic::<AngleNodes>();
+        assert_deterministic::<SNFloatNodes>();
+        assert_deterministic::<UNFloatNodes>();
+        assert_deterministic::<CoordMapNodes>();
+        assert_deterministic::<BooleanNodes>();
+        assert_deterministic::<NibbleNodes>();
+        assert_deterministic::<ByteNodes>();
+        assert_deterministic::<UIntNodes>();
+        assert_deterministic::<SIntNodes>();
+        assert_deterministic::<NoiseNodes>();
+        assert_deterministic::<SNPointNodes>();
+    }
+
+    /// Generates trees on a budget, returning the most nodes any of them ended up with
+    fn most_nodes_on_budget<T: Generatable + Traversable>(limit: usize) -> usize {
+        let mut rng = DeterministicRng::seed_from_u64(SEED);
+
+        (0..BUDGETED_TREES)
+            .map(|_| {
+                let budget = mutagen::NodeBudget::new(limit);
+                let tree =
+                    T::generate_rng(&mut rng, mutagen::State::default().with_budget(&budget));
+                tree.node_count()
+            })
+            .max()
+            .unwrap()
+    }
+
+    /// The most nodes a tree can have on a budget of `limit`: the nodes generated before it runs out,
+    /// and a leaf in every child slot they leave open
+    fn budget_bound(limit: usize) -> usize {
+        limit + (limit * (MAX_CHILD_NODES - 1) + 1) * MAX_LEAF_NODES
+    }
+
+    #[test]
+    fn test_node_budget() {
+        let budget = mutagen::NodeBudget::with_used(10, 10);
+        let state = mutagen::State::default().with_budget(&budget);
+        assert_eq!(pipe_node_weight(&state), 0.0);
+        assert_eq!(branch_node_weight(&state), 0.0);
+        assert!(leaf_node_weight(&state) > 0.0);
+
+        for &limit in &[1, 2, 4, 8] {
+            for &(name, most) in &[
+                (
+                    "FloatColorNodes",
+                    most_nodes_on_budget::<FloatColorNodes>(limit),
+                ),
+                ("SNFloatNodes", most_nodes_on_budget::<SNFloatNodes>(limit)),
+                ("SNPointNodes", most_nodes_on_budget::<SNPointNodes>(limit)),
+                ("BooleanNodes", most_nodes_on_budget::<BooleanNodes>(limit)),
+            ] {
+                assert!(
+                    most <= budget_bound(limit),
+                    "{} grew to {} nodes on a budget of {}",
+                    name,
+                    most,
+                    limit
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_simplify_removes_dead_weight() {
+        let tree: UNFloatNodes = dsl::from_str(
+            "IfElse(
+                predicate: Not(child: Not(child: Constant(child: true))),
+                child_a: Multiply(
+                    child_a: InvertNormalised(child: InvertNormalised(child: FromGametic)),
+                    child_b: Constant(value: 1.0),
+                ),
+                child_b: Constant(value: 0.5),
+            )",
+        )
+        .unwrap();
+        let simplified = tree.simplify();
+        assert_eq!(simplified.node_count(), 1);
+        assert_eq!(simplified.node_name(), Some("UNFloatNodes::FromGametic"));
+
+        let tree: SNFloatNodes = dsl::from_str(
+            "ModifyState(
+                child: Invert(child: Invert(child: XRatio)),
+                child_state: Scale(x: Constant(value: 1.0), y: Constant(value: 1.0)),
+            )",
+        )
+        .unwrap();
+        let simplified = tree.simplify();
+        assert_eq!(simplified.node_count(), 1);
+        assert_eq!(simplified.node_name(), Some("SNFloatNodes::XRatio"));
+    }
+
+    /// Checks that computing rows of cells in batches gives exactly what computing each cell does
+    fn assert_batch_keeps_output<T>(history: &History)
+    where
+        T: Generatable + Node + Debug,
+        T::Output: Debug,
+    {
+        let mut rng = DeterministicRng::seed_from_u64(SEED);
+
+        for _ in 0..BATCHED_TREES {
+            let tree = T::generate_rng(&mut rng, mutagen::State::default());
+
+            for &t in SAMPLE_TICS.iter() {
+                for y in 0..SAMPLES {
+                    let row: Vec<_> = (0..SAMPLES)
+                        .map(|x| CoordinateSet {
+                            x: SNFloat::new(x as f32 / (SAMPLES - 1) as f32 * 2.0 - 1.0),
+                            y: SNFloat::new(y as f32 / (SAMPLES - 1) as f32 * 2.0 - 1.0),
+                            t,
+                        })
+                        .collect();
+
+                    let expected: Vec<_> = row
+                        .iter()
+                        .map(|&coordinate_set| {
+                            tree.compute(UpdateState {
+                                coordinate_set,
+                                history,
+                            })
+                        })
+                        .collect();
+
+                    // Not every output type can be compared, but they can all be printed
+                    assert_eq!(
+                        format!("{:?}", expected),
+                        format!("{:?}", batch(&tree, &row, history)),
+                        "batching {:?} changed its output at {:?}",
+                        tree,
+                        row
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_batch_keeps_output() {
+        let history = History::new(
+            CONSTS.cell_array_width,
+            CONSTS.cell_array_height,
+            CONSTS.cell_array_history_length,
+        );
+
+        assert_batch_keeps_output::<FloatColorNodes>(&history);
+        assert_batch_keeps_output::<BitColorNodes>(&history);
+        assert_batch_keeps_output::<ByteColorNodes>(&history);
+        assert_batch_keeps_output::<SNFloatNodes>(&history);
+        assert_batch_keeps_output::<CoordMapNodes>(&history);
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() <= TOLERANCE || (a.is_nan() && b.is_nan())
+    }
+
+    #[test]
+    fn test_simplify_keeps_output() {
+        let history = History::new(
+            CONSTS.cell_array_width,
+            CONSTS.cell_array_height,
+            CONSTS.cell_array_history_length,
+        );
+        let mut rng = DeterministicRng::seed_from_u64(SEED);
+
+        for _ in 0..SIMPLIFIED_TREES {
+            let tree = FloatColorNodes::generate_rng(&mut rng, mutagen::State::default());
+            let simplified = tree.clone().simplify();
+
+            for &t in SAMPLE_TICS.iter() {
+                for x in 0..SAMPLES {
+                    for y in 0..SAMPLES {
+                        let coordinate_set = CoordinateSet {
+                            x: SNFloat::new(x as f32 / (SAMPLES - 1) as f32 * 2.0 - 1.0),
+                            y: SNFloat::new(y as f32 / (SAMPLES - 1) as f32 * 2.0 - 1.0),
+                            t,
+                        };
+                        let state = UpdateState {
+                            coordinate_set,
+                            history: &history,
+                        };
+
+                        let (expected, actual) = (tree.compute(state), simplified.compute(state));
+
+                        assert!(
+                            close(expected.r, actual.r)
+                                && close(expected.g, actual.g)
+                                && close(expected.b, actual.b)
+                                && close(expected.a, actual.a),
+                            "simplifying {:?} changed its output at {:?} from {:?} to {:?}",
+                            tree,
+                            coordinate_set,
+                            expected,
+                            actual
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stateful_nodes_update() {
+        let context = UpdateContext {
+            history: History::new(
+                CONSTS.cell_array_width,
+                CONSTS.cell_array_height,
+                CONSTS.cell_array_history_length,
+            ),
+            t: 0,
+            ..UpdateContext::default()
+        };
+        let update = mutagen::State::default().with_context(&context);
+        let origin = UpdateState {
+            coordinate_set: CoordinateSet {
+                x: SNFloat::new(0.0),
+                y: SNFloat::new(0.0),
+                t: 0.0,
+            },
+            history: &context.history,
+        };
+        let full = || {
+            Box::new(UNFloatNodes::Constant {
+                value: UNFloat::new(1.0),
+            })
+        };
+
+        let mut phase = AngleNodes::PhaseAccumulator {
+            speed: full(),
+            phase: 0.0,
+        };
+        let mut smoothed = UNFloatNodes::Smoothed {
+            child: full(),
+            smoothing: UNFloat::new(0.5),
+            value: 0.0,
+        };
+        let mut walk = SNPointNodes::RandomWalk {
+            step_size: UNFloat::new(0.5),
+            position: SNPoint::zero(),
+        };
+
+        // Nothing moves without the history to compute the inputs against
+        phase.update(mutagen::State::default());
+        assert_eq!(phase.compute(origin).into_inner(), 0.0);
+
+        for _ in 0..2 {
+            phase.update(update);
+            smoothed.update(update);
+            walk.update(update);
+        }
+
+        assert!((phase.compute(origin).into_inner() - std::f32::consts::PI / 4.0).abs() < 1e-6);
+        assert_eq!(smoothed.compute(origin).into_inner(), 0.75);
+        assert_ne!(
+            walk.compute(origin).into_inner(),
+            SNPoint::zero().into_inner()
+        );
+
+        assert!(phase.is_stateful() && smoothed.is_stateful() && walk.is_stateful());
+        assert!(!AngleNodes::FromGametic.is_stateful());
+    }
+}
@@ -1,3 +1,173 @@
-pub enum AutomataNodes{
-    BinaryAutomata
-}
\ No newline at end of file
+use crate::{
+    datatype::{colors::*, neighbourhoods::*, rules::*},
+    node::Node,
+    updatestate::*,
+};
+use mutagen::{Crossover, Generatable, Mutatable, Traversable, Updatable};
+use serde::{Deserialize, Serialize};
+
+/// Cellular automata, which compute each cell from its neighbours in the last finished history step
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
+#[mutagen(mut_reroll = 0.1)]
+pub enum AutomataNodes {
+    /// Treats cells of one colour as alive and every other colour as dead, like Conway's Life does
+    BinaryAutomata {
+        color: BitColor,
+        rule: Rule,
+        neighbourhood: Neighbourhood,
+    },
+    /// Gives each colour its own rule, so colours can grow into each other
+    ColorAutomata {
+        rule_set: RuleSet,
+        neighbourhood: Neighbourhood,
+    },
+}
+
+impl Node for AutomataNodes {
+    type Output = BitColor;
+
+    fn compute(&self, state: UpdateState) -> Self::Output {
+        use AutomataNodes::*;
+
+        match self {
+            BinaryAutomata {
+                color,
+                rule,
+                neighbourhood,
+            } => {
+                let (current, neighbours) = count_neighbours(state, *neighbourhood);
+                let alive_neighbours = neighbours[color.to_index()];
+
+                if current == *color {
+                    if rule.dies(alive_neighbours) {
+                        BitColor::Black
+                    } else {
+                        current
+                    }
+                } else if rule.is_born(alive_neighbours) {
+                    *color
+                } else {
+                    current
+                }
+            }
+            ColorAutomata {
+                rule_set,
+                neighbourhood,
+            } => {
+                let (current, neighbours) = count_neighbours(state, *neighbourhood);
+                rule_set.next_color(current, &neighbours)
+            }
+        }
+    }
+}
+
+/// The colour of the cell under the coordinates in the last finished step, and how many of its neighbours are each colour
+fn count_neighbours(
+    state: UpdateState,
+    neighbourhood: Neighbourhood,
+) -> (BitColor, [usize; MAX_COLORS]) {
+    let (x, y) = state.history.cell_at(state.coordinate_set);
+    let t = state.coordinate_set.t as usize;
+
+    let cell = |dx, dy| BitColor::from(state.history.get_previous(x + dx, y + dy, t));
+
+    let mut neighbours = [0; MAX_COLORS];
+
+    for &(dx, dy) in neighbourhood.offsets() {
+        neighbours[cell(dx, dy).to_index()] += 1;
+    }
+
+    (cell(0, 0), neighbours)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{datatype::continuous::*, History};
+
+    const SIZE: usize = 5;
+
+    // Born with 3 neighbours, and dies unless it has 2 or 3
+    fn life() -> Rule {
+        let mut rule = Rule {
+            life_neighbours: [false; MAX_NEIGHBOUR_ARRAY_COUNT],
+            death_neighbours: [true; MAX_NEIGHBOUR_ARRAY_COUNT],
+        };
+        rule.life_neighbours[3] = true;
+        rule.death_neighbours[2] = false;
+        rule.death_neighbours[3] = false;
+        rule
+    }
+
+    fn compute_cell(node: &AutomataNodes, history: &History, x: usize, y: usize) -> BitColor {
+        node.compute(UpdateState {
+            coordinate_set: CoordinateSet {
+                x: UNFloat::new(x as f32 / SIZE as f32).to_signed(),
+                y: UNFloat::new(y as f32 / SIZE as f32).to_signed(),
+                t: 1.0,
+            },
+            history,
+        })
+    }
+
+    #[test]
+    fn test_blinker() {
+        let mut history = History::new(SIZE, SIZE, 2);
+
+        // A vertical line of three, which Life turns into a horizontal one
+        for y in 1..4 {
+            history.set(2, y, 0, BitColor::White.get_color());
+        }
+
+        let node = AutomataNodes::BinaryAutomata {
+            color: BitColor::White,
+            rule: life(),
+            neighbourhood: Neighbourhood::Moore,
+        };
+
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let expected = if y == 2 && (1..4).contains(&x) {
+                    BitColor::White
+                } else {
+                    BitColor::Black
+                };
+
+                assert_eq!(
+                    compute_cell(&node, &history, x, y),
+                    expected,
+                    "at {}, {}",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_neighbourhoods_wrap() {
+        let mut history = History::new(SIZE, SIZE, 2);
+        history.set(0, 0, 0, BitColor::Red.get_color());
+
+        let count = |x, y, neighbourhood| {
+            count_neighbours(
+                UpdateState {
+                    coordinate_set: CoordinateSet {
+                        x: UNFloat::new(x as f32 / SIZE as f32).to_signed(),
+                        y: UNFloat::new(y as f32 / SIZE as f32).to_signed(),
+                        t: 1.0,
+                    },
+                    history: &history,
+                },
+                neighbourhood,
+            )
+            .1[BitColor::Red.to_index()]
+        };
+
+        assert_eq!(count(SIZE - 1, SIZE - 1, Neighbourhood::Moore), 1);
+        assert_eq!(count(SIZE - 1, SIZE - 1, Neighbourhood::VonNeumann), 0);
+        assert_eq!(count(SIZE - 1, 0, Neighbourhood::VonNeumann), 1);
+    }
+}
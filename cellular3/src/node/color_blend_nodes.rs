@@ -2,13 +2,17 @@ use crate::{
     datatype::{colors::*, continuous::*},
     node::{
         color_nodes::*, continuous_nodes::*, coord_map_nodes::*, discrete_nodes::*,
-        mutagen_functions::*, Node,
+        mutagen_functions::*, Node, Simplify,
     },
-    updatestate::UpdateState,
+    updatestate::{CoordinateSet, UpdateState},
+    vm::{Compile, Compiler, Instruction, Op, Reg, Registers, Step},
 };
-use mutagen::{Generatable, Mutatable};
+use mutagen::{Crossover, Generatable, Mutatable, Traversable, Updatable};
+use serde::{Deserialize, Serialize};
 
-#[derive(Generatable, Mutatable, Debug)]
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 #[mutagen(mut_reroll = 0.1)]
 pub enum ColorBlendNodes {
     #[mutagen(gen_weight = leaf_node_weight)]
@@ -144,7 +148,13 @@ impl Node for ColorBlendNodes {
                 color_b,
                 value,
             } => {
-                if UNFloat::generate().into_inner() < value.compute(state).into_inner() {
+                if UNFloat::generate_rng(
+                    &mut state.coordinate_set.get_rng(),
+                    mutagen::State::default(),
+                )
+                .into_inner()
+                    < value.compute(state).into_inner()
+                {
                     color_a.compute(state)
                 } else {
                     color_b.compute(state)
@@ -212,3 +222,203 @@ impl Node for ColorBlendNodes {
         }
     }
 }
+
+impl Simplify for ColorBlendNodes {
+    fn simplify(self) -> Self {
+        use ColorBlendNodes::*;
+
+        match self {
+            Invert { child } => Invert {
+                child: child.simplify(),
+            },
+            Dissolve {
+                color_a,
+                color_b,
+                value,
+            } => Dissolve {
+                color_a: color_a.simplify(),
+                color_b: color_b.simplify(),
+                value: value.simplify(),
+            },
+            Overlay { color_a, color_b } => Overlay {
+                color_a: color_a.simplify(),
+                color_b: color_b.simplify(),
+            },
+            ScreenDodge { color_a, color_b } => ScreenDodge {
+                color_a: color_a.simplify(),
+                color_b: color_b.simplify(),
+            },
+            ModifyState { child, child_state } => {
+                match (*child.simplify(), *child_state.simplify()) {
+                    (child @ Gray, _) => child,
+                    (child, child_state) if child_state.is_identity() => child,
+                    (child, child_state) => ModifyState {
+                        child: Box::new(child),
+                        child_state: Box::new(child_state),
+                    },
+                }
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => match *predicate.simplify() {
+                BooleanNodes::Constant { child } => {
+                    let branch = if child.into_inner() { child_a } else { child_b };
+                    *branch.simplify()
+                }
+                predicate => IfElse {
+                    predicate: Box::new(predicate),
+                    child_a: child_a.simplify(),
+                    child_b: child_b.simplify(),
+                },
+            },
+            Gray => self,
+        }
+    }
+}
+
+/// What a [ColorBlendNodes] node does once its children have been computed into registers
+#[derive(Debug)]
+pub enum ColorBlendOp {
+    Leaf(ColorBlendNodes),
+    Invert {
+        child: Reg<FloatColor>,
+    },
+    Dissolve {
+        color_a: Reg<FloatColor>,
+        color_b: Reg<FloatColor>,
+        value: Reg<UNFloat>,
+    },
+    Overlay {
+        color_a: Reg<FloatColor>,
+        color_b: Reg<FloatColor>,
+    },
+    ScreenDodge {
+        color_a: Reg<FloatColor>,
+        color_b: Reg<FloatColor>,
+    },
+}
+
+impl Op for ColorBlendOp {
+    type Output = FloatColor;
+
+    fn execute(&self, registers: &Registers, state: UpdateState) -> Self::Output {
+        use ColorBlendOp::*;
+
+        match self {
+            Leaf(node) => node.compute(state),
+            Invert { child } => {
+                let col = registers.get(*child);
+                FloatColor {
+                    r: 1.0 - col.r,
+                    g: 1.0 - col.g,
+                    b: 1.0 - col.b,
+                    a: 1.0 - col.a,
+                }
+            }
+            Dissolve {
+                color_a,
+                color_b,
+                value,
+            } => {
+                if UNFloat::generate_rng(
+                    &mut state.coordinate_set.get_rng(),
+                    mutagen::State::default(),
+                )
+                .into_inner()
+                    < registers.get(*value).into_inner()
+                {
+                    registers.get(*color_a)
+                } else {
+                    registers.get(*color_b)
+                }
+            }
+            Overlay { color_a, color_b } => {
+                let a_result = registers.get(*color_a);
+                let b_result = registers.get(*color_b);
+
+                FloatColor {
+                    r: if a_result.r < 0.5 {
+                        (2.0 * a_result.r * b_result.r).max(1.0)
+                    } else {
+                        1.0 - (2.0 * ((1.0 - a_result.r) * (1.0 - b_result.r)))
+                    },
+                    g: if a_result.g < 0.5 {
+                        (2.0 * a_result.g * b_result.g).max(1.0)
+                    } else {
+                        1.0 - (2.0 * ((1.0 - a_result.g) * (1.0 - b_result.g)))
+                    },
+                    b: if a_result.b < 0.5 {
+                        (2.0 * a_result.b * b_result.b).max(1.0)
+                    } else {
+                        1.0 - (2.0 * ((1.0 - a_result.b) * (1.0 - b_result.b)))
+                    },
+                    a: 1.0,
+                }
+            }
+            ScreenDodge { color_a, color_b } => {
+                let result_a = registers.get(*color_a);
+                let result_b = registers.get(*color_b);
+
+                FloatColor {
+                    r: 1.0 - ((1.0 - result_a.r) * (1.0 - result_b.r)),
+                    g: 1.0 - ((1.0 - result_a.g) * (1.0 - result_b.g)),
+                    b: 1.0 - ((1.0 - result_a.b) * (1.0 - result_b.b)),
+                    a: 1.0,
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, state: mutagen::State) {
+        if let ColorBlendOp::Leaf(node) = self {
+            node.update(state);
+        }
+    }
+
+    fn instruction(step: Step<Self>) -> Instruction {
+        Instruction::ColorBlend(step)
+    }
+}
+
+impl Compile for ColorBlendNodes {
+    fn compile(&self, compiler: &mut Compiler, coords: Reg<CoordinateSet>, out: Reg<FloatColor>) {
+        use ColorBlendNodes::*;
+
+        let op = match self {
+            Invert { child } => ColorBlendOp::Invert {
+                child: compiler.input(&**child, coords),
+            },
+            // Both colors get computed even though only one is picked, which costs time but gives the same output
+            Dissolve {
+                color_a,
+                color_b,
+                value,
+            } => ColorBlendOp::Dissolve {
+                color_a: compiler.input(&**color_a, coords),
+                color_b: compiler.input(&**color_b, coords),
+                value: compiler.input(&**value, coords),
+            },
+            Overlay { color_a, color_b } => ColorBlendOp::Overlay {
+                color_a: compiler.input(&**color_a, coords),
+                color_b: compiler.input(&**color_b, coords),
+            },
+            ScreenDodge { color_a, color_b } => ColorBlendOp::ScreenDodge {
+                color_a: compiler.input(&**color_a, coords),
+                color_b: compiler.input(&**color_b, coords),
+            },
+            ModifyState { child, child_state } => {
+                return compiler.modify_state(&**child, child_state, coords, out);
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => return compiler.if_else(predicate, &**child_a, &**child_b, coords, out),
+            Gray => ColorBlendOp::Leaf(self.clone()),
+        };
+
+        compiler.emit(op, coords, out);
+    }
+}
@@ -3,16 +3,22 @@ use nalgebra::Complex;
 
 use crate::{
     constants::*,
-    datatype::{colors::*, image::*},
+    datatype::{colors::*, continuous::*, discrete::*, image::*},
     node::{
-        color_blend_nodes::*, continuous_nodes::*, coord_map_nodes::*, discrete_nodes::*,
-        mutagen_functions::*, Node, point_nodes::*,
+        automata_nodes::*, color_blend_nodes::*, continuous_nodes::*, coord_map_nodes::*,
+        discrete_nodes::*, lenia_nodes::*, mutagen_functions::*, Node, point_nodes::*, Simplify,
+        batch, compute_each,
     },
-    updatestate::UpdateState,
+    updatestate::{CoordinateSet, UpdateState},
+    vm::{Compile, Compiler, Instruction, Op, Reg, Registers, Step},
+    History,
 };
-use mutagen::{Generatable, Mutatable};
+use mutagen::{Crossover, Generatable, Mutatable, Traversable, Updatable};
+use serde::{Deserialize, Serialize};
 
-#[derive(Generatable, Mutatable, Debug)]
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 #[mutagen(mut_reroll = 0.1)]
 pub enum FloatColorNodes {
     #[mutagen(gen_weight = leaf_node_weight)]
@@ -22,6 +28,8 @@ pub enum FloatColorNodes {
     FromImage { image: Image },
     #[mutagen(gen_weight = leaf_node_weight)]
     FromCellArray,
+    #[mutagen(gen_weight = expensive_leaf_node_weight)]
+    FromLenia { child: Box<LeniaNodes> },
 
     #[mutagen(gen_weight = pipe_node_weight)]
     Grayscale { child: Box<UNFloatNodes> },
@@ -97,6 +105,7 @@ impl Node for FloatColorNodes {
                     state.coordinate_set.t as usize,
                 )
                 .into(),
+            FromLenia { child } => child.compute(state),
             Grayscale { child } => {
                 let value = child.compute(state).into_inner() as f32;
                 FloatColor {
@@ -142,9 +151,214 @@ impl Node for FloatColorNodes {
             }
         }
     }
+
+    fn compute_batch(
+        &self,
+        coordinate_sets: &[CoordinateSet],
+        history: &History,
+        out: &mut Vec<Self::Output>,
+    ) {
+        use FloatColorNodes::*;
+
+        match self {
+            FromImage { image } => out.extend(
+                image
+                    .get_pixels_normalised(coordinate_sets)
+                    .map(FloatColor::from),
+            ),
+            ModifyState { child, child_state } => child.compute_batch(
+                &batch(child_state.as_ref(), coordinate_sets, history),
+                history,
+                out,
+            ),
+            _ => compute_each(self, coordinate_sets, history, out),
+        }
+    }
+}
+
+impl Simplify for FloatColorNodes {
+    fn simplify(self) -> Self {
+        use FloatColorNodes::*;
+
+        match self {
+            Grayscale { child } => Grayscale {
+                child: child.simplify(),
+            },
+            RGB { r, g, b, a } => RGB {
+                r: r.simplify(),
+                g: g.simplify(),
+                b: b.simplify(),
+                a: a.simplify(),
+            },
+            HSV { h, s, v, a } => HSV {
+                h: h.simplify(),
+                s: s.simplify(),
+                v: v.simplify(),
+                a: a.simplify(),
+            },
+            FromBlend { child } => FromBlend {
+                child: child.simplify(),
+            },
+            FromBitColor { child } => FromBitColor {
+                child: child.simplify(),
+            },
+            FromByteColor { child } => FromByteColor {
+                child: child.simplify(),
+            },
+            ModifyState { child, child_state } => {
+                match (*child.simplify(), *child_state.simplify()) {
+                    (child @ Gray, _) => child,
+                    (child, child_state) if child_state.is_identity() => child,
+                    (child, child_state) => ModifyState {
+                        child: Box::new(child),
+                        child_state: Box::new(child_state),
+                    },
+                }
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => match *predicate.simplify() {
+                BooleanNodes::Constant { child } => {
+                    let branch = if child.into_inner() { child_a } else { child_b };
+                    *branch.simplify()
+                }
+                predicate => IfElse {
+                    predicate: Box::new(predicate),
+                    child_a: child_a.simplify(),
+                    child_b: child_b.simplify(),
+                },
+            },
+            Gray | FromImage { .. } | FromCellArray | FromLenia { .. } => self,
+        }
+    }
+}
+
+/// What a [FloatColorNodes] node does once its children have been computed into registers
+#[derive(Debug)]
+pub enum FloatColorOp {
+    Leaf(FloatColorNodes),
+    Grayscale {
+        child: Reg<UNFloat>,
+    },
+    RGB {
+        r: Reg<UNFloat>,
+        g: Reg<UNFloat>,
+        b: Reg<UNFloat>,
+        a: Reg<UNFloat>,
+    },
+    HSV {
+        h: Reg<UNFloat>,
+        s: Reg<UNFloat>,
+        v: Reg<UNFloat>,
+        a: Reg<UNFloat>,
+    },
+    FromBitColor {
+        child: Reg<BitColor>,
+    },
+    FromByteColor {
+        child: Reg<ByteColor>,
+    },
+}
+
+impl Op for FloatColorOp {
+    type Output = FloatColor;
+
+    fn execute(&self, registers: &Registers, state: UpdateState) -> Self::Output {
+        use FloatColorOp::*;
+
+        match self {
+            Leaf(node) => node.compute(state),
+            Grayscale { child } => {
+                let value = registers.get(*child).into_inner() as f32;
+                FloatColor {
+                    r: value,
+                    g: value,
+                    b: value,
+                    a: value,
+                }
+            }
+            RGB { r, g, b, a } => FloatColor {
+                r: registers.get(*r).into_inner() as f32,
+                g: registers.get(*g).into_inner() as f32,
+                b: registers.get(*b).into_inner() as f32,
+                a: registers.get(*a).into_inner() as f32,
+            },
+            HSV { h, s, v, a } => {
+                let rgb: Rgb = Hsv::<Srgb, _>::from_components((
+                    RgbHue::from_degrees(registers.get(*h).into_inner() as f32 * 360.0),
+                    registers.get(*s).into_inner() as f32,
+                    registers.get(*v).into_inner() as f32,
+                ))
+                .into();
+
+                float_color_from_pallette_rgb(rgb, registers.get(*a).into_inner())
+            }
+            FromBitColor { child } => FloatColor::from(registers.get(*child)),
+            FromByteColor { child } => FloatColor::from(registers.get(*child)),
+        }
+    }
+
+    fn update(&mut self, state: mutagen::State) {
+        if let FloatColorOp::Leaf(node) = self {
+            node.update(state);
+        }
+    }
+
+    fn instruction(step: Step<Self>) -> Instruction {
+        Instruction::FloatColor(step)
+    }
+}
+
+impl Compile for FloatColorNodes {
+    fn compile(&self, compiler: &mut Compiler, coords: Reg<CoordinateSet>, out: Reg<FloatColor>) {
+        use FloatColorNodes::*;
+
+        let op = match self {
+            Grayscale { child } => FloatColorOp::Grayscale {
+                child: compiler.input(&**child, coords),
+            },
+            RGB { r, g, b, a } => FloatColorOp::RGB {
+                r: compiler.input(&**r, coords),
+                g: compiler.input(&**g, coords),
+                b: compiler.input(&**b, coords),
+                a: compiler.input(&**a, coords),
+            },
+            HSV { h, s, v, a } => FloatColorOp::HSV {
+                h: compiler.input(&**h, coords),
+                s: compiler.input(&**s, coords),
+                v: compiler.input(&**v, coords),
+                a: compiler.input(&**a, coords),
+            },
+            // Blends already output a FloatColor, so they can write straight to this node's register
+            FromBlend { child } => return child.compile(compiler, coords, out),
+            FromBitColor { child } => FloatColorOp::FromBitColor {
+                child: compiler.input(&**child, coords),
+            },
+            FromByteColor { child } => FloatColorOp::FromByteColor {
+                child: compiler.input(&**child, coords),
+            },
+            ModifyState { child, child_state } => {
+                return compiler.modify_state(&**child, child_state, coords, out);
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => return compiler.if_else(predicate, &**child_a, &**child_b, coords, out),
+            Gray | FromImage { .. } | FromCellArray | FromLenia { .. } => {
+                FloatColorOp::Leaf(self.clone())
+            }
+        };
+
+        compiler.emit(op, coords, out);
+    }
 }
 
-#[derive(Generatable, Mutatable, Debug)]
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 #[mutagen(mut_reroll = 0.1)]
 pub enum BitColorNodes {
     #[mutagen(gen_weight = leaf_node_weight)]
@@ -185,6 +399,8 @@ pub enum BitColorNodes {
     FromImage { image: Image },
     #[mutagen(gen_weight = leaf_node_weight)]
     FromCellArray,
+    #[mutagen(gen_weight = leaf_node_weight)]
+    FromAutomata { child: Box<AutomataNodes> },
 
     #[mutagen(gen_weight = pipe_node_weight)]
     FromUNFloat { child: Box<UNFloatNodes> },
@@ -252,6 +468,7 @@ impl Node for BitColorNodes {
                     state.coordinate_set.t as usize,
                 )
                 .into(),
+            FromAutomata { child } => child.compute(state),
             FromUNFloat { child } => BitColor::from_index(
                 (child.compute(state).into_inner() * 0.99 * (CONSTS.max_colors) as f32) as usize,
             ),
@@ -277,9 +494,281 @@ impl Node for BitColorNodes {
             }
         }
     }
+
+    fn compute_batch(
+        &self,
+        coordinate_sets: &[CoordinateSet],
+        history: &History,
+        out: &mut Vec<Self::Output>,
+    ) {
+        use BitColorNodes::*;
+
+        match self {
+            FromImage { image } => out.extend(
+                image
+                    .get_pixels_normalised(coordinate_sets)
+                    .map(BitColor::from),
+            ),
+            ModifyState { child, child_state } => child.compute_batch(
+                &batch(child_state.as_ref(), coordinate_sets, history),
+                history,
+                out,
+            ),
+            _ => compute_each(self, coordinate_sets, history, out),
+        }
+    }
+}
+
+impl Simplify for BitColorNodes {
+    fn simplify(self) -> Self {
+        use BitColorNodes::*;
+
+        match self {
+            GiveColor { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: BitColor::from_components(a.give_color(b)),
+                },
+                (child_a, child_b) => GiveColor {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            TakeColor { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: BitColor::from_components(a.take_color(b)),
+                },
+                (child_a, child_b) => TakeColor {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            XorColor { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: BitColor::from_components(a.xor_color(b)),
+                },
+                (child_a, child_b) => XorColor {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            EqColor { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: BitColor::from_components(a.eq_color(b)),
+                },
+                (child_a, child_b) => EqColor {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            FromComponents { r, g, b } => match (*r.simplify(), *g.simplify(), *b.simplify()) {
+                (
+                    BooleanNodes::Constant { child: r },
+                    BooleanNodes::Constant { child: g },
+                    BooleanNodes::Constant { child: b },
+                ) => Constant {
+                    value: BitColor::from_components([
+                        r.into_inner(),
+                        g.into_inner(),
+                        b.into_inner(),
+                    ]),
+                },
+                (r, g, b) => FromComponents {
+                    r: Box::new(r),
+                    g: Box::new(g),
+                    b: Box::new(b),
+                },
+            },
+            FromUNFloat { child } => FromUNFloat {
+                child: child.simplify(),
+            },
+            FromFloatColor { child } => FromFloatColor {
+                child: child.simplify(),
+            },
+            FromByteColor { child } => FromByteColor {
+                child: child.simplify(),
+            },
+            FromNibbleIndex { child } => match *child.simplify() {
+                NibbleNodes::Constant { value } => Constant {
+                    value: BitColor::from_index(value.into_inner() as usize % 8),
+                },
+                child => FromNibbleIndex {
+                    child: Box::new(child),
+                },
+            },
+            ModifyState { child, child_state } => {
+                match (*child.simplify(), *child_state.simplify()) {
+                    (child @ Constant { .. }, _) => child,
+                    (child, child_state) if child_state.is_identity() => child,
+                    (child, child_state) => ModifyState {
+                        child: Box::new(child),
+                        child_state: Box::new(child_state),
+                    },
+                }
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => match *predicate.simplify() {
+                BooleanNodes::Constant { child } => {
+                    let branch = if child.into_inner() { child_a } else { child_b };
+                    *branch.simplify()
+                }
+                predicate => IfElse {
+                    predicate: Box::new(predicate),
+                    child_a: child_a.simplify(),
+                    child_b: child_b.simplify(),
+                },
+            },
+            Constant { .. } | FromImage { .. } | FromCellArray | FromAutomata { .. } => self,
+        }
+    }
+}
+
+/// What a [BitColorNodes] node does once its children have been computed into registers
+#[derive(Debug)]
+pub enum BitColorOp {
+    Leaf(BitColorNodes),
+    GiveColor {
+        child_a: Reg<BitColor>,
+        child_b: Reg<BitColor>,
+    },
+    TakeColor {
+        child_a: Reg<BitColor>,
+        child_b: Reg<BitColor>,
+    },
+    XorColor {
+        child_a: Reg<BitColor>,
+        child_b: Reg<BitColor>,
+    },
+    EqColor {
+        child_a: Reg<BitColor>,
+        child_b: Reg<BitColor>,
+    },
+    FromComponents {
+        r: Reg<Boolean>,
+        g: Reg<Boolean>,
+        b: Reg<Boolean>,
+    },
+    FromUNFloat {
+        child: Reg<UNFloat>,
+    },
+    FromFloatColor {
+        child: Reg<FloatColor>,
+    },
+    FromByteColor {
+        child: Reg<ByteColor>,
+    },
+    FromNibbleIndex {
+        child: Reg<Nibble>,
+    },
+}
+
+impl Op for BitColorOp {
+    type Output = BitColor;
+
+    fn execute(&self, registers: &Registers, state: UpdateState) -> Self::Output {
+        use BitColorOp::*;
+
+        match self {
+            Leaf(node) => node.compute(state),
+            GiveColor { child_a, child_b } => BitColor::from_components(
+                registers.get(*child_a).give_color(registers.get(*child_b)),
+            ),
+            TakeColor { child_a, child_b } => BitColor::from_components(
+                registers.get(*child_a).take_color(registers.get(*child_b)),
+            ),
+            XorColor { child_a, child_b } => BitColor::from_components(
+                registers.get(*child_a).xor_color(registers.get(*child_b)),
+            ),
+            EqColor { child_a, child_b } => {
+                BitColor::from_components(registers.get(*child_a).eq_color(registers.get(*child_b)))
+            }
+            FromComponents { r, g, b } => BitColor::from_components([
+                registers.get(*r).into_inner(),
+                registers.get(*g).into_inner(),
+                registers.get(*b).into_inner(),
+            ]),
+            FromUNFloat { child } => BitColor::from_index(
+                (registers.get(*child).into_inner() * 0.99 * (CONSTS.max_colors) as f32) as usize,
+            ),
+            FromFloatColor { child } => BitColor::from_float_color(registers.get(*child)),
+            FromByteColor { child } => BitColor::from_byte_color(registers.get(*child)),
+            FromNibbleIndex { child } => {
+                BitColor::from_index(registers.get(*child).into_inner() as usize % 8)
+            }
+        }
+    }
+
+    fn update(&mut self, state: mutagen::State) {
+        if let BitColorOp::Leaf(node) = self {
+            node.update(state);
+        }
+    }
+
+    fn instruction(step: Step<Self>) -> Instruction {
+        Instruction::BitColor(step)
+    }
+}
+
+impl Compile for BitColorNodes {
+    fn compile(&self, compiler: &mut Compiler, coords: Reg<CoordinateSet>, out: Reg<BitColor>) {
+        use BitColorNodes::*;
+
+        let op = match self {
+            GiveColor { child_a, child_b } => BitColorOp::GiveColor {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            TakeColor { child_a, child_b } => BitColorOp::TakeColor {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            XorColor { child_a, child_b } => BitColorOp::XorColor {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            EqColor { child_a, child_b } => BitColorOp::EqColor {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            FromComponents { r, g, b } => BitColorOp::FromComponents {
+                r: compiler.input(&**r, coords),
+                g: compiler.input(&**g, coords),
+                b: compiler.input(&**b, coords),
+            },
+            FromUNFloat { child } => BitColorOp::FromUNFloat {
+                child: compiler.input(&**child, coords),
+            },
+            FromFloatColor { child } => BitColorOp::FromFloatColor {
+                child: compiler.input(&**child, coords),
+            },
+            FromByteColor { child } => BitColorOp::FromByteColor {
+                child: compiler.input(&**child, coords),
+            },
+            FromNibbleIndex { child } => BitColorOp::FromNibbleIndex {
+                child: compiler.input(&**child, coords),
+            },
+            ModifyState { child, child_state } => {
+                return compiler.modify_state(&**child, child_state, coords, out);
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => return compiler.if_else(predicate, &**child_a, &**child_b, coords, out),
+            Constant { .. } | FromImage { .. } | FromCellArray | FromAutomata { .. } => {
+                BitColorOp::Leaf(self.clone())
+            }
+        };
+
+        compiler.emit(op, coords, out);
+    }
 }
 
-#[derive(Generatable, Mutatable, Debug)]
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 #[mutagen(mut_reroll = 0.1)]
 pub enum ByteColorNodes {
     #[mutagen(gen_weight = leaf_node_weight)]
@@ -378,4 +867,169 @@ impl Node for ByteColorNodes {
             }
         }
     }
+
+    fn compute_batch(
+        &self,
+        coordinate_sets: &[CoordinateSet],
+        history: &History,
+        out: &mut Vec<Self::Output>,
+    ) {
+        use ByteColorNodes::*;
+
+        match self {
+            FromImage { image } => out.extend(image.get_pixels_normalised(coordinate_sets)),
+            ModifyState { child, child_state } => child.compute_batch(
+                &batch(child_state.as_ref(), coordinate_sets, history),
+                history,
+                out,
+            ),
+            _ => compute_each(self, coordinate_sets, history, out),
+        }
+    }
+}
+
+impl Simplify for ByteColorNodes {
+    fn simplify(self) -> Self {
+        use ByteColorNodes::*;
+
+        match self {
+            FromFloatColor { child } => FromFloatColor {
+                child: child.simplify(),
+            },
+            FromBitColor { child } => FromBitColor {
+                child: child.simplify(),
+            },
+            Decompose { r, g, b, a } => {
+                match (*r.simplify(), *g.simplify(), *b.simplify(), *a.simplify()) {
+                    (
+                        ByteNodes::Constant { value: r },
+                        ByteNodes::Constant { value: g },
+                        ByteNodes::Constant { value: b },
+                        ByteNodes::Constant { value: a },
+                    ) => Constant {
+                        value: ByteColor {
+                            r: r.into_inner(),
+                            g: g.into_inner(),
+                            b: b.into_inner(),
+                            a: a.into_inner(),
+                        },
+                    },
+                    (r, g, b, a) => Decompose {
+                        r: Box::new(r),
+                        g: Box::new(g),
+                        b: Box::new(b),
+                        a: Box::new(a),
+                    },
+                }
+            }
+            ModifyState { child, child_state } => {
+                match (*child.simplify(), *child_state.simplify()) {
+                    (child @ Constant { .. }, _) => child,
+                    (child, child_state) if child_state.is_identity() => child,
+                    (child, child_state) => ModifyState {
+                        child: Box::new(child),
+                        child_state: Box::new(child_state),
+                    },
+                }
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => match *predicate.simplify() {
+                BooleanNodes::Constant { child } => {
+                    let branch = if child.into_inner() { child_a } else { child_b };
+                    *branch.simplify()
+                }
+                predicate => IfElse {
+                    predicate: Box::new(predicate),
+                    child_a: child_a.simplify(),
+                    child_b: child_b.simplify(),
+                },
+            },
+            Constant { .. } | FromImage { .. } | FromCellArray | Mandelbrot => self,
+        }
+    }
+}
+
+/// What a [ByteColorNodes] node does once its children have been computed into registers
+#[derive(Debug)]
+pub enum ByteColorOp {
+    Leaf(ByteColorNodes),
+    FromFloatColor {
+        child: Reg<FloatColor>,
+    },
+    FromBitColor {
+        child: Reg<FloatColor>,
+    },
+    Decompose {
+        r: Reg<Byte>,
+        g: Reg<Byte>,
+        b: Reg<Byte>,
+        a: Reg<Byte>,
+    },
+}
+
+impl Op for ByteColorOp {
+    type Output = ByteColor;
+
+    fn execute(&self, registers: &Registers, state: UpdateState) -> Self::Output {
+        use ByteColorOp::*;
+
+        match self {
+            Leaf(node) => node.compute(state),
+            FromFloatColor { child } => registers.get(*child).into(),
+            FromBitColor { child } => registers.get(*child).into(),
+            Decompose { r, g, b, a } => ByteColor {
+                r: registers.get(*r).into_inner(),
+                g: registers.get(*g).into_inner(),
+                b: registers.get(*b).into_inner(),
+                a: registers.get(*a).into_inner(),
+            },
+        }
+    }
+
+    fn update(&mut self, state: mutagen::State) {
+        if let ByteColorOp::Leaf(node) = self {
+            node.update(state);
+        }
+    }
+
+    fn instruction(step: Step<Self>) -> Instruction {
+        Instruction::ByteColor(step)
+    }
+}
+
+impl Compile for ByteColorNodes {
+    fn compile(&self, compiler: &mut Compiler, coords: Reg<CoordinateSet>, out: Reg<ByteColor>) {
+        use ByteColorNodes::*;
+
+        let op = match self {
+            FromFloatColor { child } => ByteColorOp::FromFloatColor {
+                child: compiler.input(&**child, coords),
+            },
+            FromBitColor { child } => ByteColorOp::FromBitColor {
+                child: compiler.input(&**child, coords),
+            },
+            Decompose { r, g, b, a } => ByteColorOp::Decompose {
+                r: compiler.input(&**r, coords),
+                g: compiler.input(&**g, coords),
+                b: compiler.input(&**b, coords),
+                a: compiler.input(&**a, coords),
+            },
+            ModifyState { child, child_state } => {
+                return compiler.modify_state(&**child, child_state, coords, out);
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => return compiler.if_else(predicate, &**child_a, &**child_b, coords, out),
+            Constant { .. } | FromImage { .. } | FromCellArray | Mandelbrot => {
+                ByteColorOp::Leaf(self.clone())
+            }
+        };
+
+        compiler.emit(op, coords, out);
+    }
 }
@@ -1,15 +1,23 @@
+use std::f32::consts::PI;
+
 use crate::{
-    datatype::{colors::*, continuous::*},
+    datatype::{colors::*, continuous::*, discrete::*, points::*},
     node::{
-        color_nodes::*, coord_map_nodes::*, discrete_nodes::*, mutagen_functions::*,
-        noise_nodes::*, point_nodes::*, Node,
+        color_nodes::*, coord_map_nodes::*, discrete_nodes::*, lenia_nodes::*,
+        mutagen_functions::*, noise_nodes::*, point_nodes::*, reaction_diffusion_nodes::*, batch,
+        compute_each, update_state, Node, Simplify,
     },
     updatestate::*,
+    vm::{Compile, Compiler, Instruction, Op, Reg, Registers, Step},
+    History,
 };
-use mutagen::{Generatable, Mutatable};
+use mutagen::{Crossover, Generatable, Mutatable, Traversable, Updatable};
 use nalgebra::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Generatable, Mutatable, Debug)]
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 #[mutagen(mut_reroll = 0.1)]
 pub enum AngleNodes {
     #[mutagen(gen_weight = leaf_node_weight)]
@@ -37,6 +45,16 @@ pub enum AngleNodes {
     #[mutagen(gen_weight = pipe_node_weight)]
     FromUNFloat { child: Box<UNFloatNodes> },
 
+    /// Turns a little further every step, by up to [MAX_PHASE_STEP] at the full speed
+    #[mutagen(gen_weight = pipe_node_weight)]
+    #[mutagen(update = accumulate_phase)]
+    PhaseAccumulator {
+        speed: Box<UNFloatNodes>,
+        #[serde(skip)]
+        #[mutagen(mut_weight = 0.0)]
+        phase: f32,
+    },
+
     #[mutagen(gen_weight = branch_node_weight)]
     ModifyState {
         child: Box<AngleNodes>,
@@ -69,6 +87,7 @@ impl Node for AngleNodes {
             FromSNPoint { child } => child.compute(state).to_angle(),
             FromSNFloat { child } => child.compute(state).to_angle(),
             FromUNFloat { child } => child.compute(state).to_angle(),
+            PhaseAccumulator { phase, .. } => Angle::new(*phase),
             ModifyState { child, child_state } => child.compute(UpdateState {
                 coordinate_set: child_state.compute(state),
                 ..state
@@ -88,7 +107,143 @@ impl Node for AngleNodes {
     }
 }
 
-#[derive(Generatable, Mutatable, Debug)]
+//A sixteenth of a turn, so the fastest accumulators still take a few steps to come round
+const MAX_PHASE_STEP: f32 = PI / 8.0;
+
+fn accumulate_phase(node: &mut AngleNodes, state: mutagen::State) {
+    if let (AngleNodes::PhaseAccumulator { speed, phase }, Some(state)) =
+        (node, update_state(&state))
+    {
+        *phase = Angle::new(*phase + speed.compute(state).into_inner() * MAX_PHASE_STEP)
+            .into_inner();
+    }
+}
+
+impl Simplify for AngleNodes {
+    fn simplify(self) -> Self {
+        use AngleNodes::*;
+
+        match self {
+            ArcSin { theta } => ArcSin {
+                theta: theta.simplify(),
+            },
+            ArcCos { theta } => ArcCos {
+                theta: theta.simplify(),
+            },
+            FromSNPoint { child } => FromSNPoint {
+                child: child.simplify(),
+            },
+            FromSNFloat { child } => FromSNFloat {
+                child: child.simplify(),
+            },
+            FromUNFloat { child } => FromUNFloat {
+                child: child.simplify(),
+            },
+            ModifyState { child, child_state } => {
+                match (*child.simplify(), *child_state.simplify()) {
+                    (child, child_state) if child_state.is_identity() => child,
+                    (child, child_state) => ModifyState {
+                        child: Box::new(child),
+                        child_state: Box::new(child_state),
+                    },
+                }
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => match *predicate.simplify() {
+                BooleanNodes::Constant { child } => {
+                    let branch = if child.into_inner() { child_a } else { child_b };
+                    *branch.simplify()
+                }
+                predicate => IfElse {
+                    predicate: Box::new(predicate),
+                    child_a: child_a.simplify(),
+                    child_b: child_b.simplify(),
+                },
+            },
+            FromGametic | FromCoordinate | PhaseAccumulator { .. } => self,
+        }
+    }
+}
+
+/// What an [AngleNodes] node does once its children have been computed into registers
+#[derive(Debug)]
+pub enum AngleOp {
+    Leaf(AngleNodes),
+    ArcSin { theta: Reg<SNFloat> },
+    ArcCos { theta: Reg<SNFloat> },
+    FromSNPoint { child: Reg<SNPoint> },
+    FromSNFloat { child: Reg<SNFloat> },
+    FromUNFloat { child: Reg<UNFloat> },
+}
+
+impl Op for AngleOp {
+    type Output = Angle;
+
+    fn execute(&self, registers: &Registers, state: UpdateState) -> Self::Output {
+        use AngleOp::*;
+
+        match self {
+            Leaf(node) => node.compute(state),
+            ArcSin { theta } => Angle::new(f32::asin(registers.get(*theta).into_inner())),
+            ArcCos { theta } => Angle::new(f32::acos(registers.get(*theta).into_inner())),
+            FromSNPoint { child } => registers.get(*child).to_angle(),
+            FromSNFloat { child } => registers.get(*child).to_angle(),
+            FromUNFloat { child } => registers.get(*child).to_angle(),
+        }
+    }
+
+    fn update(&mut self, state: mutagen::State) {
+        if let AngleOp::Leaf(node) = self {
+            node.update(state);
+        }
+    }
+
+    fn instruction(step: Step<Self>) -> Instruction {
+        Instruction::Angle(step)
+    }
+}
+
+impl Compile for AngleNodes {
+    fn compile(&self, compiler: &mut Compiler, coords: Reg<CoordinateSet>, out: Reg<Angle>) {
+        use AngleNodes::*;
+
+        let op = match self {
+            ArcSin { theta } => AngleOp::ArcSin {
+                theta: compiler.input(&**theta, coords),
+            },
+            ArcCos { theta } => AngleOp::ArcCos {
+                theta: compiler.input(&**theta, coords),
+            },
+            FromSNPoint { child } => AngleOp::FromSNPoint {
+                child: compiler.input(&**child, coords),
+            },
+            FromSNFloat { child } => AngleOp::FromSNFloat {
+                child: compiler.input(&**child, coords),
+            },
+            FromUNFloat { child } => AngleOp::FromUNFloat {
+                child: compiler.input(&**child, coords),
+            },
+            ModifyState { child, child_state } => {
+                return compiler.modify_state(&**child, child_state, coords, out);
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => return compiler.if_else(predicate, &**child_a, &**child_b, coords, out),
+            FromGametic | FromCoordinate | PhaseAccumulator { .. } => AngleOp::Leaf(self.clone()),
+        };
+
+        compiler.emit(op, coords, out);
+    }
+}
+
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 #[mutagen(mut_reroll = 0.1)]
 pub enum SNFloatNodes {
     #[mutagen(gen_weight = pipe_node_weight)]
@@ -195,9 +350,243 @@ impl Node for SNFloatNodes {
             }
         }
     }
+
+    fn compute_batch(
+        &self,
+        coordinate_sets: &[CoordinateSet],
+        history: &History,
+        out: &mut Vec<Self::Output>,
+    ) {
+        use SNFloatNodes::*;
+
+        match self {
+            Multiply { child_a, child_b } => {
+                let a = batch(child_a.as_ref(), coordinate_sets, history);
+                let b = batch(child_b.as_ref(), coordinate_sets, history);
+
+                out.extend(
+                    a.into_iter()
+                        .zip(b)
+                        .map(|(a, b)| SNFloat::new(a.into_inner() * b.into_inner())),
+                );
+            }
+            ModifyState { child, child_state } => child.compute_batch(
+                &batch(child_state.as_ref(), coordinate_sets, history),
+                history,
+                out,
+            ),
+            NoiseFunction { child } => child.compute_batch(coordinate_sets, history, out),
+            _ => compute_each(self, coordinate_sets, history, out),
+        }
+    }
+}
+
+impl Simplify for SNFloatNodes {
+    fn simplify(self) -> Self {
+        use SNFloatNodes::*;
+
+        match self {
+            Sin { child } => Sin {
+                child: child.simplify(),
+            },
+            Cos { child } => Cos {
+                child: child.simplify(),
+            },
+            FromAngle { child } => FromAngle {
+                child: child.simplify(),
+            },
+            FromUNFloat { child } => match *child.simplify() {
+                UNFloatNodes::Constant { value } => Constant {
+                    value: value.to_signed(),
+                },
+                child => FromUNFloat {
+                    child: Box::new(child),
+                },
+            },
+            Multiply { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: SNFloat::new(a.into_inner() * b.into_inner()),
+                },
+                (Constant { value }, child) | (child, Constant { value })
+                    if value.into_inner() == 1.0 =>
+                {
+                    child
+                }
+                (child_a, child_b) => Multiply {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            Abs { child } => match *child.simplify() {
+                Constant { value } => Constant {
+                    value: SNFloat::new(value.into_inner().abs()),
+                },
+                child @ Abs { .. } => child,
+                child => Abs {
+                    child: Box::new(child),
+                },
+            },
+            Invert { child } => match *child.simplify() {
+                Constant { value } => Constant {
+                    value: SNFloat::new(value.into_inner() * -1.0),
+                },
+                Invert { child } => *child,
+                child => Invert {
+                    child: Box::new(child),
+                },
+            },
+            SubDivide { child_a, child_b } => SubDivide {
+                child_a: child_a.simplify(),
+                child_b: child_b.simplify(),
+            },
+            ModifyState { child, child_state } => {
+                match (*child.simplify(), *child_state.simplify()) {
+                    // Constants don't read the coordinates, so there's nothing to modify
+                    (child @ Constant { .. }, _) => child,
+                    (child, child_state) if child_state.is_identity() => child,
+                    (child, child_state) => ModifyState {
+                        child: Box::new(child),
+                        child_state: Box::new(child_state),
+                    },
+                }
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => match *predicate.simplify() {
+                BooleanNodes::Constant { child } => {
+                    let branch = if child.into_inner() { child_a } else { child_b };
+                    *branch.simplify()
+                }
+                predicate => IfElse {
+                    predicate: Box::new(predicate),
+                    child_a: child_a.simplify(),
+                    child_b: child_b.simplify(),
+                },
+            },
+            Constant { .. } | XRatio | YRatio | FromGametic | NoiseFunction { .. } => self,
+        }
+    }
 }
 
-#[derive(Generatable, Mutatable, Debug)]
+/// What an [SNFloatNodes] node does once its children have been computed into registers
+#[derive(Debug)]
+pub enum SNFloatOp {
+    Leaf(SNFloatNodes),
+    Sin {
+        child: Reg<Angle>,
+    },
+    Cos {
+        child: Reg<Angle>,
+    },
+    FromAngle {
+        child: Reg<Angle>,
+    },
+    FromUNFloat {
+        child: Reg<UNFloat>,
+    },
+    Multiply {
+        child_a: Reg<SNFloat>,
+        child_b: Reg<SNFloat>,
+    },
+    Abs {
+        child: Reg<SNFloat>,
+    },
+    Invert {
+        child: Reg<SNFloat>,
+    },
+    SubDivide {
+        child_a: Reg<SNFloat>,
+        child_b: Reg<Nibble>,
+    },
+}
+
+impl Op for SNFloatOp {
+    type Output = SNFloat;
+
+    fn execute(&self, registers: &Registers, state: UpdateState) -> Self::Output {
+        use SNFloatOp::*;
+
+        match self {
+            Leaf(node) => node.compute(state),
+            Sin { child } => SNFloat::new(f32::sin(registers.get(*child).into_inner())),
+            Cos { child } => SNFloat::new(f32::cos(registers.get(*child).into_inner())),
+            FromAngle { child } => registers.get(*child).to_signed(),
+            FromUNFloat { child } => registers.get(*child).to_signed(),
+            Multiply { child_a, child_b } => SNFloat::new(
+                registers.get(*child_a).into_inner() * registers.get(*child_b).into_inner(),
+            ),
+            Abs { child } => SNFloat::new(registers.get(*child).into_inner().abs()),
+            Invert { child } => SNFloat::new(registers.get(*child).into_inner() * -1.0),
+            SubDivide { child_a, child_b } => {
+                registers.get(*child_a).subdivide(registers.get(*child_b))
+            }
+        }
+    }
+
+    fn update(&mut self, state: mutagen::State) {
+        if let SNFloatOp::Leaf(node) = self {
+            node.update(state);
+        }
+    }
+
+    fn instruction(step: Step<Self>) -> Instruction {
+        Instruction::SNFloat(step)
+    }
+}
+
+impl Compile for SNFloatNodes {
+    fn compile(&self, compiler: &mut Compiler, coords: Reg<CoordinateSet>, out: Reg<SNFloat>) {
+        use SNFloatNodes::*;
+
+        let op = match self {
+            Sin { child } => SNFloatOp::Sin {
+                child: compiler.input(&**child, coords),
+            },
+            Cos { child } => SNFloatOp::Cos {
+                child: compiler.input(&**child, coords),
+            },
+            FromAngle { child } => SNFloatOp::FromAngle {
+                child: compiler.input(&**child, coords),
+            },
+            FromUNFloat { child } => SNFloatOp::FromUNFloat {
+                child: compiler.input(&**child, coords),
+            },
+            Multiply { child_a, child_b } => SNFloatOp::Multiply {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            Abs { child } => SNFloatOp::Abs {
+                child: compiler.input(&**child, coords),
+            },
+            Invert { child } => SNFloatOp::Invert {
+                child: compiler.input(&**child, coords),
+            },
+            SubDivide { child_a, child_b } => SNFloatOp::SubDivide {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            ModifyState { child, child_state } => {
+                return compiler.modify_state(&**child, child_state, coords, out);
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => return compiler.if_else(predicate, &**child_a, &**child_b, coords, out),
+            Constant { .. } | XRatio | YRatio | FromGametic | NoiseFunction { .. } => {
+                SNFloatOp::Leaf(self.clone())
+            }
+        };
+
+        compiler.emit(op, coords, out);
+    }
+}
+
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 #[mutagen(mut_reroll = 0.1)]
 pub enum UNFloatNodes {
     // #[mutagen(gen_weight = leaf_node_weight)]
@@ -236,6 +625,22 @@ pub enum UNFloatNodes {
     ColorComponentH { child: Box<FloatColorNodes> },
     #[mutagen(gen_weight = leaf_node_weight)]
     FromGametic,
+    /// Eases towards what `child` computes each step, keeping more of the old value the higher `smoothing` is
+    #[mutagen(gen_weight = pipe_node_weight)]
+    #[mutagen(update = smooth)]
+    Smoothed {
+        child: Box<UNFloatNodes>,
+        smoothing: UNFloat,
+        #[serde(skip)]
+        #[mutagen(mut_weight = 0.0)]
+        value: f32,
+    },
+    #[mutagen(gen_weight = expensive_leaf_node_weight)]
+    FromLenia { child: Box<LeniaNodes> },
+    #[mutagen(gen_weight = expensive_leaf_node_weight)]
+    ReactionDiffusionU { child: Box<ReactionDiffusionNodes> },
+    #[mutagen(gen_weight = expensive_leaf_node_weight)]
+    ReactionDiffusionV { child: Box<ReactionDiffusionNodes> },
     #[mutagen(gen_weight = pipe_node_weight)]
     Mandelbrot { 
         child_power: Box<UNFloatNodes>,
@@ -299,25 +704,17 @@ impl Node for UNFloatNodes {
             ColorComponentB { child } => UNFloat::new(child.compute(state).b),
             ColorComponentH { child } => get_hue_unfloat(child.compute(state)),
             FromGametic => state.coordinate_set.get_unfloat_t(),
-            Mandelbrot { child_power, child_offset, child_scale, child_iterations } => {
-                let power = 1.0 + child_power.compute(state).into_inner() * 8.0;
-                let offset = child_offset.compute(state).into_inner();
-                let scale = child_scale.compute(state).into_inner();
-                let mut z = Complex { re: 0.0, im: 0.0 };
-                //scaling in this fashion will give us a lot of boring stuff :<
-                let c = Complex { re: ((state.coordinate_set.x.into_inner() * scale.x) + offset.x) * 0.5, im: ((state.coordinate_set.y.into_inner() * scale.y) + offset.y)};
-                let mut escape = 0;
-                let iterations = child_iterations.compute(state).into_inner() / 2;
-                for i in 0..=iterations {
-                    z = z.powf(power) + c;  
-                    if z.norm_sqr() > 4.0 {
-                        escape = i;
-                        break;
-                    }
-                }
-
-                UNFloat::new(escape as f32 / (1 + iterations) as f32)
-            }
+            Smoothed { value, .. } => UNFloat::new(*value),
+            FromLenia { child } => UNFloat::new(get_average(child.compute(state))),
+            ReactionDiffusionU { child } => child.compute(state).0,
+            ReactionDiffusionV { child } => child.compute(state).1,
+            Mandelbrot { child_power, child_offset, child_scale, child_iterations } => mandelbrot(
+                child_power.compute(state),
+                child_offset.compute(state),
+                child_scale.compute(state),
+                child_iterations.compute(state),
+                state.coordinate_set,
+            ),
             SubDivide { child_a, child_b } => {
                 child_a.compute(state).subdivide(child_b.compute(state))
             }
@@ -346,3 +743,382 @@ impl Node for UNFloatNodes {
         }
     }
 }
+
+fn smooth(node: &mut UNFloatNodes, state: mutagen::State) {
+    if let (
+        UNFloatNodes::Smoothed {
+            child,
+            smoothing,
+            value,
+        },
+        Some(state),
+    ) = (node, update_state(&state))
+    {
+        *value += (child.compute(state).into_inner() - *value) * (1.0 - smoothing.into_inner());
+    }
+}
+
+/// How many iterations it takes the point at the coordinates to escape a Multibrot set, as a fraction of the maximum
+fn mandelbrot(
+    power: UNFloat,
+    offset: SNPoint,
+    scale: SNPoint,
+    iterations: Byte,
+    coordinate_set: CoordinateSet,
+) -> UNFloat {
+    let power = 1.0 + power.into_inner() * 8.0;
+    let offset = offset.into_inner();
+    let scale = scale.into_inner();
+    let mut z = Complex { re: 0.0, im: 0.0 };
+    //scaling in this fashion will give us a lot of boring stuff :<
+    let c = Complex {
+        re: ((coordinate_set.x.into_inner() * scale.x) + offset.x) * 0.5,
+        im: ((coordinate_set.y.into_inner() * scale.y) + offset.y),
+    };
+    let mut escape = 0;
+    let iterations = iterations.into_inner() / 2;
+    for i in 0..=iterations {
+        z = z.powf(power) + c;
+        if z.norm_sqr() > 4.0 {
+            escape = i;
+            break;
+        }
+    }
+
+    UNFloat::new(escape as f32 / (1 + iterations) as f32)
+}
+
+impl Simplify for UNFloatNodes {
+    fn simplify(self) -> Self {
+        use UNFloatNodes::*;
+
+        match self {
+            FromAngle { child } => FromAngle {
+                child: child.simplify(),
+            },
+            FromSNFloat { child } => match *child.simplify() {
+                SNFloatNodes::Constant { value } => Constant {
+                    value: value.to_unsigned(),
+                },
+                child => FromSNFloat {
+                    child: Box::new(child),
+                },
+            },
+            AbsSNFloat { child } => match *child.simplify() {
+                SNFloatNodes::Constant { value } => Constant {
+                    value: UNFloat::new(value.into_inner().abs()),
+                },
+                child => AbsSNFloat {
+                    child: Box::new(child),
+                },
+            },
+            SquareSNFloat { child } => match *child.simplify() {
+                SNFloatNodes::Constant { value } => Constant {
+                    value: UNFloat::new(value.into_inner().powf(2.0)),
+                },
+                child => SquareSNFloat {
+                    child: Box::new(child),
+                },
+            },
+            Multiply { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: UNFloat::new(a.into_inner() * b.into_inner()),
+                },
+                (Constant { value }, child) | (child, Constant { value })
+                    if value.into_inner() == 1.0 =>
+                {
+                    child
+                }
+                (child_a, child_b) => Multiply {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            CircularAdd { child_a, child_b } => CircularAdd {
+                child_a: child_a.simplify(),
+                child_b: child_b.simplify(),
+            },
+            InvertNormalised { child } => match *child.simplify() {
+                Constant { value } => Constant {
+                    value: UNFloat::new(1.0 - value.into_inner()),
+                },
+                // 1 - (1 - x) can be off from x in the last bit, which is far too little to show up in a color
+                InvertNormalised { child } => *child,
+                child => InvertNormalised {
+                    child: Box::new(child),
+                },
+            },
+            ColorAverage { child } => ColorAverage {
+                child: child.simplify(),
+            },
+            ColorComponentR { child } => ColorComponentR {
+                child: child.simplify(),
+            },
+            ColorComponentG { child } => ColorComponentG {
+                child: child.simplify(),
+            },
+            ColorComponentB { child } => ColorComponentB {
+                child: child.simplify(),
+            },
+            ColorComponentH { child } => ColorComponentH {
+                child: child.simplify(),
+            },
+            Mandelbrot {
+                child_power,
+                child_offset,
+                child_scale,
+                child_iterations,
+            } => Mandelbrot {
+                child_power: child_power.simplify(),
+                child_offset: child_offset.simplify(),
+                child_scale: child_scale.simplify(),
+                child_iterations: child_iterations.simplify(),
+            },
+            SubDivide { child_a, child_b } => SubDivide {
+                child_a: child_a.simplify(),
+                child_b: child_b.simplify(),
+            },
+            EuclideanDistance { child_a, child_b } => EuclideanDistance {
+                child_a: child_a.simplify(),
+                child_b: child_b.simplify(),
+            },
+            ModifyState { child, child_state } => {
+                match (*child.simplify(), *child_state.simplify()) {
+                    (child @ Constant { .. }, _) => child,
+                    (child, child_state) if child_state.is_identity() => child,
+                    (child, child_state) => ModifyState {
+                        child: Box::new(child),
+                        child_state: Box::new(child_state),
+                    },
+                }
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => match *predicate.simplify() {
+                BooleanNodes::Constant { child } => {
+                    let branch = if child.into_inner() { child_a } else { child_b };
+                    *branch.simplify()
+                }
+                predicate => IfElse {
+                    predicate: Box::new(predicate),
+                    child_a: child_a.simplify(),
+                    child_b: child_b.simplify(),
+                },
+            },
+            ReactionDiffusionU { child } => ReactionDiffusionU {
+                child: child.simplify(),
+            },
+            ReactionDiffusionV { child } => ReactionDiffusionV {
+                child: child.simplify(),
+            },
+            Constant { .. } | FromGametic | Smoothed { .. } | FromLenia { .. } => self,
+        }
+    }
+}
+
+/// What an [UNFloatNodes] node does once its children have been computed into registers
+#[derive(Debug)]
+pub enum UNFloatOp {
+    Leaf(UNFloatNodes),
+    FromAngle {
+        child: Reg<Angle>,
+    },
+    FromSNFloat {
+        child: Reg<SNFloat>,
+    },
+    AbsSNFloat {
+        child: Reg<SNFloat>,
+    },
+    SquareSNFloat {
+        child: Reg<SNFloat>,
+    },
+    Multiply {
+        child_a: Reg<UNFloat>,
+        child_b: Reg<UNFloat>,
+    },
+    CircularAdd {
+        child_a: Reg<UNFloat>,
+        child_b: Reg<UNFloat>,
+    },
+    InvertNormalised {
+        child: Reg<UNFloat>,
+    },
+    ColorAverage {
+        child: Reg<FloatColor>,
+    },
+    ColorComponentR {
+        child: Reg<FloatColor>,
+    },
+    ColorComponentG {
+        child: Reg<FloatColor>,
+    },
+    ColorComponentB {
+        child: Reg<FloatColor>,
+    },
+    ColorComponentH {
+        child: Reg<FloatColor>,
+    },
+    Mandelbrot {
+        child_power: Reg<UNFloat>,
+        child_offset: Reg<SNPoint>,
+        child_scale: Reg<SNPoint>,
+        child_iterations: Reg<Byte>,
+    },
+    SubDivide {
+        child_a: Reg<UNFloat>,
+        child_b: Reg<Nibble>,
+    },
+    EuclideanDistance {
+        child_a: Reg<SNPoint>,
+        child_b: Reg<SNPoint>,
+    },
+}
+
+impl Op for UNFloatOp {
+    type Output = UNFloat;
+
+    fn execute(&self, registers: &Registers, state: UpdateState) -> Self::Output {
+        use UNFloatOp::*;
+
+        match self {
+            Leaf(node) => node.compute(state),
+            FromAngle { child } => registers.get(*child).to_unsigned(),
+            FromSNFloat { child } => registers.get(*child).to_unsigned(),
+            AbsSNFloat { child } => UNFloat::new(registers.get(*child).into_inner().abs()),
+            SquareSNFloat { child } => UNFloat::new(registers.get(*child).into_inner().powf(2.0)),
+            Multiply { child_a, child_b } => UNFloat::new(
+                registers.get(*child_a).into_inner() * registers.get(*child_b).into_inner(),
+            ),
+            CircularAdd { child_a, child_b } => {
+                let value =
+                    registers.get(*child_a).into_inner() + registers.get(*child_b).into_inner();
+                UNFloat::new(value - (value.floor()))
+            }
+            InvertNormalised { child } => UNFloat::new(1.0 - registers.get(*child).into_inner()),
+            ColorAverage { child } => {
+                let color = registers.get(*child);
+                UNFloat::new((color.r + color.g + color.b) / 3.0)
+            }
+            ColorComponentR { child } => UNFloat::new(registers.get(*child).r),
+            ColorComponentG { child } => UNFloat::new(registers.get(*child).g),
+            ColorComponentB { child } => UNFloat::new(registers.get(*child).b),
+            ColorComponentH { child } => get_hue_unfloat(registers.get(*child)),
+            Mandelbrot {
+                child_power,
+                child_offset,
+                child_scale,
+                child_iterations,
+            } => mandelbrot(
+                registers.get(*child_power),
+                registers.get(*child_offset),
+                registers.get(*child_scale),
+                registers.get(*child_iterations),
+                state.coordinate_set,
+            ),
+            SubDivide { child_a, child_b } => {
+                registers.get(*child_a).subdivide(registers.get(*child_b))
+            }
+            EuclideanDistance { child_a, child_b } => UNFloat::new(
+                (distance(
+                    &registers.get(*child_a).into_inner(),
+                    &registers.get(*child_b).into_inner(),
+                ) * 0.5)
+                    .min(1.0),
+            ),
+        }
+    }
+
+    fn update(&mut self, state: mutagen::State) {
+        if let UNFloatOp::Leaf(node) = self {
+            node.update(state);
+        }
+    }
+
+    fn instruction(step: Step<Self>) -> Instruction {
+        Instruction::UNFloat(step)
+    }
+}
+
+impl Compile for UNFloatNodes {
+    fn compile(&self, compiler: &mut Compiler, coords: Reg<CoordinateSet>, out: Reg<UNFloat>) {
+        use UNFloatNodes::*;
+
+        let op = match self {
+            FromAngle { child } => UNFloatOp::FromAngle {
+                child: compiler.input(&**child, coords),
+            },
+            FromSNFloat { child } => UNFloatOp::FromSNFloat {
+                child: compiler.input(&**child, coords),
+            },
+            AbsSNFloat { child } => UNFloatOp::AbsSNFloat {
+                child: compiler.input(&**child, coords),
+            },
+            SquareSNFloat { child } => UNFloatOp::SquareSNFloat {
+                child: compiler.input(&**child, coords),
+            },
+            Multiply { child_a, child_b } => UNFloatOp::Multiply {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            CircularAdd { child_a, child_b } => UNFloatOp::CircularAdd {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            InvertNormalised { child } => UNFloatOp::InvertNormalised {
+                child: compiler.input(&**child, coords),
+            },
+            ColorAverage { child } => UNFloatOp::ColorAverage {
+                child: compiler.input(&**child, coords),
+            },
+            ColorComponentR { child } => UNFloatOp::ColorComponentR {
+                child: compiler.input(&**child, coords),
+            },
+            ColorComponentG { child } => UNFloatOp::ColorComponentG {
+                child: compiler.input(&**child, coords),
+            },
+            ColorComponentB { child } => UNFloatOp::ColorComponentB {
+                child: compiler.input(&**child, coords),
+            },
+            ColorComponentH { child } => UNFloatOp::ColorComponentH {
+                child: compiler.input(&**child, coords),
+            },
+            Mandelbrot {
+                child_power,
+                child_offset,
+                child_scale,
+                child_iterations,
+            } => UNFloatOp::Mandelbrot {
+                child_power: compiler.input(&**child_power, coords),
+                child_offset: compiler.input(&**child_offset, coords),
+                child_scale: compiler.input(&**child_scale, coords),
+                child_iterations: compiler.input(&**child_iterations, coords),
+            },
+            SubDivide { child_a, child_b } => UNFloatOp::SubDivide {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            EuclideanDistance { child_a, child_b } => UNFloatOp::EuclideanDistance {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            ModifyState { child, child_state } => {
+                return compiler.modify_state(&**child, child_state, coords, out);
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => return compiler.if_else(predicate, &**child_a, &**child_b, coords, out),
+            Constant { .. }
+            | FromGametic
+            | Smoothed { .. }
+            | FromLenia { .. }
+            | ReactionDiffusionU { .. }
+            | ReactionDiffusionV { .. } => UNFloatOp::Leaf(self.clone()),
+        };
+
+        compiler.emit(op, coords, out);
+    }
+}
@@ -1,12 +1,20 @@
 use crate::{
     datatype::continuous::*,
-    node::{continuous_nodes::*, discrete_nodes::*, mutagen_functions::*, Node},
+    node::{
+        batch, compute_each, continuous_nodes::*, discrete_nodes::*, mutagen_functions::*, Node,
+        Simplify,
+    },
     updatestate::{CoordinateSet, UpdateState},
+    vm::{Compile, Compiler, Instruction, Op, Reg, Registers, Step},
+    History,
 };
-use mutagen::{Generatable, Mutatable};
+use mutagen::{Crossover, Generatable, Mutatable, Traversable, Updatable};
 use nalgebra::{geometry::Point2, geometry::Rotation2};
+use serde::{Deserialize, Serialize};
 
-#[derive(Generatable, Mutatable, Debug)]
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 #[mutagen(mut_reroll = 0.1)]
 pub enum CoordMapNodes {
     #[mutagen(gen_weight = branch_node_weight)]
@@ -37,6 +45,22 @@ pub enum CoordMapNodes {
     },
 }
 
+impl CoordMapNodes {
+    /// Whether the coordinates always come out unchanged.
+    /// A shift by zero isn't one, because shifting wraps coordinates of exactly 1 or -1 around to 0.
+    pub fn is_identity(&self) -> bool {
+        match self {
+            CoordMapNodes::Scale { x, y } => match (x.as_ref(), y.as_ref()) {
+                (SNFloatNodes::Constant { value: x }, SNFloatNodes::Constant { value: y }) => {
+                    x.into_inner() == 1.0 && y.into_inner() == 1.0
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
 impl Node for CoordMapNodes {
     type Output = CoordinateSet;
 
@@ -112,4 +136,159 @@ impl Node for CoordMapNodes {
             }
         }
     }
+
+    fn compute_batch(
+        &self,
+        coordinate_sets: &[CoordinateSet],
+        history: &History,
+        out: &mut Vec<Self::Output>,
+    ) {
+        use CoordMapNodes::*;
+
+        match self {
+            Rotation { angle } => out.extend(
+                batch(angle.as_ref(), coordinate_sets, history)
+                    .into_iter()
+                    .zip(coordinate_sets)
+                    .map(|(angle, coordinate_set)| {
+                        let new_pos =
+                            Rotation2::new(angle.into_inner()).transform_point(&Point2::new(
+                                coordinate_set.x.into_inner(),
+                                coordinate_set.y.into_inner(),
+                            ));
+
+                        CoordinateSet {
+                            x: SNFloat::new(0.0).circular_add_f32(new_pos.x),
+                            y: SNFloat::new(0.0).circular_add_f32(new_pos.y),
+                            t: coordinate_set.t,
+                        }
+                    }),
+            ),
+            _ => compute_each(self, coordinate_sets, history, out),
+        }
+    }
+}
+
+impl Simplify for CoordMapNodes {
+    fn simplify(self) -> Self {
+        use CoordMapNodes::*;
+
+        match self {
+            Shift { x, y } => Shift {
+                x: x.simplify(),
+                y: y.simplify(),
+            },
+            Scale { x, y } => Scale {
+                x: x.simplify(),
+                y: y.simplify(),
+            },
+            Rotation { angle } => Rotation {
+                angle: angle.simplify(),
+            },
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => match *predicate.simplify() {
+                BooleanNodes::Constant { child } => {
+                    let branch = if child.into_inner() { child_a } else { child_b };
+                    *branch.simplify()
+                }
+                predicate => IfElse {
+                    predicate: Box::new(predicate),
+                    child_a: child_a.simplify(),
+                    child_b: child_b.simplify(),
+                },
+            },
+            ToPolar | FromPolar => self,
+        }
+    }
+}
+
+/// What a [CoordMapNodes] node does once its children have been computed into registers
+#[derive(Debug)]
+pub enum CoordMapOp {
+    Leaf(CoordMapNodes),
+    Shift { x: Reg<SNFloat>, y: Reg<SNFloat> },
+    Scale { x: Reg<SNFloat>, y: Reg<SNFloat> },
+    Rotation { angle: Reg<Angle> },
+}
+
+impl Op for CoordMapOp {
+    type Output = CoordinateSet;
+
+    fn execute(&self, registers: &Registers, state: UpdateState) -> Self::Output {
+        use CoordMapOp::*;
+
+        match self {
+            Leaf(node) => node.compute(state),
+            Shift { x, y } => state.coordinate_set.get_coord_shifted(
+                registers.get(*x),
+                registers.get(*y),
+                SNFloat::new(0.0),
+            ),
+            Scale { x, y } => state.coordinate_set.get_coord_scaled(
+                registers.get(*x),
+                registers.get(*y),
+                SNFloat::new(1.0),
+            ),
+            Rotation { angle } => {
+                let new_pos = Rotation2::new(registers.get(*angle).into_inner()).transform_point(
+                    &Point2::new(
+                        state.coordinate_set.x.into_inner(),
+                        state.coordinate_set.y.into_inner(),
+                    ),
+                );
+
+                CoordinateSet {
+                    x: SNFloat::new(0.0).circular_add_f32(new_pos.x),
+                    y: SNFloat::new(0.0).circular_add_f32(new_pos.y),
+                    t: state.coordinate_set.t,
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, state: mutagen::State) {
+        if let CoordMapOp::Leaf(node) = self {
+            node.update(state);
+        }
+    }
+
+    fn instruction(step: Step<Self>) -> Instruction {
+        Instruction::CoordMap(step)
+    }
+}
+
+impl Compile for CoordMapNodes {
+    fn compile(
+        &self,
+        compiler: &mut Compiler,
+        coords: Reg<CoordinateSet>,
+        out: Reg<CoordinateSet>,
+    ) {
+        use CoordMapNodes::*;
+
+        let op = match self {
+            Shift { x, y } => CoordMapOp::Shift {
+                x: compiler.input(&**x, coords),
+                y: compiler.input(&**y, coords),
+            },
+            Scale { x, y } => CoordMapOp::Scale {
+                x: compiler.input(&**x, coords),
+                y: compiler.input(&**y, coords),
+            },
+            Rotation { angle } => CoordMapOp::Rotation {
+                angle: compiler.input(&**angle, coords),
+            },
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => return compiler.if_else(predicate, &**child_a, &**child_b, coords, out),
+            ToPolar | FromPolar => CoordMapOp::Leaf(self.clone()),
+        };
+
+        compiler.emit(op, coords, out);
+    }
 }
@@ -1,12 +1,19 @@
 use crate::{
-    datatype::{discrete::*},
-    node::{color_nodes::*, continuous_nodes::*, coord_map_nodes::*, mutagen_functions::*, Node},
+    datatype::{colors::*, continuous::*, discrete::*},
+    node::{
+        color_nodes::*, continuous_nodes::*, coord_map_nodes::*, mutagen_functions::*, Node,
+        Simplify,
+    },
     updatestate::*,
     constants::*,
+    vm::{Compile, Compiler, Instruction, Op, Reg, Registers, Step},
 };
-use mutagen::{Generatable, Mutatable};
+use mutagen::{Crossover, Generatable, Mutatable, Traversable, Updatable};
+use serde::{Deserialize, Serialize};
 
-#[derive(Generatable, Mutatable, Debug)]
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 pub enum BooleanNodes {
     #[mutagen(gen_weight = branch_node_weight)]
     UNFloatLess {
@@ -130,7 +137,301 @@ impl Node for BooleanNodes {
     }
 }
 
-#[derive(Generatable, Mutatable, Debug)]
+impl Simplify for BooleanNodes {
+    fn simplify(self) -> Self {
+        use BooleanNodes::*;
+
+        match self {
+            UNFloatLess { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (UNFloatNodes::Constant { value: a }, UNFloatNodes::Constant { value: b }) => {
+                    Constant {
+                        child: Boolean {
+                            value: a.into_inner() < b.into_inner(),
+                        },
+                    }
+                }
+                (child_a, child_b) => UNFloatLess {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            UNFloatMore { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (UNFloatNodes::Constant { value: a }, UNFloatNodes::Constant { value: b }) => {
+                    Constant {
+                        child: Boolean {
+                            value: a.into_inner() > b.into_inner(),
+                        },
+                    }
+                }
+                (child_a, child_b) => UNFloatMore {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            SNFloatLess { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (SNFloatNodes::Constant { value: a }, SNFloatNodes::Constant { value: b }) => {
+                    Constant {
+                        child: Boolean {
+                            value: a.into_inner() < b.into_inner(),
+                        },
+                    }
+                }
+                (child_a, child_b) => SNFloatLess {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            SNFloatMore { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (SNFloatNodes::Constant { value: a }, SNFloatNodes::Constant { value: b }) => {
+                    Constant {
+                        child: Boolean {
+                            value: a.into_inner() > b.into_inner(),
+                        },
+                    }
+                }
+                (child_a, child_b) => SNFloatMore {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            SNFloatSign { child } => match *child.simplify() {
+                SNFloatNodes::Constant { value } => Constant {
+                    child: Boolean {
+                        value: value.into_inner() >= 0.0,
+                    },
+                },
+                child => SNFloatSign {
+                    child: Box::new(child),
+                },
+            },
+            And { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { child }, other) | (other, Constant { child }) => {
+                    if child.into_inner() {
+                        other
+                    } else {
+                        Constant { child }
+                    }
+                }
+                (child_a, child_b) => And {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            Or { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { child }, other) | (other, Constant { child }) => {
+                    if child.into_inner() {
+                        Constant { child }
+                    } else {
+                        other
+                    }
+                }
+                (child_a, child_b) => Or {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            Not { child } => match *child.simplify() {
+                Constant { child } => Constant {
+                    child: Boolean {
+                        value: !child.into_inner(),
+                    },
+                },
+                Not { child } => *child,
+                child => Not {
+                    child: Box::new(child),
+                },
+            },
+            BitColorHas { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (BitColorNodes::Constant { value: a }, BitColorNodes::Constant { value: b }) => {
+                    Constant {
+                        child: Boolean {
+                            value: a.has_color(b),
+                        },
+                    }
+                }
+                (child_a, child_b) => BitColorHas {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            ModifyState { child, child_state } => {
+                match (*child.simplify(), *child_state.simplify()) {
+                    (child @ Constant { .. }, _) => child,
+                    (child, child_state) if child_state.is_identity() => child,
+                    (child, child_state) => ModifyState {
+                        child: Box::new(child),
+                        child_state: Box::new(child_state),
+                    },
+                }
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => match *predicate.simplify() {
+                BooleanNodes::Constant { child } => {
+                    let branch = if child.into_inner() { child_a } else { child_b };
+                    *branch.simplify()
+                }
+                predicate => IfElse {
+                    predicate: Box::new(predicate),
+                    child_a: child_a.simplify(),
+                    child_b: child_b.simplify(),
+                },
+            },
+            ByteEquals { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (ByteNodes::Constant { value: a }, ByteNodes::Constant { value: b }) => Constant {
+                    child: Boolean {
+                        value: a.into_inner() == b.into_inner(),
+                    },
+                },
+                (child_a, child_b) => ByteEquals {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            Constant { .. } => self,
+        }
+    }
+}
+
+/// What a [BooleanNodes] node does once its children have been computed into registers
+#[derive(Debug)]
+pub enum BooleanOp {
+    Leaf(BooleanNodes),
+    UNFloatLess {
+        child_a: Reg<UNFloat>,
+        child_b: Reg<UNFloat>,
+    },
+    UNFloatMore {
+        child_a: Reg<UNFloat>,
+        child_b: Reg<UNFloat>,
+    },
+    SNFloatLess {
+        child_a: Reg<SNFloat>,
+        child_b: Reg<SNFloat>,
+    },
+    SNFloatMore {
+        child_a: Reg<SNFloat>,
+        child_b: Reg<SNFloat>,
+    },
+    SNFloatSign {
+        child: Reg<SNFloat>,
+    },
+    Not {
+        child: Reg<Boolean>,
+    },
+    BitColorHas {
+        child_a: Reg<BitColor>,
+        child_b: Reg<BitColor>,
+    },
+    ByteEquals {
+        child_a: Reg<Byte>,
+        child_b: Reg<Byte>,
+    },
+}
+
+impl Op for BooleanOp {
+    type Output = Boolean;
+
+    fn execute(&self, registers: &Registers, state: UpdateState) -> Self::Output {
+        use BooleanOp::*;
+
+        match self {
+            Leaf(node) => node.compute(state),
+            UNFloatLess { child_a, child_b } => Boolean {
+                value: registers.get(*child_a).into_inner() < registers.get(*child_b).into_inner(),
+            },
+            UNFloatMore { child_a, child_b } => Boolean {
+                value: registers.get(*child_a).into_inner() > registers.get(*child_b).into_inner(),
+            },
+            SNFloatLess { child_a, child_b } => Boolean {
+                value: registers.get(*child_a).into_inner() < registers.get(*child_b).into_inner(),
+            },
+            SNFloatMore { child_a, child_b } => Boolean {
+                value: registers.get(*child_a).into_inner() > registers.get(*child_b).into_inner(),
+            },
+            SNFloatSign { child } => Boolean {
+                value: registers.get(*child).into_inner() >= 0.0,
+            },
+            Not { child } => Boolean {
+                value: !registers.get(*child).into_inner(),
+            },
+            BitColorHas { child_a, child_b } => Boolean {
+                value: registers.get(*child_a).has_color(registers.get(*child_b)),
+            },
+            ByteEquals { child_a, child_b } => Boolean {
+                value: registers.get(*child_a).into_inner() == registers.get(*child_b).into_inner(),
+            },
+        }
+    }
+
+    fn update(&mut self, state: mutagen::State) {
+        if let BooleanOp::Leaf(node) = self {
+            node.update(state);
+        }
+    }
+
+    fn instruction(step: Step<Self>) -> Instruction {
+        Instruction::Boolean(step)
+    }
+}
+
+impl Compile for BooleanNodes {
+    fn compile(&self, compiler: &mut Compiler, coords: Reg<CoordinateSet>, out: Reg<Boolean>) {
+        use BooleanNodes::*;
+
+        let op = match self {
+            UNFloatLess { child_a, child_b } => BooleanOp::UNFloatLess {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            UNFloatMore { child_a, child_b } => BooleanOp::UNFloatMore {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            SNFloatLess { child_a, child_b } => BooleanOp::SNFloatLess {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            SNFloatMore { child_a, child_b } => BooleanOp::SNFloatMore {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            SNFloatSign { child } => BooleanOp::SNFloatSign {
+                child: compiler.input(&**child, coords),
+            },
+            And { child_a, child_b } => return compiler.and(child_a, child_b, coords, out),
+            Or { child_a, child_b } => return compiler.or(child_a, child_b, coords, out),
+            Not { child } => BooleanOp::Not {
+                child: compiler.input(&**child, coords),
+            },
+            BitColorHas { child_a, child_b } => BooleanOp::BitColorHas {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            ByteEquals { child_a, child_b } => BooleanOp::ByteEquals {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            ModifyState { child, child_state } => {
+                return compiler.modify_state(&**child, child_state, coords, out);
+            }
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => return compiler.if_else(predicate, &**child_a, &**child_b, coords, out),
+            Constant { .. } => BooleanOp::Leaf(self.clone()),
+        };
+
+        compiler.emit(op, coords, out);
+    }
+}
+
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 pub enum NibbleNodes {
     #[mutagen(gen_weight = leaf_node_weight)]
     Constant { value: Nibble },
@@ -241,7 +542,231 @@ impl Node for NibbleNodes {
     }
 }
 
-#[derive(Generatable, Mutatable, Debug)]
+impl Simplify for NibbleNodes {
+    fn simplify(self) -> Self {
+        use NibbleNodes::*;
+
+        match self {
+            Add { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant { value: a.add(b) },
+                (child_a, child_b) => Add {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            Multiply { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: a.multiply(b),
+                },
+                (child_a, child_b) => Multiply {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            Divide {
+                child_value,
+                child_divisor,
+            } => match (*child_value.simplify(), *child_divisor.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant { value: a.divide(b) },
+                (child_value, child_divisor) => Divide {
+                    child_value: Box::new(child_value),
+                    child_divisor: Box::new(child_divisor),
+                },
+            },
+            Modulus {
+                child_value,
+                child_divisor,
+            } => match (*child_value.simplify(), *child_divisor.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: a.modulus(b),
+                },
+                (child_value, child_divisor) => Modulus {
+                    child_value: Box::new(child_value),
+                    child_divisor: Box::new(child_divisor),
+                },
+            },
+            FromBooleans { a, b, c, d } => FromBooleans {
+                a: a.simplify(),
+                b: b.simplify(),
+                c: c.simplify(),
+                d: d.simplify(),
+            },
+            FromByteModulo { child } => FromByteModulo {
+                child: child.simplify(),
+            },
+            FromByteDivide { child } => FromByteDivide {
+                child: child.simplify(),
+            },
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => match *predicate.simplify() {
+                BooleanNodes::Constant { child } => {
+                    let branch = if child.into_inner() { child_a } else { child_b };
+                    *branch.simplify()
+                }
+                predicate => IfElse {
+                    predicate: Box::new(predicate),
+                    child_a: child_a.simplify(),
+                    child_b: child_b.simplify(),
+                },
+            },
+            Constant { .. } | FromGametic => self,
+        }
+    }
+}
+
+/// What a [NibbleNodes] node does once its children have been computed into registers
+#[derive(Debug)]
+pub enum NibbleOp {
+    Leaf(NibbleNodes),
+    Add {
+        child_a: Reg<Nibble>,
+        child_b: Reg<Nibble>,
+    },
+    Multiply {
+        child_a: Reg<Nibble>,
+        child_b: Reg<Nibble>,
+    },
+    Divide {
+        child_value: Reg<Nibble>,
+        child_divisor: Reg<Nibble>,
+    },
+    Modulus {
+        child_value: Reg<Nibble>,
+        child_divisor: Reg<Nibble>,
+    },
+    FromBooleans {
+        a: Reg<Boolean>,
+        b: Reg<Boolean>,
+        c: Reg<Boolean>,
+        d: Reg<Boolean>,
+    },
+    FromByteModulo {
+        child: Reg<Byte>,
+    },
+    FromByteDivide {
+        child: Reg<Byte>,
+    },
+}
+
+impl Op for NibbleOp {
+    type Output = Nibble;
+
+    fn execute(&self, registers: &Registers, state: UpdateState) -> Self::Output {
+        use NibbleOp::*;
+
+        match self {
+            Leaf(node) => node.compute(state),
+            Add { child_a, child_b } => registers.get(*child_a).add(registers.get(*child_b)),
+            Multiply { child_a, child_b } => {
+                registers.get(*child_a).multiply(registers.get(*child_b))
+            }
+            Divide {
+                child_value,
+                child_divisor,
+            } => registers
+                .get(*child_value)
+                .divide(registers.get(*child_divisor)),
+            Modulus {
+                child_value,
+                child_divisor,
+            } => registers
+                .get(*child_value)
+                .modulus(registers.get(*child_divisor)),
+            FromBooleans { a, b, c, d } => {
+                let mut value = 0;
+
+                if registers.get(*a).into_inner() {
+                    value += 1;
+                }
+                if registers.get(*b).into_inner() {
+                    value += 2;
+                }
+                if registers.get(*c).into_inner() {
+                    value += 4;
+                }
+                if registers.get(*d).into_inner() {
+                    value += 8;
+                }
+
+                Nibble::new(value)
+            }
+            FromByteModulo { child } => {
+                Nibble::new(registers.get(*child).into_inner() % CONSTS.nibble_possible_values)
+            }
+            FromByteDivide { child } => {
+                Nibble::new(registers.get(*child).into_inner() / CONSTS.nibble_possible_values)
+            }
+        }
+    }
+
+    fn update(&mut self, state: mutagen::State) {
+        if let NibbleOp::Leaf(node) = self {
+            node.update(state);
+        }
+    }
+
+    fn instruction(step: Step<Self>) -> Instruction {
+        Instruction::Nibble(step)
+    }
+}
+
+impl Compile for NibbleNodes {
+    fn compile(&self, compiler: &mut Compiler, coords: Reg<CoordinateSet>, out: Reg<Nibble>) {
+        use NibbleNodes::*;
+
+        let op = match self {
+            Add { child_a, child_b } => NibbleOp::Add {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            Multiply { child_a, child_b } => NibbleOp::Multiply {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            Divide {
+                child_value,
+                child_divisor,
+            } => NibbleOp::Divide {
+                child_value: compiler.input(&**child_value, coords),
+                child_divisor: compiler.input(&**child_divisor, coords),
+            },
+            Modulus {
+                child_value,
+                child_divisor,
+            } => NibbleOp::Modulus {
+                child_value: compiler.input(&**child_value, coords),
+                child_divisor: compiler.input(&**child_divisor, coords),
+            },
+            FromBooleans { a, b, c, d } => NibbleOp::FromBooleans {
+                a: compiler.input(&**a, coords),
+                b: compiler.input(&**b, coords),
+                c: compiler.input(&**c, coords),
+                d: compiler.input(&**d, coords),
+            },
+            FromByteModulo { child } => NibbleOp::FromByteModulo {
+                child: compiler.input(&**child, coords),
+            },
+            FromByteDivide { child } => NibbleOp::FromByteDivide {
+                child: compiler.input(&**child, coords),
+            },
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => return compiler.if_else(predicate, &**child_a, &**child_b, coords, out),
+            Constant { .. } | FromGametic => NibbleOp::Leaf(self.clone()),
+        };
+
+        compiler.emit(op, coords, out);
+    }
+}
+
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 pub enum ByteNodes {
     #[mutagen(gen_weight = leaf_node_weight)]
     Constant { value: Byte },
@@ -319,7 +844,171 @@ impl Node for ByteNodes {
     }
 }
 
-#[derive(Generatable, Mutatable, Debug)]
+impl Simplify for ByteNodes {
+    fn simplify(self) -> Self {
+        use ByteNodes::*;
+
+        match self {
+            Add { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant { value: a.add(b) },
+                (child_a, child_b) => Add {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            Multiply { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: a.multiply(b),
+                },
+                (child_a, child_b) => Multiply {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            Divide {
+                child_value,
+                child_divisor,
+            } => match (*child_value.simplify(), *child_divisor.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant { value: a.divide(b) },
+                (child_value, child_divisor) => Divide {
+                    child_value: Box::new(child_value),
+                    child_divisor: Box::new(child_divisor),
+                },
+            },
+            Modulus {
+                child_value,
+                child_divisor,
+            } => match (*child_value.simplify(), *child_divisor.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: a.modulus(b),
+                },
+                (child_value, child_divisor) => Modulus {
+                    child_value: Box::new(child_value),
+                    child_divisor: Box::new(child_divisor),
+                },
+            },
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => match *predicate.simplify() {
+                BooleanNodes::Constant { child } => {
+                    let branch = if child.into_inner() { child_a } else { child_b };
+                    *branch.simplify()
+                }
+                predicate => IfElse {
+                    predicate: Box::new(predicate),
+                    child_a: child_a.simplify(),
+                    child_b: child_b.simplify(),
+                },
+            },
+            Constant { .. } | FromGametic => self,
+        }
+    }
+}
+
+/// What a [ByteNodes] node does once its children have been computed into registers
+#[derive(Debug)]
+pub enum ByteOp {
+    Leaf(ByteNodes),
+    Add {
+        child_a: Reg<Byte>,
+        child_b: Reg<Byte>,
+    },
+    Multiply {
+        child_a: Reg<Byte>,
+        child_b: Reg<Byte>,
+    },
+    Divide {
+        child_value: Reg<Byte>,
+        child_divisor: Reg<Byte>,
+    },
+    Modulus {
+        child_value: Reg<Byte>,
+        child_divisor: Reg<Byte>,
+    },
+}
+
+impl Op for ByteOp {
+    type Output = Byte;
+
+    fn execute(&self, registers: &Registers, state: UpdateState) -> Self::Output {
+        use ByteOp::*;
+
+        match self {
+            Leaf(node) => node.compute(state),
+            Add { child_a, child_b } => registers.get(*child_a).add(registers.get(*child_b)),
+            Multiply { child_a, child_b } => {
+                registers.get(*child_a).multiply(registers.get(*child_b))
+            }
+            Divide {
+                child_value,
+                child_divisor,
+            } => registers
+                .get(*child_value)
+                .divide(registers.get(*child_divisor)),
+            Modulus {
+                child_value,
+                child_divisor,
+            } => registers
+                .get(*child_value)
+                .modulus(registers.get(*child_divisor)),
+        }
+    }
+
+    fn update(&mut self, state: mutagen::State) {
+        if let ByteOp::Leaf(node) = self {
+            node.update(state);
+        }
+    }
+
+    fn instruction(step: Step<Self>) -> Instruction {
+        Instruction::Byte(step)
+    }
+}
+
+impl Compile for ByteNodes {
+    fn compile(&self, compiler: &mut Compiler, coords: Reg<CoordinateSet>, out: Reg<Byte>) {
+        use ByteNodes::*;
+
+        let op = match self {
+            Add { child_a, child_b } => ByteOp::Add {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            Multiply { child_a, child_b } => ByteOp::Multiply {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            Divide {
+                child_value,
+                child_divisor,
+            } => ByteOp::Divide {
+                child_value: compiler.input(&**child_value, coords),
+                child_divisor: compiler.input(&**child_divisor, coords),
+            },
+            Modulus {
+                child_value,
+                child_divisor,
+            } => ByteOp::Modulus {
+                child_value: compiler.input(&**child_value, coords),
+                child_divisor: compiler.input(&**child_divisor, coords),
+            },
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => return compiler.if_else(predicate, &**child_a, &**child_b, coords, out),
+            Constant { .. } | FromGametic => ByteOp::Leaf(self.clone()),
+        };
+
+        compiler.emit(op, coords, out);
+    }
+}
+
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 pub enum UIntNodes {
     #[mutagen(gen_weight = leaf_node_weight)]
     Constant { value: UInt },
@@ -396,7 +1085,72 @@ impl Node for UIntNodes {
     }
 }
 
-#[derive(Generatable, Mutatable, Debug)]
+impl Simplify for UIntNodes {
+    fn simplify(self) -> Self {
+        use UIntNodes::*;
+
+        match self {
+            Add { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant { value: a.add(b) },
+                (child_a, child_b) => Add {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            Multiply { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: a.multiply(b),
+                },
+                (child_a, child_b) => Multiply {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            Divide {
+                child_value,
+                child_divisor,
+            } => match (*child_value.simplify(), *child_divisor.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant { value: a.divide(b) },
+                (child_value, child_divisor) => Divide {
+                    child_value: Box::new(child_value),
+                    child_divisor: Box::new(child_divisor),
+                },
+            },
+            Modulus {
+                child_value,
+                child_divisor,
+            } => match (*child_value.simplify(), *child_divisor.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: a.modulus(b),
+                },
+                (child_value, child_divisor) => Modulus {
+                    child_value: Box::new(child_value),
+                    child_divisor: Box::new(child_divisor),
+                },
+            },
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => match *predicate.simplify() {
+                BooleanNodes::Constant { child } => {
+                    let branch = if child.into_inner() { child_a } else { child_b };
+                    *branch.simplify()
+                }
+                predicate => IfElse {
+                    predicate: Box::new(predicate),
+                    child_a: child_a.simplify(),
+                    child_b: child_b.simplify(),
+                },
+            },
+            Constant { .. } | FromGametic => self,
+        }
+    }
+}
+
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 pub enum SIntNodes {
     #[mutagen(gen_weight = leaf_node_weight)]
     Constant { value: SInt },
@@ -469,3 +1223,66 @@ impl Node for SIntNodes {
         }
     }
 }
+
+impl Simplify for SIntNodes {
+    fn simplify(self) -> Self {
+        use SIntNodes::*;
+
+        match self {
+            Add { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant { value: a.add(b) },
+                (child_a, child_b) => Add {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            Multiply { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: a.multiply(b),
+                },
+                (child_a, child_b) => Multiply {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            Divide {
+                child_value,
+                child_divisor,
+            } => match (*child_value.simplify(), *child_divisor.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant { value: a.divide(b) },
+                (child_value, child_divisor) => Divide {
+                    child_value: Box::new(child_value),
+                    child_divisor: Box::new(child_divisor),
+                },
+            },
+            Modulus {
+                child_value,
+                child_divisor,
+            } => match (*child_value.simplify(), *child_divisor.simplify()) {
+                (Constant { value: a }, Constant { value: b }) => Constant {
+                    value: a.modulus(b),
+                },
+                (child_value, child_divisor) => Modulus {
+                    child_value: Box::new(child_value),
+                    child_divisor: Box::new(child_divisor),
+                },
+            },
+            IfElse {
+                predicate,
+                child_a,
+                child_b,
+            } => match *predicate.simplify() {
+                BooleanNodes::Constant { child } => {
+                    let branch = if child.into_inner() { child_a } else { child_b };
+                    *branch.simplify()
+                }
+                predicate => IfElse {
+                    predicate: Box::new(predicate),
+                    child_a: child_a.simplify(),
+                    child_b: child_b.simplify(),
+                },
+            },
+            Constant { .. } => self,
+        }
+    }
+}
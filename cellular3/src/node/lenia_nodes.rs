@@ -0,0 +1,318 @@
+use std::fmt::{self, Debug, Formatter};
+
+use mutagen::{Crossover, Generatable, Mutatable, Traversable, Updatable};
+use ndarray::{Array2, Axis};
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    datatype::{colors::*, kernels::*},
+    node::{Node, UpdateContext},
+    updatestate::*,
+};
+
+/// Continuous cellular automata in the style of Lenia, which grow each cell by how the cells around it in the last
+/// finished history step compare to what a growth function wants.
+/// The whole array is grown when the trees are updated, ahead of the step that reads it, so computing a cell only looks it up.
+#[derive(Generatable, Mutatable, Traversable, Crossover, Serialize, Deserialize, Debug, Clone)]
+#[mutagen(mut_reroll = 0.1)]
+pub enum LeniaNodes {
+    /// Grows the brightness of each cell, giving shades of gray
+    Brightness {
+        kernel: Kernel,
+        growth: Growth,
+        #[serde(skip)]
+        #[mutagen(mut_weight = 0.0, cross_recurse = 0.0)]
+        cells: GrownCells<f32>,
+    },
+    /// Grows each of red, green and blue on its own, with the same kernel and growth function
+    Channels {
+        kernel: Kernel,
+        growth: Growth,
+        #[serde(skip)]
+        #[mutagen(mut_weight = 0.0, cross_recurse = 0.0)]
+        cells: GrownCells<[f32; 3]>,
+    },
+}
+
+impl Node for LeniaNodes {
+    type Output = FloatColor;
+
+    fn compute(&self, state: UpdateState) -> Self::Output {
+        use LeniaNodes::*;
+
+        match self {
+            Brightness {
+                kernel,
+                growth,
+                cells,
+            } => {
+                let value = cells
+                    .get(state)
+                    .unwrap_or_else(|| grow_cell(state, kernel, *growth, get_average));
+
+                FloatColor {
+                    r: value,
+                    g: value,
+                    b: value,
+                    a: 1.0,
+                }
+            }
+            Channels {
+                kernel,
+                growth,
+                cells,
+            } => {
+                let [r, g, b] = cells.get(state).unwrap_or_else(|| {
+                    [
+                        grow_cell(state, kernel, *growth, |color| color.r),
+                        grow_cell(state, kernel, *growth, |color| color.g),
+                        grow_cell(state, kernel, *growth, |color| color.b),
+                    ]
+                });
+
+                FloatColor { r, g, b, a: 1.0 }
+            }
+        }
+    }
+}
+
+/// Grows every cell for the next step from the last finished one, which needs an [UpdateContext] passed down.
+/// Nothing is grown when following, since the cells only depend on the history and the copy that's computed grows its own.
+impl Updatable for LeniaNodes {
+    fn update(&mut self, state: mutagen::State) {
+        use LeniaNodes::*;
+
+        let (history, t) = match state.context::<UpdateContext>() {
+            Some(UpdateContext {
+                following: false,
+                history,
+                t,
+                ..
+            }) => (history, t),
+            _ => return,
+        };
+        let previous = history.previous_cell_array(*t);
+
+        match self {
+            Brightness {
+                kernel,
+                growth,
+                cells,
+            } => {
+                *cells = GrownCells {
+                    t: *t,
+                    cells: grow(&channel(previous, get_average), kernel, *growth),
+                };
+            }
+            Channels {
+                kernel,
+                growth,
+                cells,
+            } => {
+                let r = grow(&channel(previous, |color| color.r), kernel, *growth);
+                let g = grow(&channel(previous, |color| color.g), kernel, *growth);
+                let b = grow(&channel(previous, |color| color.b), kernel, *growth);
+
+                *cells = GrownCells {
+                    t: *t,
+                    cells: Array2::from_shape_fn(r.dim(), |i| [r[i], g[i], b[i]]),
+                };
+            }
+        }
+    }
+
+    fn is_stateful(&self) -> bool {
+        true
+    }
+}
+
+/// One value for every cell of a cell array, indexed by y and then x
+pub fn channel<F: Fn(FloatColor) -> f32>(
+    cell_array: &ndarray::Array3<u8>,
+    value: F,
+) -> Array2<f32> {
+    cell_array.map_axis(Axis(2), |raw| {
+        value(FloatColor::from(ByteColor {
+            r: raw[0],
+            g: raw[1],
+            b: raw[2],
+            a: raw[3],
+        }))
+    })
+}
+
+/// Convolves `field` with the kernel and grows every cell by the result, wrapping around the edges
+fn grow(field: &Array2<f32>, kernel: &Kernel, growth: Growth) -> Array2<f32> {
+    let (height, width) = field.dim();
+    let field = field.as_standard_layout();
+    let values = field.as_slice().unwrap();
+    let taps = kernel.taps();
+    let mut grown = vec![0.0; width * height];
+
+    grown
+        .par_chunks_mut(width)
+        .enumerate()
+        .for_each(|(y, row)| {
+            // Adding up one tap at a time over the whole row, rather than one cell at a time, keeps every loop on contiguous memory
+            for &(dx, dy, weight) in &taps {
+                let shift = dx.rem_euclid(width as isize) as usize;
+                let source_y = (y as isize + dy).rem_euclid(height as isize) as usize;
+                let source = &values[source_y * width..(source_y + 1) * width];
+
+                for (cell, &value) in row
+                    .iter_mut()
+                    .zip(source[shift..].iter().chain(&source[..shift]))
+                {
+                    *cell += weight * value;
+                }
+            }
+
+            for (cell, &value) in row.iter_mut().zip(&values[y * width..(y + 1) * width]) {
+                *cell = growth.grow(value, *cell);
+            }
+        });
+
+    Array2::from_shape_vec((height, width), grown).unwrap()
+}
+
+/// Grows the one cell under the coordinates straight from the step before, for steps that weren't grown ahead of time.
+/// That's the first step after a genome is made or loaded, and any step a parent node shifts the time to.
+fn grow_cell<F: Fn(FloatColor) -> f32>(
+    state: UpdateState,
+    kernel: &Kernel,
+    growth: Growth,
+    value: F,
+) -> f32 {
+    let (x, y) = state.history.cell_at(state.coordinate_set);
+    let t = state.coordinate_set.t as usize;
+    let value_at = |dx, dy| {
+        let color = state.history.get_previous(x + dx, y + dy, t);
+        value(FloatColor::from(color))
+    };
+
+    let potential = kernel
+        .taps()
+        .into_iter()
+        .fold(0.0, |potential, (dx, dy, weight)| {
+            potential + weight * value_at(dx, dy)
+        });
+
+    growth.grow(value_at(0, 0), potential)
+}
+
+/// A node's output for every cell of the step at `t`, indexed by y and then x.
+/// Empty until the node is first updated.
+#[derive(Clone, Default)]
+pub struct GrownCells<T> {
+    t: usize,
+    cells: Array2<T>,
+}
+
+impl<T: Copy> GrownCells<T> {
+    /// The output for the cell under the coordinates, if the cells were grown for their step and cell array
+    pub fn get(&self, state: UpdateState) -> Option<T> {
+        let (width, height) = state.history.size();
+
+        if self.t != state.coordinate_set.t as usize || self.cells.dim() != (height, width) {
+            return None;
+        }
+
+        let (x, y) = state.history.cell_at(state.coordinate_set);
+
+        Some(
+            self.cells[[
+                y.rem_euclid(height as isize) as usize,
+                x.rem_euclid(width as isize) as usize,
+            ]],
+        )
+    }
+}
+
+impl<T> Debug for GrownCells<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let (height, width) = self.cells.dim();
+        write!(f, "GrownCells({}x{} at {})", width, height, self.t)
+    }
+}
+
+impl<T: Default> Generatable for GrownCells<T> {
+    fn generate_rng<R: Rng + ?Sized>(_rng: &mut R, _state: mutagen::State) -> Self {
+        Self::default()
+    }
+}
+
+// Nothing worth showing, since it's only ever filled in while the node is updated
+impl<T> Traversable for GrownCells<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{datatype::continuous::*, History};
+
+    const SIZE: usize = 16;
+
+    fn compute_cell(node: &LeniaNodes, history: &History, x: usize, y: usize) -> FloatColor {
+        node.compute(UpdateState {
+            coordinate_set: CoordinateSet {
+                x: UNFloat::new(x as f32 / SIZE as f32).to_signed(),
+                y: UNFloat::new(y as f32 / SIZE as f32).to_signed(),
+                t: 1.0,
+            },
+            history,
+        })
+    }
+
+    #[test]
+    fn test_grown_step_matches_direct() {
+        let mut history = History::new(SIZE, SIZE, 2);
+
+        for y in 4..9 {
+            for x in 5..8 {
+                history.set(x, y, 0, BitColor::White.get_color());
+            }
+        }
+
+        let kernel = Kernel {
+            radius: 3,
+            ring_weights: vec![UNFloat::new(1.0)],
+        };
+        let growth = Growth {
+            mu: 0.3,
+            sigma: 0.1,
+            dt: 0.2,
+        };
+        let direct = LeniaNodes::Brightness {
+            kernel: kernel.clone(),
+            growth,
+            cells: GrownCells::default(),
+        };
+
+        let context = UpdateContext {
+            history,
+            t: 1,
+            ..UpdateContext::default()
+        };
+        let mut grown = direct.clone();
+        grown.update(mutagen::State::default().with_context(&context));
+        let history = &context.history;
+
+        let expected = grow(
+            &channel(history.previous_cell_array(1), get_average),
+            &kernel,
+            growth,
+        );
+
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                assert_eq!(compute_cell(&grown, history, x, y).r, expected[[y, x]]);
+                assert_eq!(compute_cell(&direct, history, x, y).r, expected[[y, x]]);
+            }
+        }
+
+        // The cells around the block are close enough to mu to grow, and the ones far from it shrink back to nothing
+        assert!(expected[[6, 4]] > 0.0);
+        assert_eq!(expected[[0, 0]], 0.0);
+    }
+}
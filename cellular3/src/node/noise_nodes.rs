@@ -1,42 +1,29 @@
 use crate::{
     constants::*,
     datatype::{continuous::*, noisefunctions::*},
-    node::{mutagen_functions::*, Node},
+    node::Node,
     updatestate::*,
+    History,
 };
-use mutagen::{Generatable, Mutatable};
+use mutagen::{Crossover, Generatable, Mutatable, Traversable, Updatable};
 use noise::NoiseFn;
+use serde::{Deserialize, Serialize};
 
-#[derive(Mutatable, Generatable, Debug)]
+/// Which noise function a noise leaf samples.
+/// The leaf's already been placed by its parent by the time this is picked, so every variant is equally likely at any depth.
+#[derive(
+    Mutatable, Generatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 pub enum NoiseNodes {
-    #[mutagen(gen_weight = leaf_node_weight)]
     BasicMultiFractalNoise { noise: Box<BasicMultiFractalNoise> },
-
-    #[mutagen(gen_weight = leaf_node_weight)]
     BillowNoise { noise: Box<BasicMultiFractalNoise> },
-
-    #[mutagen(gen_weight = leaf_node_weight)]
     CheckerboardNoise { noise: Box<BasicMultiFractalNoise> },
-
-    #[mutagen(gen_weight = leaf_node_weight)]
     FractalBrownianNoise { noise: Box<FractalBrownianNoise> },
-
-    #[mutagen(gen_weight = leaf_node_weight)]
     HybridMultiFractalNoise { noise: Box<BasicMultiFractalNoise> },
-
-    #[mutagen(gen_weight = leaf_node_weight)]
     OpenSimplexNoise { noise: Box<OpenSimplexNoise> },
-
-    #[mutagen(gen_weight = leaf_node_weight)]
     RidgedMultiFractalNoise { noise: Box<RidgedMultiFractalNoise> },
-
-    #[mutagen(gen_weight = leaf_node_weight)]
     SuperSimplexNoise { noise: Box<SuperSimplexNoise> },
-
-    #[mutagen(gen_weight = leaf_node_weight)]
     ValueNoise { noise: Box<RidgedMultiFractalNoise> },
-
-    #[mutagen(gen_weight = leaf_node_weight)]
     WorleyNoise { noise: Box<WorleyNoise> },
 }
 
@@ -219,4 +206,121 @@ impl Node for NoiseNodes {
             ),
         }
     }
+
+    fn compute_batch(
+        &self,
+        coordinate_sets: &[CoordinateSet],
+        _history: &History,
+        out: &mut Vec<Self::Output>,
+    ) {
+        use NoiseNodes::*;
+
+        match self {
+            BasicMultiFractalNoise { noise } => sample_batch(
+                &noise.noise,
+                noise.x_scale.into_inner().powf(2.0),
+                noise.y_scale.into_inner().powf(2.0),
+                noise.t_scale.into_inner(),
+                coordinate_sets,
+                out,
+            ),
+            BillowNoise { noise } => sample_batch(
+                &noise.noise,
+                noise.x_scale.into_inner().powf(2.0),
+                noise.y_scale.into_inner().powf(2.0),
+                noise.t_scale.into_inner(),
+                coordinate_sets,
+                out,
+            ),
+            CheckerboardNoise { noise } => sample_batch(
+                &noise.noise,
+                noise.x_scale.into_inner().powf(2.0),
+                noise.y_scale.into_inner().powf(2.0),
+                noise.t_scale.into_inner(),
+                coordinate_sets,
+                out,
+            ),
+            FractalBrownianNoise { noise } => sample_batch(
+                &noise.noise,
+                noise.x_scale.into_inner().powf(2.0),
+                noise.y_scale.into_inner().powf(2.0),
+                noise.t_scale.into_inner(),
+                coordinate_sets,
+                out,
+            ),
+            HybridMultiFractalNoise { noise } => sample_batch(
+                &noise.noise,
+                noise.x_scale.into_inner().powf(2.0),
+                noise.y_scale.into_inner().powf(2.0),
+                noise.t_scale.into_inner(),
+                coordinate_sets,
+                out,
+            ),
+            OpenSimplexNoise { noise } => sample_batch(
+                &noise.noise,
+                noise.x_scale.into_inner().powf(2.0),
+                noise.y_scale.into_inner().powf(2.0),
+                noise.t_scale.into_inner(),
+                coordinate_sets,
+                out,
+            ),
+            RidgedMultiFractalNoise { noise } => sample_batch(
+                &noise.noise,
+                noise.x_scale.into_inner().powf(2.0),
+                noise.y_scale.into_inner().powf(2.0),
+                noise.t_scale.into_inner(),
+                coordinate_sets,
+                out,
+            ),
+            SuperSimplexNoise { noise } => sample_batch(
+                &noise.noise,
+                noise.x_scale.into_inner().powf(2.0),
+                noise.y_scale.into_inner().powf(2.0),
+                noise.t_scale.into_inner(),
+                coordinate_sets,
+                out,
+            ),
+            ValueNoise { noise } => sample_batch(
+                &noise.noise,
+                noise.x_scale.into_inner().powf(2.0),
+                noise.y_scale.into_inner().powf(2.0),
+                noise.t_scale.into_inner(),
+                coordinate_sets,
+                out,
+            ),
+            WorleyNoise { noise } => sample_batch(
+                &noise.noise,
+                noise.x_scale.into_inner().powf(2.0),
+                noise.y_scale.into_inner().powf(2.0),
+                noise.t_scale.into_inner().powf(2.0),
+                coordinate_sets,
+                out,
+            ),
+        }
+    }
+}
+
+/// Samples a noise function at every coordinate set, working out the node's scales once for the whole batch
+fn sample_batch<N: NoiseFn<[f64; 3]>>(
+    noise: &N,
+    x_scale: f32,
+    y_scale: f32,
+    t_scale: f32,
+    coordinate_sets: &[CoordinateSet],
+    out: &mut Vec<SNFloat>,
+) {
+    let (x_scale, y_scale, t_scale) = (x_scale as f64, y_scale as f64, t_scale as f64);
+
+    out.extend(coordinate_sets.iter().map(|coordinate_set| {
+        SNFloat::new(
+            noise
+                .get([
+                    coordinate_set.x.into_inner() as f64 * x_scale * CONSTS.noise_x_scale_factor,
+                    coordinate_set.y.into_inner() as f64 * y_scale * CONSTS.noise_y_scale_factor,
+                    coordinate_set.t as f64 * t_scale * CONSTS.noise_t_scale_factor,
+                ])
+                .min(1.0)
+                .max(0.0) as f32,
+        )
+    }));
 }
@@ -1,14 +1,18 @@
 use crate::{
-    datatype::{points::*},
+    datatype::{continuous::*, points::*},
     node::{
-        continuous_nodes::*, Node, mutagen_functions::*
+        continuous_nodes::*, Node, mutagen_functions::*, update_state, Simplify
     },
     updatestate::*,
+    vm::{Compile, Compiler, Instruction, Op, Reg, Registers, Step},
 };
-use mutagen::{Generatable, Mutatable};
+use mutagen::{Crossover, Generatable, Mutatable, Traversable, Updatable};
 use nalgebra::*;
+use serde::{Deserialize, Serialize};
 //Note: SNPoints are not normalised in the matematical sense, each coordinate is simply capped at -1..1
-#[derive(Generatable, Mutatable, Debug)]
+#[derive(
+    Generatable, Mutatable, Traversable, Updatable, Crossover, Serialize, Deserialize, Debug, Clone,
+)]
 #[mutagen(mut_reroll = 0.1)]
 pub enum SNPointNodes {
     #[mutagen(gen_weight = leaf_node_weight)]
@@ -26,6 +30,15 @@ pub enum SNPointNodes {
         child_a: Box<SNFloatNodes>,
         child_b: Box<SNFloatNodes>,
     },
+    /// Wanders in a random direction every step, by up to `step_size` along each axis
+    #[mutagen(gen_weight = leaf_node_weight)]
+    #[mutagen(update = walk)]
+    RandomWalk {
+        step_size: UNFloat,
+        #[serde(skip, default = "SNPoint::zero")]
+        #[mutagen(mut_weight = 0.0)]
+        position: SNPoint,
+    },
 }
 
 impl Node for SNPointNodes {
@@ -45,6 +58,124 @@ impl Node for SNPointNodes {
                 child_a.compute(state).into_inner(),
                 child_b.compute(state).into_inner(),
             )),
+            RandomWalk { position, .. } => *position,
+        }
+    }
+}
+
+fn walk(node: &mut SNPointNodes, state: mutagen::State) {
+    if let (SNPointNodes::RandomWalk { step_size, position }, Some(state)) =
+        (node, update_state(&state))
+    {
+        let point = position.into_inner();
+        // Seeded from where the walk is as well as the step, so walks that start apart don't move in step
+        let mut rng = CoordinateSet {
+            x: SNFloat::new(point.x),
+            y: SNFloat::new(point.y),
+            ..state.coordinate_set
+        }
+        .get_rng();
+        let step = SNPoint::generate_rng(&mut rng, mutagen::State::default()).into_inner();
+        let step_size = step_size.into_inner();
+
+        *position = SNPoint::new(Point2::new(
+            (point.x + step.x * step_size).clamp(-1.0, 1.0),
+            (point.y + step.y * step_size).clamp(-1.0, 1.0),
+        ));
+    }
+}
+
+impl Simplify for SNPointNodes {
+    fn simplify(self) -> Self {
+        use SNPointNodes::*;
+
+        match self {
+            Invert { child } => match *child.simplify() {
+                Constant { value } => {
+                    let point = value.into_inner();
+                    Constant {
+                        value: SNPoint::new(Point2::new(point.x * -1.0, point.y * -1.0)),
+                    }
+                }
+                Invert { child } => *child,
+                child => Invert {
+                    child: Box::new(child),
+                },
+            },
+            FromSNFloats { child_a, child_b } => match (*child_a.simplify(), *child_b.simplify()) {
+                (SNFloatNodes::Constant { value: a }, SNFloatNodes::Constant { value: b }) => {
+                    Constant {
+                        value: SNPoint::new(Point2::new(a.into_inner(), b.into_inner())),
+                    }
+                }
+                (child_a, child_b) => FromSNFloats {
+                    child_a: Box::new(child_a),
+                    child_b: Box::new(child_b),
+                },
+            },
+            Zero | Constant { .. } | RandomWalk { .. } => self,
         }
     }
 }
+
+/// What an [SNPointNodes] node does once its children have been computed into registers
+#[derive(Debug)]
+pub enum SNPointOp {
+    Leaf(SNPointNodes),
+    Invert {
+        child: Reg<SNPoint>,
+    },
+    FromSNFloats {
+        child_a: Reg<SNFloat>,
+        child_b: Reg<SNFloat>,
+    },
+}
+
+impl Op for SNPointOp {
+    type Output = SNPoint;
+
+    fn execute(&self, registers: &Registers, state: UpdateState) -> Self::Output {
+        use SNPointOp::*;
+
+        match self {
+            Leaf(node) => node.compute(state),
+            Invert { child } => {
+                let point = registers.get(*child).into_inner();
+                SNPoint::new(Point2::new(point.x * -1.0, point.y * -1.0))
+            }
+            FromSNFloats { child_a, child_b } => SNPoint::new(Point2::new(
+                registers.get(*child_a).into_inner(),
+                registers.get(*child_b).into_inner(),
+            )),
+        }
+    }
+
+    fn update(&mut self, state: mutagen::State) {
+        if let SNPointOp::Leaf(node) = self {
+            node.update(state);
+        }
+    }
+
+    fn instruction(step: Step<Self>) -> Instruction {
+        Instruction::SNPoint(step)
+    }
+}
+
+impl Compile for SNPointNodes {
+    fn compile(&self, compiler: &mut Compiler, coords: Reg<CoordinateSet>, out: Reg<SNPoint>) {
+        use SNPointNodes::*;
+
+        let op = match self {
+            Invert { child } => SNPointOp::Invert {
+                child: compiler.input(&**child, coords),
+            },
+            FromSNFloats { child_a, child_b } => SNPointOp::FromSNFloats {
+                child_a: compiler.input(&**child_a, coords),
+                child_b: compiler.input(&**child_b, coords),
+            },
+            Zero | Constant { .. } | RandomWalk { .. } => SNPointOp::Leaf(self.clone()),
+        };
+
+        compiler.emit(op, coords, out);
+    }
+}
@@ -0,0 +1,295 @@
+use mutagen::{Crossover, Generatable, Mutatable, Traversable, Updatable};
+use ndarray::{Array2, Zip};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    datatype::{colors::*, continuous::*, reaction_diffusion::*},
+    node::{
+        continuous_nodes::*, lenia_nodes::channel, mutagen_functions::*, Node, Simplify,
+        UpdateContext,
+    },
+    updatestate::*,
+    History,
+};
+
+//Most rates take thousands of steps of the reaction to settle into a pattern, so each update runs a few
+const MAX_ITERATIONS: usize = 8;
+
+/// Gray-Scott reaction-diffusion, on a grid of its own the size of the cell array, which runs forward every time the trees are updated.
+/// The grid is seeded from the brightness of the newest history step the first time it's updated.
+/// Outputs the concentrations of u and then v at the cell under the coordinates.
+#[derive(Generatable, Mutatable, Traversable, Crossover, Serialize, Deserialize, Debug, Clone)]
+#[mutagen(mut_reroll = 0.1)]
+pub enum ReactionDiffusionNodes {
+    /// The same rates everywhere
+    Uniform {
+        rates: Rates,
+        #[mutagen(range = 1..=MAX_ITERATIONS)]
+        iterations: usize,
+        #[serde(skip)]
+        #[mutagen(mut_weight = 0.0, cross_recurse = 0.0)]
+        concentrations: Concentrations,
+    },
+    /// Blends from one set of rates to another at each cell, by what `modulation` computes there
+    #[mutagen(gen_weight = pipe_node_weight)]
+    Modulated {
+        rates_a: Rates,
+        rates_b: Rates,
+        modulation: Box<UNFloatNodes>,
+        #[mutagen(range = 1..=MAX_ITERATIONS)]
+        iterations: usize,
+        #[serde(skip)]
+        #[mutagen(mut_weight = 0.0, cross_recurse = 0.0)]
+        concentrations: Concentrations,
+    },
+}
+
+impl ReactionDiffusionNodes {
+    fn concentrations(&self) -> &Concentrations {
+        use ReactionDiffusionNodes::*;
+
+        match self {
+            Uniform { concentrations, .. } | Modulated { concentrations, .. } => concentrations,
+        }
+    }
+
+    fn concentrations_mut(&mut self) -> &mut Concentrations {
+        use ReactionDiffusionNodes::*;
+
+        match self {
+            Uniform { concentrations, .. } | Modulated { concentrations, .. } => concentrations,
+        }
+    }
+}
+
+impl Node for ReactionDiffusionNodes {
+    type Output = (UNFloat, UNFloat);
+
+    fn compute(&self, state: UpdateState) -> Self::Output {
+        let (x, y) = state.history.cell_at(state.coordinate_set);
+        let (u, v) = self.concentrations().get(x, y);
+
+        (UNFloat::new(u), UNFloat::new(v))
+    }
+}
+
+/// Runs the reaction on from the grid the last update left, which needs an [UpdateContext] passed down.
+/// When following, the grid is copied from the node with the same id that reacted before instead, if there is one.
+impl Updatable for ReactionDiffusionNodes {
+    fn update(&mut self, state: mutagen::State) {
+        use ReactionDiffusionNodes::*;
+
+        let UpdateContext {
+            history,
+            t,
+            following,
+            reacted,
+        } = match state.context::<UpdateContext>() {
+            Some(context) => context,
+            None => return,
+        };
+
+        if *following {
+            if let Modulated { modulation, .. } = self {
+                modulation.update(state.deepen());
+            }
+
+            let concentrations = self.concentrations_mut();
+            if let Some(grid) = reacted.borrow().get(&concentrations.id()) {
+                *concentrations = grid.clone();
+            }
+
+            return;
+        }
+
+        match self {
+            Uniform {
+                rates,
+                iterations,
+                concentrations,
+            } => {
+                fit_to(concentrations, history, *t);
+
+                for _ in 0..*iterations {
+                    concentrations.react(|_, _| *rates);
+                }
+            }
+            Modulated {
+                rates_a,
+                rates_b,
+                modulation,
+                iterations,
+                concentrations,
+            } => {
+                modulation.update(state.deepen());
+                fit_to(concentrations, history, *t);
+
+                let (height, width) = concentrations.dim();
+                let t = *t as f32;
+                let mut amounts = Array2::zeros((height, width));
+
+                // Computed the same way as the cells of the next step will be, since the trees haven't changed in between
+                Zip::indexed(&mut amounts).par_apply(|(y, x), amount: &mut f32| {
+                    let coordinate_set = CoordinateSet {
+                        x: UNFloat::new(x as f32 / width as f32).to_signed(),
+                        y: UNFloat::new(y as f32 / height as f32).to_signed(),
+                        t,
+                    };
+
+                    *amount = modulation
+                        .compute(UpdateState {
+                            coordinate_set,
+                            history,
+                        })
+                        .into_inner();
+                });
+
+                for _ in 0..*iterations {
+                    concentrations.react(|x, y| rates_a.lerp(*rates_b, amounts[[y, x]]));
+                }
+            }
+        }
+
+        let concentrations = self.concentrations();
+        reacted
+            .borrow_mut()
+            .insert(concentrations.id(), concentrations.clone());
+    }
+
+    fn is_stateful(&self) -> bool {
+        true
+    }
+}
+
+/// Seeds the grid from the step before `t`, unless it's already the same size as the cell array
+fn fit_to(concentrations: &mut Concentrations, history: &History, t: usize) {
+    let (width, height) = history.size();
+
+    if concentrations.dim() != (height, width) {
+        let newest = history.previous_cell_array(t);
+        concentrations.seed(&channel(newest, get_average));
+    }
+}
+
+impl Simplify for ReactionDiffusionNodes {
+    fn simplify(self) -> Self {
+        use ReactionDiffusionNodes::*;
+
+        match self {
+            Modulated {
+                rates_a,
+                rates_b,
+                modulation,
+                iterations,
+                concentrations,
+            } => match *modulation.simplify() {
+                UNFloatNodes::Constant { value } => Uniform {
+                    rates: rates_a.lerp(rates_b, value.into_inner()),
+                    iterations,
+                    concentrations,
+                },
+                modulation => Modulated {
+                    rates_a,
+                    rates_b,
+                    modulation: Box::new(modulation),
+                    iterations,
+                    concentrations,
+                },
+            },
+            Uniform { .. } => self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 16;
+
+    fn rates(feed: f32) -> Rates {
+        Rates {
+            feed,
+            kill: 0.0649,
+            diffusion_u: 1.0,
+            diffusion_v: 0.5,
+        }
+    }
+
+    /// Updates as the step after the one with a white square in it
+    fn context() -> UpdateContext {
+        let mut history = History::new(SIZE, SIZE, 2);
+
+        for y in 6..10 {
+            for x in 6..10 {
+                history.set(x, y, 0, BitColor::White.get_color());
+            }
+        }
+
+        UpdateContext {
+            history,
+            t: 1,
+            ..UpdateContext::default()
+        }
+    }
+
+    fn updated(node: &mut ReactionDiffusionNodes, context: &UpdateContext) -> Concentrations {
+        node.update(mutagen::State::default().with_context(context));
+        node.concentrations().clone()
+    }
+
+    #[test]
+    fn test_update_runs_reaction() {
+        let context = context();
+        let mut node = ReactionDiffusionNodes::Uniform {
+            rates: rates(0.0367),
+            iterations: 2,
+            concentrations: Concentrations::default(),
+        };
+
+        // Nothing happens without a history to seed the grid from
+        node.update(mutagen::State::default());
+        assert_eq!(node.concentrations().dim(), (0, 0));
+
+        let mut expected = Concentrations::default();
+        expected.seed(&channel(
+            context.history.previous_cell_array(context.t),
+            get_average,
+        ));
+        expected.react(|_, _| rates(0.0367));
+        expected.react(|_, _| rates(0.0367));
+
+        let concentrations = updated(&mut node, &context);
+        assert_eq!(concentrations.get(8, 8), expected.get(8, 8));
+        assert_eq!(concentrations.get(5, 8), expected.get(5, 8));
+    }
+
+    #[test]
+    fn test_constant_modulation_simplifies() {
+        let context = context();
+        let modulated = ReactionDiffusionNodes::Modulated {
+            rates_a: rates(0.03),
+            rates_b: rates(0.05),
+            modulation: Box::new(UNFloatNodes::Constant {
+                value: UNFloat::new(0.5),
+            }),
+            iterations: 3,
+            concentrations: Concentrations::default(),
+        };
+
+        let mut simplified = modulated.clone().simplify();
+        assert!(matches!(simplified, ReactionDiffusionNodes::Uniform { .. }));
+
+        // Both blend the rates the same way, so they should react the same
+        let mut modulated = modulated;
+        let a = updated(&mut modulated, &context);
+        let b = updated(&mut simplified, &context);
+
+        for y in 0..SIZE as isize {
+            for x in 0..SIZE as isize {
+                let ((a_u, a_v), (b_u, b_v)) = (a.get(x, y), b.get(x, y));
+                assert!((a_u - b_u).abs() < 1e-6 && (a_v - b_v).abs() < 1e-6);
+            }
+        }
+    }
+}
@@ -1,8 +1,137 @@
+use std::path::PathBuf;
+
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
 pub struct Opts {
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+
     /// A number to seed the rng with
     #[structopt(long)]
     pub seed: Option<u128>,
+
+    /// A saved genome to resume instead of generating a new one
+    #[structopt(long, parse(from_os_str), conflicts_with = "trees")]
+    pub genome: Option<PathBuf>,
+
+    /// Root trees written in the tree format, with any roots left out generated as usual
+    #[structopt(long, parse(from_os_str))]
+    pub trees: Option<PathBuf>,
+
+    /// The path of a subtree for mutation to leave alone, such as root_node.child_a, which can be given more than once.
+    /// Run the paths command to list the paths in a saved genome.
+    #[structopt(long, number_of_values = 1)]
+    pub freeze: Vec<String>,
+
+    /// Record everything needed to replay this run exactly into a log file
+    #[structopt(long, parse(from_os_str), conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Replay a log file recorded with --record, using the seed it was recorded with
+    #[structopt(long, parse(from_os_str))]
+    pub replay: Option<PathBuf>,
+
+    /// Directory to save genomes to
+    #[structopt(long, default_value = "genomes", parse(from_os_str))]
+    pub genome_dir: PathBuf,
+
+    /// Run without a window, rendering frames to disk instead
+    #[structopt(long)]
+    pub headless: bool,
+
+    /// Number of frames to render in headless mode, or generations to evolve when evolving
+    #[structopt(long, default_value = "100")]
+    pub frames: usize,
+
+    /// Directory to write frames to in headless mode
+    #[structopt(long, default_value = "frames", parse(from_os_str))]
+    pub output_dir: PathBuf,
+
+    /// Show a grid of mutated variants of the trees, and breed from whichever one is clicked
+    #[structopt(long, conflicts_with_all = &["headless", "evolve", "record", "replay"])]
+    pub interactive: bool,
+
+    /// Number of variants along each side of the grid in interactive mode
+    #[structopt(long, default_value = "3")]
+    pub grid_size: usize,
+
+    #[structopt(flatten)]
+    pub evolution: EvolutionOpts,
+}
+
+#[derive(StructOpt, Clone)]
+pub struct EvolutionOpts {
+    /// Evolve a population of genomes by selection and show the best of each generation,
+    /// instead of mutating a single genome whenever a metric breaks its rule
+    #[structopt(long, conflicts_with_all = &["record", "replay"])]
+    pub evolve: bool,
+
+    /// Number of genomes in the population
+    #[structopt(long, default_value = "12")]
+    pub population: usize,
+
+    /// Number of genomes competing in each tournament to become a parent
+    #[structopt(long, default_value = "3")]
+    pub tournament_size: usize,
+
+    /// Number of the best genomes carried over unchanged into each generation
+    #[structopt(long, default_value = "2")]
+    pub elites: usize,
+
+    /// Chance of a child being crossed over from two parents instead of copied from one, before it's mutated
+    #[structopt(long, default_value = "0.5")]
+    pub crossover_chance: f64,
+
+    /// Number of steps to run each genome for when scoring it
+    #[structopt(long, default_value = "8")]
+    pub scoring_steps: usize,
+
+    /// CSV file to log statistics about each generation to
+    #[structopt(long, default_value = "evolution.csv", parse(from_os_str))]
+    pub evolution_log: PathBuf,
+}
+
+#[derive(StructOpt)]
+pub enum Command {
+    /// Export a saved genome as a Graphviz DOT graph
+    Dot {
+        /// The saved genome to export
+        #[structopt(parse(from_os_str))]
+        genome: PathBuf,
+
+        /// Where to write the graph, defaulting to the genome's path with a .dot extension
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Print a saved genome in the tree format, ready to be edited and passed to --trees
+    Print {
+        /// The saved genome to print
+        #[structopt(parse(from_os_str))]
+        genome: PathBuf,
+
+        /// Where to write the trees, defaulting to standard output
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// List the path of every node in a saved genome, for choosing subtrees to --freeze
+    Paths {
+        /// The saved genome to list
+        #[structopt(parse(from_os_str))]
+        genome: PathBuf,
+    },
+
+    /// Time computing cells in row batches and through compiled programs against computing the trees
+    /// recursively, using trees generated from the seed
+    Bench {
+        /// Number of trees to generate and time
+        #[structopt(long, default_value = "20")]
+        trees: usize,
+
+        /// Number of times to compute every cell with each tree
+        #[structopt(long, default_value = "3")]
+        passes: usize,
+    },
 }
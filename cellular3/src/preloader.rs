@@ -88,8 +88,9 @@ where
 {
     fn drop(&mut self) {
         info!("Shutting down preloader thread");
-        let mut running = self.running.lock().unwrap();
-        if *running {
+        // The child checks the flag between items, so it can't stay locked while waiting for the child to finish
+        let was_running = std::mem::replace(&mut *self.running.lock().unwrap(), false);
+        if was_running {
             let child_thread = self.child_thread.take().unwrap();
             debug!(
                 "Parent thread {:?} shutting down child preloader thread {:?}",
@@ -97,8 +98,6 @@ where
                 child_thread.thread().id()
             );
 
-            *running = false;
-
             loop {
                 if self.receiver.try_recv().is_err() {
                     break;
@@ -115,3 +114,11 @@ pub trait Generator {
 
     fn generate(&mut self) -> Self::Output;
 }
+
+impl<G: Generator + ?Sized> Generator for Box<G> {
+    type Output = G::Output;
+
+    fn generate(&mut self) -> Self::Output {
+        (**self).generate()
+    }
+}
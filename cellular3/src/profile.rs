@@ -0,0 +1,229 @@
+//! Grammar profiles, which change how often each kind of node is generated and rerolled without recompiling
+
+use std::{collections::HashMap, fs, path::Path};
+
+use failure::{format_err, Fallible};
+use lazy_static::lazy_static;
+use log::info;
+use mutagen::{Generatable, Profile};
+use serde::Deserialize;
+
+use crate::{
+    constants::*,
+    node::{
+        automata_nodes::*, color_blend_nodes::*, color_nodes::*, continuous_nodes::*,
+        coord_map_nodes::*, discrete_nodes::*, lenia_nodes::*, noise_nodes::*, point_nodes::*,
+        reaction_diffusion_nodes::*,
+    },
+};
+
+/// Pairs the name of each node type with the names of its variants
+macro_rules! node_types {
+    ($($node_type:ident),* $(,)?) => {
+        vec![$((stringify!($node_type), $node_type::variant_names())),*]
+    };
+}
+
+lazy_static! {
+    /// The profile named in constants.yml, or an empty one that leaves every weight to the node attributes
+    pub static ref PROFILE: Profile = match &CONSTS.grammar_profile {
+        Some(path) => load(path)
+            .unwrap_or_else(|e| panic!("Failed to load grammar profile {}: {}", path, e)),
+        None => Profile::new(),
+    };
+}
+
+/// A grammar profile as written in YAML, with every table keyed by node type and then variant, such as
+///
+/// ```yaml
+/// gen_weights:
+///   SNFloatNodes:
+///     NoiseFunction: 4.0
+/// mut_rerolls:
+///   FloatColorNodes:
+///     FromImage: 0.9
+/// ```
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct GrammarProfile {
+    // Multiplies each variant's gen_weight
+    gen_weights: HashMap<String, HashMap<String, f64>>,
+    // Replaces each variant's mut_reroll
+    mut_rerolls: HashMap<String, HashMap<String, f64>>,
+}
+
+/// Every node type a profile can weight, with the variants it can name for each
+fn known_variants() -> HashMap<&'static str, &'static [&'static str]> {
+    node_types![
+        AngleNodes,
+        AutomataNodes,
+        BitColorNodes,
+        BooleanNodes,
+        ByteColorNodes,
+        ByteNodes,
+        ColorBlendNodes,
+        CoordMapNodes,
+        FloatColorNodes,
+        LeniaNodes,
+        NibbleNodes,
+        NoiseNodes,
+        ReactionDiffusionNodes,
+        SIntNodes,
+        SNFloatNodes,
+        SNPointNodes,
+        UIntNodes,
+        UNFloatNodes,
+    ]
+    .into_iter()
+    .collect()
+}
+
+/// Fails on the first node type or variant in `table` that doesn't exist, which would otherwise be silently ignored
+fn check_names(table_name: &str, table: &HashMap<String, HashMap<String, f64>>) -> Fallible<()> {
+    let known = known_variants();
+
+    for (node_type, variants) in table {
+        let known_variants = known.get(node_type.as_str()).ok_or_else(|| {
+            format_err!(
+                "{} names {}, which isn't a node type",
+                table_name,
+                node_type
+            )
+        })?;
+
+        if let Some(variant) = variants
+            .keys()
+            .find(|variant| !known_variants.contains(&variant.as_str()))
+        {
+            return Err(format_err!(
+                "{} names {}::{}, which isn't a variant of {}",
+                table_name,
+                node_type,
+                variant,
+                node_type
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads a grammar profile from a YAML file, failing on any node type or variant it names that doesn't exist
+pub fn load<P: AsRef<Path>>(path: P) -> Fallible<Profile> {
+    let grammar: GrammarProfile = serde_yaml::from_str(&fs::read_to_string(&path)?)?;
+    check_names("gen_weights", &grammar.gen_weights)?;
+    check_names("mut_rerolls", &grammar.mut_rerolls)?;
+
+    let mut profile = Profile::new();
+
+    for (node_type, variants) in &grammar.gen_weights {
+        for (variant, &scale) in variants {
+            if scale < 0.0 {
+                return Err(format_err!(
+                    "gen_weight for {}::{} is {}, but can't be negative",
+                    node_type,
+                    variant,
+                    scale
+                ));
+            }
+
+            profile.set_gen_weight(node_type, variant, scale);
+        }
+    }
+
+    for (node_type, variants) in &grammar.mut_rerolls {
+        for (variant, &probability) in variants {
+            if !(0.0..=1.0).contains(&probability) {
+                return Err(format_err!(
+                    "mut_reroll for {}::{} is {}, but has to be between 0 and 1",
+                    node_type,
+                    variant,
+                    probability
+                ));
+            }
+
+            profile.set_mut_reroll(node_type, variant, probability);
+        }
+    }
+
+    info!(
+        "Loaded grammar profile from {}",
+        path.as_ref().to_string_lossy()
+    );
+
+    Ok(profile)
+}
+
+/// The state to start generating or mutating a tree from, which looks up weights in the grammar profile
+pub fn mutagen_state() -> mutagen::State<'static> {
+    mutagen::State::default().with_profile(&PROFILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_str(name: &str, yaml: &str) -> Fallible<Profile> {
+        let path = std::env::temp_dir().join(format!("grammar_profile_{}.yml", name));
+        fs::write(&path, yaml)?;
+        load(&path)
+    }
+
+    #[test]
+    fn test_example_profiles() {
+        for path in &["../profiles/noise_heavy.yml", "../profiles/image_heavy.yml"] {
+            load(path).unwrap_or_else(|e| panic!("Failed to load {}: {}", path, e));
+        }
+    }
+
+    #[test]
+    fn test_invalid_profiles() {
+        let profiles = [
+            (
+                "valid",
+                "gen_weights: {SNFloatNodes: {NoiseFunction: 2.0}}",
+                true,
+            ),
+            (
+                "negative",
+                "gen_weights: {SNFloatNodes: {NoiseFunction: -1.0}}",
+                false,
+            ),
+            (
+                "improbable",
+                "mut_rerolls: {SNFloatNodes: {NoiseFunction: 1.5}}",
+                false,
+            ),
+            (
+                "misspelt",
+                "gen_weight: {SNFloatNodes: {NoiseFunction: 2.0}}",
+                false,
+            ),
+        ];
+
+        for &(name, yaml, valid) in &profiles {
+            assert_eq!(load_str(name, yaml).is_ok(), valid, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_unknown_names() {
+        let profiles = [
+            (
+                "unknown_type",
+                "gen_weights: {SNFloatNode: {NoiseFunction: 2.0}}",
+                "SNFloatNode",
+            ),
+            (
+                "unknown_variant",
+                "mut_rerolls: {FloatColorNodes: {FromImages: 0.5}}",
+                "FloatColorNodes::FromImages",
+            ),
+        ];
+
+        for &(name, yaml, bad_key) in &profiles {
+            let error = load_str(name, yaml).err().unwrap().to_string();
+            assert!(error.contains(bad_key), "{}: {}", name, error);
+        }
+    }
+}
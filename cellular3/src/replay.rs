@@ -0,0 +1,184 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use failure::{format_err, Fallible};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::datatype::image::{Image, ImageSource};
+
+thread_local! {
+    /// Whether the current run is being recorded, replayed, or neither.
+    /// Only the thread stepping the simulation records or replays, so simulations on other threads,
+    /// like the ones scored while evolving, can't add to the log or take from it.
+    pub static REPLAY: RefCell<Replay> = const { RefCell::new(Replay::Off) };
+}
+
+/// Something that happened during a run that can't be reproduced from the seed alone
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum ReplayEvent {
+    /// The trees were mutated at the end of tic `t`
+    Mutation { t: usize, cause: MutationCause },
+    /// The cell array was reseeded at the end of tic `t`, after any mutation
+    Reseed { t: usize, cause: ReseedCause },
+    /// An image was handed out to a newly generated node
+    Image { source: ImageSource },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutationCause {
+    /// Space was pressed, or the trees had never been mutated yet
+    TreeDirty,
+    /// A metric broke its mutation rule
+    Stats,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReseedCause {
+    /// R was pressed
+    Requested,
+    /// A metric broke its reseed rule
+    Stats,
+}
+
+/// The first line of a replay log, before any events
+#[derive(Serialize, Deserialize)]
+struct ReplayHeader {
+    // Stored the same way as in last_seed.txt so it survives formats without 128 bit integers
+    seed: String,
+}
+
+pub enum Replay {
+    Off,
+    /// Appends every event to a log file, one JSON object per line
+    Recording(BufWriter<File>),
+    /// Feeds back the events from a log file in the order they were recorded
+    Playing(VecDeque<ReplayEvent>),
+}
+
+impl Replay {
+    /// Starts a new log file at `path`, returning a replay that records into it
+    pub fn record<P: AsRef<Path>>(path: P, seed: u128) -> Fallible<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        write_line(
+            &mut writer,
+            &ReplayHeader {
+                seed: seed.to_string(),
+            },
+        )?;
+
+        Ok(Replay::Recording(writer))
+    }
+
+    /// Reads a log file written by [`record`](Replay::record), returning the seed it was recorded with
+    pub fn play<P: AsRef<Path>>(path: P) -> Fallible<(Self, u128)> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+
+        let header: ReplayHeader = serde_json::from_str(
+            &lines
+                .next()
+                .ok_or_else(|| format_err!("Replay log is empty"))??,
+        )?;
+
+        let events = lines
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect::<Fallible<_>>()?;
+
+        Ok((Replay::Playing(events), header.seed.parse()?))
+    }
+
+    /// Decides whether to mutate at the end of tic `t`.
+    /// `live` is the decision the simulation would make on its own, which a replay overrides.
+    pub fn mutation(&mut self, t: usize, live: Option<MutationCause>) -> Option<MutationCause> {
+        match self {
+            Replay::Off => live,
+            Replay::Recording(_) => {
+                if let Some(cause) = live {
+                    self.write(&ReplayEvent::Mutation { t, cause });
+                }
+
+                live
+            }
+            Replay::Playing(events) => match events.front() {
+                Some(&ReplayEvent::Mutation { t: event_t, cause }) if event_t == t => {
+                    events.pop_front();
+                    Some(cause)
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// Decides whether to reseed at the end of tic `t`, in the same way as [`mutation`](Replay::mutation)
+    pub fn reseed(&mut self, t: usize, live: Option<ReseedCause>) -> Option<ReseedCause> {
+        match self {
+            Replay::Off => live,
+            Replay::Recording(_) => {
+                if let Some(cause) = live {
+                    self.write(&ReplayEvent::Reseed { t, cause });
+                }
+
+                live
+            }
+            Replay::Playing(events) => match events.front() {
+                Some(&ReplayEvent::Reseed { t: event_t, cause }) if event_t == t => {
+                    events.pop_front();
+                    Some(cause)
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// Hands out an image for a newly generated node.
+    /// `live` provides the image the simulation would use on its own, which a replay overrides.
+    pub fn image<F: FnOnce() -> Image>(&mut self, live: F) -> Image {
+        match self {
+            Replay::Off => live(),
+            Replay::Recording(_) => {
+                let image = live();
+
+                self.write(&ReplayEvent::Image {
+                    source: image.source().clone(),
+                });
+
+                image
+            }
+            Replay::Playing(events) => match events.pop_front() {
+                Some(ReplayEvent::Image { source }) => {
+                    Image::load_source(&source).unwrap_or_else(|e| {
+                        panic!("Failed to load replayed image {:?}: {}", source, e)
+                    })
+                }
+                Some(event) => panic!("Replay log out of sync: expected an image, got {:?}", event),
+                None => {
+                    warn!("Replay log ran out of images");
+                    live()
+                }
+            },
+        }
+    }
+
+    fn write(&mut self, event: &ReplayEvent) {
+        if let Replay::Recording(writer) = self {
+            if let Err(e) = write_line(writer, event) {
+                error!("Failed to record replay event {:?}: {}", event, e);
+            }
+        }
+    }
+}
+
+// Flushes every line so that the log is still usable if the program doesn't exit cleanly
+fn write_line<T: Serialize>(writer: &mut BufWriter<File>, value: &T) -> Fallible<()> {
+    serde_json::to_writer(&mut *writer, value)?;
+    writeln!(writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,821 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use failure::Fallible;
+use itertools::Itertools;
+use log::{debug, info, warn};
+use mutagen::{Frozen, Generatable, Mutatable, Traversable, Updatable};
+use ndarray::{s, ArrayViewMut1, Axis};
+use rayon::prelude::*;
+
+use crate::{
+    constants::*,
+    datatype::{
+        colors::{ByteColor, FloatColor},
+        continuous::*,
+        reseeders::Reseeder,
+    },
+    genome::{Genome, PartialGenome, SavedGenome},
+    metric::MutationRule,
+    node::{Node, Simplify, UpdateContext},
+    opts::Opts,
+    profile::mutagen_state,
+    replay::{MutationCause, Replay, ReseedCause, REPLAY},
+    updatestate::*,
+    util::{DeterministicRng, RNG_SEED},
+    vm::{Program, Registers},
+    History, HistoryStep,
+};
+
+/// Creates the simulation requested on the command line, resuming a saved genome if one was given.
+/// Also applies the seed requested on the command line (if any), sets up recording or replaying, and records the seed in use.
+pub fn init_simulation(opts: &Opts) -> Fallible<Simulation> {
+    let saved = match &opts.genome {
+        Some(path) => {
+            info!("Loading genome from {}", path.to_string_lossy());
+            Some(SavedGenome::load(path)?)
+        }
+        None => None,
+    };
+
+    if let Some(seed) = opts.seed {
+        info!("Manually setting RNG seed");
+        *RNG_SEED.lock().unwrap() = seed;
+    } else if let Some(saved) = &saved {
+        info!("Using RNG seed from saved genome");
+        *RNG_SEED.lock().unwrap() = saved.seed;
+    }
+
+    if let Some(path) = &opts.replay {
+        info!("Replaying from {}", path.to_string_lossy());
+        let (replay, seed) = Replay::play(path)?;
+        REPLAY.with(|r| *r.borrow_mut() = replay);
+        *RNG_SEED.lock().unwrap() = seed;
+    } else if let Some(path) = &opts.record {
+        info!("Recording to {}", path.to_string_lossy());
+        let replay = Replay::record(path, *RNG_SEED.lock().unwrap())?;
+        REPLAY.with(|r| *r.borrow_mut() = replay);
+    }
+
+    fs::write("last_seed.txt", RNG_SEED.lock().unwrap().to_string())?;
+
+    let mut frozen_paths = opts.freeze.clone();
+
+    let mut simulation = match (saved, &opts.trees) {
+        (Some(saved), _) => Simulation::from_saved(saved),
+        (None, Some(path)) => {
+            info!("Loading trees from {}", path.to_string_lossy());
+            let partial = PartialGenome::load(path)?;
+            frozen_paths.extend(partial.frozen.iter().cloned());
+            Simulation::from_partial(partial)
+        }
+        (None, None) => Simulation::new(),
+    };
+
+    for path in &frozen_paths {
+        simulation.freeze(path);
+    }
+
+    Ok(simulation)
+}
+
+/// Everything needed to advance the cell array, independent of any window or graphics context
+pub struct Simulation {
+    pub history: History,
+    pub next_history_step: HistoryStep,
+
+    //The value of each metric in CONSTS.mutation_rules over time, calculated by averaging each new measurement and itself once an update
+    //Empty until the first update has been measured
+    metric_averages: Vec<f64>,
+    //The same for each metric in CONSTS.reseed_rules, starting over after every reseed
+    reseed_averages: Vec<f64>,
+    pub genome: Genome,
+    // A simplified copy of the genome, which is what actually gets computed
+    simplified: Genome,
+    // The simplified root node compiled into a program, which is what computes each cell
+    program: Program<FloatColor>,
+    //The genomes from before each mutation or load, newest last and at most CONSTS.genome_undo_length of them
+    undo_genomes: VecDeque<Genome>,
+    //The genomes stepped back from by undoing, newest last, until the next mutation
+    redo_genomes: Vec<Genome>,
+
+    pub tree_dirty: bool,
+    //Whether to reseed the cell array at the end of the current step, whatever the reseed rules say
+    pub reseed_requested: bool,
+    //The pattern the cell array is reseeded with, generated the first time it's needed and mutated before each reseed after that
+    reseeder: Option<Reseeder>,
+    //Subtrees that mutation leaves alone, by their path from the genome
+    pub frozen: Frozen,
+    //Whether breaking a mutation rule mutates the trees, which is turned off when evolving so that selection decides instead,
+    //and while locked to browse through earlier genomes
+    pub auto_mutate: bool,
+    //Whether breaking a reseed rule reseeds the cell array, which is turned off when scoring so that the score only depends on the genome
+    pub auto_reseed: bool,
+    pub current_t: usize,
+    rng: DeterministicRng,
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        let mut rng = DeterministicRng::new();
+        let genome = Genome::generate_rng(&mut rng, mutagen_state());
+
+        Self::with_genome(genome, rng)
+    }
+
+    /// Starts from hand written trees, generating any that are missing
+    pub fn from_partial(partial: PartialGenome) -> Self {
+        let mut rng = DeterministicRng::new();
+        let genome = partial.complete_rng(&mut rng, mutagen_state());
+
+        Self::with_genome(genome, rng)
+    }
+
+    pub fn from_saved(saved: SavedGenome) -> Self {
+        let SavedGenome {
+            genome, current_t, ..
+        } = saved;
+
+        let mut simulation = Self::from_genome(genome);
+        simulation.current_t = current_t;
+        simulation
+    }
+
+    /// Starts from an existing genome, without mutating it before it's had a chance to be seen
+    pub fn from_genome(genome: Genome) -> Self {
+        let mut simulation = Self::with_genome(genome, DeterministicRng::new());
+        simulation.tree_dirty = false;
+        simulation
+    }
+
+    /// Runs an existing genome on a smaller cell array than usual, for previewing it next to others
+    pub fn thumbnail(genome: Genome, width: usize, height: usize) -> Self {
+        let mut simulation = Self::with_size(genome, DeterministicRng::new(), width, height);
+        simulation.tree_dirty = false;
+        simulation
+    }
+
+    fn with_genome(genome: Genome, rng: DeterministicRng) -> Self {
+        Self::with_size(
+            genome,
+            rng,
+            CONSTS.cell_array_width,
+            CONSTS.cell_array_height,
+        )
+    }
+
+    fn with_size(genome: Genome, rng: DeterministicRng, width: usize, height: usize) -> Self {
+        let simplified = genome.clone().simplify();
+
+        Self {
+            next_history_step: HistoryStep::new(width, height),
+            history: History::new(width, height, CONSTS.cell_array_history_length),
+            metric_averages: Vec::new(),
+            reseed_averages: Vec::new(),
+
+            program: Program::compile(simplified.root_node.as_ref()),
+            simplified,
+            genome,
+            undo_genomes: VecDeque::new(),
+            redo_genomes: Vec::new(),
+
+            frozen: Frozen::new(),
+            tree_dirty: true,
+            reseed_requested: false,
+            reseeder: None,
+            auto_mutate: true,
+            auto_reseed: true,
+            current_t: 0,
+            rng,
+        }
+    }
+
+    /// Computes one horizontal slice of the next history step.
+    /// A full step is made of `tics_per_update` slices, indexed from 0.
+    pub fn update_slice(&mut self, slice_index: usize) {
+        let current_t = self.current_t;
+        let (height, width, _) = self.next_history_step.cell_array.dim();
+
+        // Spread any leftover rows between the slices, so thumbnails too small to divide evenly are still fully computed
+        let slice_y = slice_index * height / CONSTS.tics_per_update;
+        let slice_y_range = slice_y..(slice_index + 1) * height / CONSTS.tics_per_update;
+
+        let mut new_update_slice =
+            self.next_history_step
+                .cell_array
+                .slice_mut(s![slice_y_range, .., ..]);
+        let new_update_iter = new_update_slice.lanes_mut(Axis(2));
+
+        let history = &self.history;
+
+        //let rule_sets = self.rule_sets;
+
+        let program = &self.program;
+
+        let update_step = |registers: &mut Registers, y, x, mut new: ArrayViewMut1<u8>| {
+            // let neighbour_result =
+            //     get_alive_neighbours(cell_array_view, x as i32, y as i32 + slice_y);
+
+            let coordinate_set = CoordinateSet {
+                x: UNFloat::new(x as f32 / width as f32).to_signed(),
+                y: UNFloat::new((y + slice_y) as f32 / height as f32).to_signed(),
+                t: current_t as f32,
+            };
+
+            let compute_result = program.run(
+                registers,
+                UpdateState {
+                    coordinate_set,
+                    history,
+                },
+            ); //get_next_color(rule_sets, *current, neighbour_result.0);
+
+            let new_color = ByteColor::from(compute_result);
+
+            new[0] = new_color.r;
+            new[1] = new_color.g;
+            new[2] = new_color.b;
+            new[3] = new_color.a;
+        };
+
+        let zip = ndarray::Zip::indexed(new_update_iter);
+
+        if CONSTS.parallelize {
+            // Each thread gets its own registers, which are reused for every cell it computes
+            zip.into_par_iter().for_each_init(
+                || program.registers(),
+                |registers, ((y, x), new)| update_step(registers, y, x, new),
+            );
+        } else {
+            let mut registers = program.registers();
+            zip.apply(|(y, x), new| update_step(&mut registers, y, x, new));
+        }
+    }
+
+    /// Completes the step whose slices have been computed by `update_slice`:
+    /// measures the result, mutates the trees if needed, rotates the history buffers, and then updates the trees.
+    pub fn finish_step(&mut self) {
+        self.measure_step();
+
+        let broken_rule = CONSTS
+            .mutation_rules
+            .iter()
+            .zip(&self.metric_averages)
+            .find(|(rule, &value)| !rule.allows(value));
+
+        let live_mutation_cause = if self.tree_dirty {
+            Some(MutationCause::TreeDirty)
+        } else if !self.auto_mutate {
+            None
+        } else if let Some((rule, value)) = broken_rule {
+            info!(
+                "{:?} is {}, outside of {}..={}",
+                rule.metric, value, rule.lower_bound, rule.upper_bound
+            );
+            Some(MutationCause::Stats)
+        } else {
+            None
+        };
+
+        // When replaying the log decides instead, so a replay still plays out the same if the rules have changed since it was recorded
+        let mutation_cause =
+            REPLAY.with(|r| r.borrow_mut().mutation(self.current_t, live_mutation_cause));
+
+        if let Some(cause) = mutation_cause {
+            info!(
+                "====TIC: {} MUTATING TREE ({:?})====",
+                self.current_t, cause
+            );
+            self.remember_genome();
+            self.genome
+                .mutate_rng(&mut self.rng, mutagen_state().with_frozen(&self.frozen));
+            self.tree_dirty = false;
+            self.simplify_genome();
+        }
+
+        let broken_reseed_rule = CONSTS
+            .reseed_rules
+            .iter()
+            .zip(&self.reseed_averages)
+            .find(|(rule, &value)| !rule.allows(value));
+
+        let live_reseed_cause = if self.reseed_requested {
+            Some(ReseedCause::Requested)
+        } else if !self.auto_reseed {
+            None
+        } else if let Some((rule, value)) = broken_reseed_rule {
+            info!(
+                "{:?} is {}, outside of {}..={}",
+                rule.metric, value, rule.lower_bound, rule.upper_bound
+            );
+            Some(ReseedCause::Stats)
+        } else {
+            None
+        };
+
+        self.reseed_requested = false;
+
+        let reseed_cause =
+            REPLAY.with(|r| r.borrow_mut().reseed(self.current_t, live_reseed_cause));
+
+        if let Some(cause) = reseed_cause {
+            info!("====TIC: {} RESEEDING ({:?})====", self.current_t, cause);
+            self.reseed();
+        }
+
+        let origin_state = UpdateState {
+            coordinate_set: CoordinateSet {
+                x: SNFloat::new(0.0),
+                y: SNFloat::new(0.0),
+                t: self.current_t as f32,
+            },
+            history: &self.history,
+        };
+
+        self.next_history_step.rotation = self
+            .simplified
+            .root_angle_node
+            .compute(origin_state)
+            .into_inner();
+        self.next_history_step.translation =
+            self.simplified.root_translation_node.compute(origin_state);
+        self.next_history_step.offset = self.simplified.root_offset_node.compute(origin_state);
+        self.next_history_step.from_scale =
+            self.simplified.root_from_scale_node.compute(origin_state);
+        self.next_history_step.to_scale = self.simplified.root_to_scale_node.compute(origin_state);
+
+        self.next_history_step.apply_rotation = self
+            .simplified
+            .apply_angle_node
+            .compute(origin_state)
+            .into_inner();
+        self.next_history_step.apply_translation = self
+            .simplified
+            .apply_translation_node
+            .compute(origin_state)
+            .into_inner();
+        self.next_history_step.apply_offset = self
+            .simplified
+            .apply_offset_node
+            .compute(origin_state)
+            .into_inner();
+        self.next_history_step.apply_scale = self
+            .simplified
+            .apply_scale_node
+            .compute(origin_state)
+            .into_inner();
+
+        // Rotate the buffers by swapping
+        let h_len = self.history.history_steps.len();
+        std::mem::swap(
+            &mut self.history.history_steps[self.current_t % h_len],
+            &mut self.next_history_step,
+        );
+
+        self.current_t += 1;
+
+        self.update_genome();
+    }
+
+    /// Moves on whatever the nodes keep from one step to the next, in the program and the simplified trees the step is computed from
+    /// and then in the genome, so that compiling the genome again carries on from the same state.
+    /// The program is updated in place rather than compiled again, since updating never changes what a tree simplifies to.
+    /// A genome without any stateful nodes is left alone, since updating it would change nothing.
+    fn update_genome(&mut self) {
+        if !self.genome.is_stateful() {
+            return;
+        }
+
+        let mut context = UpdateContext {
+            history: std::mem::take(&mut self.history),
+            t: self.current_t,
+            ..UpdateContext::default()
+        };
+        let state = mutagen_state().with_context(&context);
+        self.program.update(state.deepen());
+        self.simplified.update_transforms(state);
+
+        // The genome takes the grids its copies in the program and simplified trees just worked out
+        context.following = true;
+        self.genome.update(mutagen_state().with_context(&context));
+        self.history = context.history;
+    }
+
+    /// Measures the newly computed step with every metric in the mutation and reseed rules, averaging each with its previous value
+    fn measure_step(&mut self) {
+        let step = &self.next_history_step;
+        let previous = self.last_history_step();
+
+        let metric_averages = measure_rules(
+            &CONSTS.mutation_rules,
+            &self.metric_averages,
+            step,
+            previous,
+        );
+        let reseed_averages =
+            measure_rules(&CONSTS.reseed_rules, &self.reseed_averages, step, previous);
+
+        self.metric_averages = metric_averages;
+        self.reseed_averages = reseed_averages;
+
+        debug!(
+            "Metrics: {}",
+            CONSTS
+                .mutation_rules
+                .iter()
+                .zip(&self.metric_averages)
+                .map(|(rule, value)| format!("{:?} {:.4}", rule.metric, value))
+                .join(", ")
+        );
+    }
+
+    /// Overwrites the newly computed step with a new pattern, which takes the place of whatever the trees computed
+    fn reseed(&mut self) {
+        let reseeder = match self.reseeder.take() {
+            Some(mut reseeder) => {
+                reseeder.mutate_rng(&mut self.rng, mutagen_state());
+                reseeder
+            }
+            None => Reseeder::generate_rng(&mut self.rng, mutagen_state()),
+        };
+
+        debug!("Reseeding with {:?}", reseeder);
+        reseeder.reseed(&mut self.next_history_step.cell_array);
+        self.reseeder = Some(reseeder);
+
+        // The new pattern has nothing to do with the measurements that led up to it
+        self.reseed_averages.clear();
+    }
+
+    /// Computes every slice of the next history step and then completes it
+    pub fn step(&mut self) {
+        for slice_index in 0..CONSTS.tics_per_update {
+            self.update_slice(slice_index);
+        }
+
+        self.finish_step();
+    }
+
+    /// The most recently completed history step
+    pub fn last_history_step(&self) -> &HistoryStep {
+        let h_len = self.history.history_steps.len();
+        &self.history.history_steps[(self.current_t + h_len - 1) % h_len]
+    }
+
+    pub fn last_history_step_mut(&mut self) -> &mut HistoryStep {
+        let h_len = self.history.history_steps.len();
+        &mut self.history.history_steps[(self.current_t + h_len - 1) % h_len]
+    }
+
+    /// Saves the current genome to a new timestamped file in `dir`, returning its path
+    pub fn save_genome<P: AsRef<Path>>(&self, dir: P) -> Fallible<PathBuf> {
+        SavedGenome {
+            genome: &self.genome,
+            current_t: self.current_t,
+            seed: *RNG_SEED.lock().unwrap(),
+        }
+        .save_to_dir(dir)
+    }
+
+    /// Replaces the current genome with a saved one, picking up from the tic it was saved at
+    pub fn load_genome(&mut self, saved: SavedGenome) {
+        self.remember_genome();
+        self.set_genome(saved.genome);
+        self.current_t = saved.current_t;
+    }
+
+    /// Replaces the current genome, carrying on from the current tic
+    pub fn set_genome(&mut self, genome: Genome) {
+        self.genome = genome;
+        self.tree_dirty = false;
+        self.simplify_genome();
+    }
+
+    /// Stops mutation from changing the subtree at `path`, written as [Frozen] reads it
+    pub fn freeze(&mut self, path: &str) {
+        // A path can also lead to a constant, or to where a node could grow later, so this only warns
+        if !self
+            .genome
+            .node_paths()
+            .iter()
+            .any(|(node_path, _)| node_path == path)
+        {
+            warn!("There's no node at {} to freeze yet", path);
+        }
+
+        info!("Freezing {}", path);
+        self.frozen.insert(path);
+    }
+
+    /// Steps back to the genome from before the latest mutation, returning false if there wasn't one
+    pub fn undo_mutation(&mut self) -> bool {
+        match self.undo_genomes.pop_back() {
+            Some(genome) => {
+                let current = std::mem::replace(&mut self.genome, genome);
+                self.redo_genomes.push(current);
+                self.tree_dirty = false;
+                self.simplify_genome();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Steps forward to the genome most recently stepped back from, returning false if there wasn't one
+    pub fn redo_mutation(&mut self) -> bool {
+        match self.redo_genomes.pop() {
+            Some(genome) => {
+                let current = std::mem::replace(&mut self.genome, genome);
+                self.push_undo_genome(current);
+                self.tree_dirty = false;
+                self.simplify_genome();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Keeps the current genome to step back to before it's replaced, which starts a new line of genomes with nothing to redo
+    fn remember_genome(&mut self) {
+        self.push_undo_genome(self.genome.clone());
+        self.redo_genomes.clear();
+    }
+
+    fn push_undo_genome(&mut self, genome: Genome) {
+        self.undo_genomes.push_back(genome);
+
+        while self.undo_genomes.len() > CONSTS.genome_undo_length {
+            self.undo_genomes.pop_front();
+        }
+    }
+
+    /// The averaged value of each metric, in the same order as `CONSTS.mutation_rules`.
+    /// Empty until the first step has finished.
+    pub fn metric_averages(&self) -> &[f64] {
+        &self.metric_averages
+    }
+
+    /// Brings the simplified trees and the program up to date with the genome, which has to happen whenever the genome changes
+    fn simplify_genome(&mut self) {
+        self.simplified = self.genome.clone().simplify();
+        self.program = Program::compile(self.simplified.root_node.as_ref());
+
+        info!(
+            "Simplified trees from {} nodes to {}, compiling the root node into {} instructions",
+            self.genome.node_count(),
+            self.simplified.node_count(),
+            self.program.instruction_count()
+        );
+    }
+}
+
+/// Measures `step` with the metric of each rule, averaging each with its previous value in `averages` if there is one
+fn measure_rules(
+    rules: &[MutationRule],
+    averages: &[f64],
+    step: &HistoryStep,
+    previous: &HistoryStep,
+) -> Vec<f64> {
+    let values = rules
+        .iter()
+        .map(|MutationRule { metric, .. }| metric.metric().measure(step, previous));
+
+    if averages.is_empty() {
+        values.collect()
+    } else {
+        values
+            .zip(averages)
+            .map(|(value, average)| (value + average) / 2.0)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array3;
+
+    use super::*;
+    use crate::{
+        datatype::image::{tests::*, Image, ImageSource},
+        dsl,
+        node::color_nodes::FloatColorNodes,
+        replay::ReplayEvent,
+    };
+
+    //How many steps a still cell array takes to break the activity reseed rule, with room to spare
+    const STILL_STEPS: usize = 40;
+    //How many steps the replay test records, with the trees mutated every few of them
+    const REPLAY_STEPS: usize = 12;
+
+    fn describe(genome: &Genome) -> String {
+        format!("{:?}", genome)
+    }
+
+    #[test]
+    fn test_undo_and_redo_mutations() {
+        let mut simulation = Simulation::new();
+        let original = describe(&simulation.genome);
+
+        assert!(!simulation.undo_mutation());
+
+        // A new simulation starts with its trees dirty, so the first step mutates them
+        simulation.step();
+        let mutated = describe(&simulation.genome);
+
+        assert!(simulation.undo_mutation());
+        assert_eq!(describe(&simulation.genome), original);
+        assert!(!simulation.undo_mutation());
+
+        assert!(simulation.redo_mutation());
+        assert_eq!(describe(&simulation.genome), mutated);
+        assert!(!simulation.redo_mutation());
+    }
+
+    #[test]
+    fn test_reseed_request() {
+        let mut simulation = Simulation::new();
+        simulation.step();
+
+        simulation.reseed_requested = true;
+        simulation.step();
+        assert!(!simulation.reseed_requested);
+
+        let mut expected = simulation.last_history_step().cell_array.clone();
+        simulation.reseeder.as_ref().unwrap().reseed(&mut expected);
+        assert_eq!(simulation.last_history_step().cell_array, expected);
+    }
+
+    #[test]
+    fn test_auto_reseed() {
+        let mut genome = Simulation::new().genome;
+        *genome.root_node = dsl::from_str("Grayscale(child: Constant(value: 0.5))").unwrap();
+
+        // Nothing moves once the cells are all the same color, so the activity reseed rule is broken before long
+        let reseeders = [true, false].iter().map(|&auto_reseed| {
+            let mut simulation = Simulation::from_genome(genome.clone());
+            simulation.auto_mutate = false;
+            simulation.auto_reseed = auto_reseed;
+
+            for _ in 0..STILL_STEPS {
+                simulation.step();
+            }
+
+            simulation.reseeder.is_some()
+        });
+
+        assert_eq!(reseeders.collect::<Vec<_>>(), vec![true, false]);
+    }
+
+    /// Runs a new simulation on the images the preloader hands out, mutating and reseeding it along the way,
+    /// and returns every frame it computes
+    fn run_with_replay(replay: Replay) -> Vec<Array3<u8>> {
+        REPLAY.with(|r| *r.borrow_mut() = replay);
+
+        let mut simulation = Simulation::new();
+        let image = until_preloaded(|| {
+            Image::generate_rng(&mut DeterministicRng::new(), mutagen::State::default())
+        })
+        .pop()
+        .unwrap();
+        *simulation.genome.root_node = FloatColorNodes::FromImage { image };
+        simulation.simplify_genome();
+
+        let frames = (0..REPLAY_STEPS)
+            .map(|step| {
+                simulation.tree_dirty = step % 4 == 3;
+                simulation.reseed_requested = step == 6;
+                simulation.step();
+                simulation.last_history_step().cell_array.clone()
+            })
+            .collect();
+
+        REPLAY.with(|r| *r.borrow_mut() = Replay::Off);
+        frames
+    }
+
+    #[test]
+    fn test_replay_reproduces_frames() {
+        let path =
+            std::env::temp_dir().join(format!("simulation_replay_{}.log", std::process::id()));
+
+        let rng_seed = *RNG_SEED.lock().unwrap();
+
+        set_image_loader(FileImageLoader);
+        let recorded = run_with_replay(Replay::record(&path, rng_seed).unwrap());
+
+        // The preloader only has blank images now, so the frames can only match if the recorded images are handed back
+        set_image_loader(BlankImageLoader);
+        let (playing, seed) = Replay::play(&path).unwrap();
+        assert_eq!(seed, rng_seed);
+
+        let events = match &playing {
+            Replay::Playing(events) => events.clone(),
+            _ => unreachable!(),
+        };
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, ReplayEvent::Mutation { .. })));
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, ReplayEvent::Reseed { .. })));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            ReplayEvent::Image {
+                source: ImageSource::File(_)
+            }
+        )));
+
+        let played = run_with_replay(playing);
+        assert_eq!(played.len(), recorded.len());
+        assert!(
+            played == recorded,
+            "The replayed frames differ from the recorded ones"
+        );
+    }
+
+    #[test]
+    fn test_step_keeps_stateless_program() {
+        let genome: Genome = dsl::from_str(
+            "Genome(
+                root_node: Grayscale(child: Constant(value: 0.5)),
+                root_angle_node: Constant(value: 0.0),
+                root_translation_node: Zero,
+                root_offset_node: Zero,
+                root_from_scale_node: Zero,
+                root_to_scale_node: Zero,
+                apply_angle_node: Constant(child: false),
+                apply_translation_node: Constant(child: false),
+                apply_offset_node: Constant(child: false),
+                apply_scale_node: Constant(child: false),
+            )",
+        )
+        .unwrap();
+        assert!(!genome.is_stateful());
+
+        let mut simulation = Simulation::from_genome(genome);
+        simulation.auto_mutate = false;
+        let instructions = simulation.program.instructions_ptr();
+
+        simulation.step();
+        assert_eq!(simulation.program.instructions_ptr(), instructions);
+    }
+
+    #[test]
+    fn test_step_updates_stateful_program_in_place() {
+        let genome: Genome = dsl::from_str(
+            "Genome(
+                root_node: Grayscale(child: ReactionDiffusionV(child: Uniform(
+                    rates: (feed: 0.0367, kill: 0.0649, diffusion_u: 1.0, diffusion_v: 0.5),
+                    iterations: 2,
+                ))),
+                root_angle_node: FromAngle(child: PhaseAccumulator(speed: Constant(value: 0.5))),
+                root_translation_node: Zero,
+                root_offset_node: Zero,
+                root_from_scale_node: Zero,
+                root_to_scale_node: Zero,
+                apply_angle_node: Constant(child: true),
+                apply_translation_node: Constant(child: false),
+                apply_offset_node: Constant(child: false),
+                apply_scale_node: Constant(child: false),
+            )",
+        )
+        .unwrap();
+        assert!(genome.is_stateful());
+
+        // Compiling the genome again after every step should carry on from the same state as the program updated in place
+        let frames = [false, true].iter().map(|&recompile| {
+            let mut simulation = Simulation::from_genome(genome.clone());
+            simulation.auto_mutate = false;
+            simulation.auto_reseed = false;
+            let instructions = simulation.program.instructions_ptr();
+
+            // Reseeded so the reaction has something to start from
+            let frames: Vec<_> = (0..4)
+                .map(|step| {
+                    simulation.reseed_requested = step == 0;
+                    simulation.step();
+
+                    if recompile {
+                        simulation.simplify_genome();
+                    } else {
+                        assert_eq!(simulation.program.instructions_ptr(), instructions);
+                    }
+
+                    simulation.last_history_step().cell_array.clone()
+                })
+                .collect();
+
+            assert_ne!(frames[1], frames[3]);
+            frames
+        });
+
+        let frames: Vec<_> = frames.collect();
+        assert!(
+            frames[0] == frames[1],
+            "The program updated in place computes different frames than one compiled again"
+        );
+    }
+}
@@ -1,6 +1,9 @@
+use rand::SeedableRng;
+
 use crate::{
     constants::*,
     datatype::{continuous::*, discrete::*},
+    util::DeterministicRng,
     History,
 };
 
@@ -46,4 +49,13 @@ impl CoordinateSet {
     pub fn get_unfloat_t(&self) -> UNFloat {
         UNFloat::new(self.get_byte_t().into_inner() as f32 / CONSTS.byte_possible_values as f32)
     }
+
+    /// An rng seeded from the coordinates, for nodes that want per cell noise that still comes out the same every run
+    pub fn get_rng(&self) -> DeterministicRng {
+        DeterministicRng::seed_from_u64(
+            ((u64::from(self.x.into_inner().to_bits()) << 32)
+                | u64::from(self.y.into_inner().to_bits()))
+                ^ u64::from(self.t.to_bits()).rotate_left(16),
+        )
+    }
 }
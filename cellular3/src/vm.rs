@@ -0,0 +1,643 @@
+//! Compiles node trees into flat programs, so that computing a cell runs through a list of instructions
+//! instead of recursing through boxed nodes.
+//!
+//! Every node writes its output to a register of its output type, which the nodes above it read from.
+//! `ModifyState` nodes compute new coordinates into a register instead of copying the whole `UpdateState`,
+//! and `IfElse` nodes jump over the branch they don't take.
+//! Nodes without any child nodes are kept as they are and computed directly.
+
+use std::{fmt::Debug, marker::PhantomData};
+
+use crate::{
+    datatype::{colors::*, continuous::*, discrete::*, points::*},
+    node::{
+        color_blend_nodes::*, color_nodes::*, continuous_nodes::*, coord_map_nodes::*,
+        discrete_nodes::*, point_nodes::*, Node,
+    },
+    updatestate::{CoordinateSet, UpdateState},
+    History,
+};
+
+/// A node that can be compiled into a [Program]
+pub trait Compile: Node {
+    /// Adds the instructions that compute this node's output into `out`, using the coordinates in `coords`
+    fn compile(&self, compiler: &mut Compiler, coords: Reg<CoordinateSet>, out: Reg<Self::Output>);
+}
+
+/// The index of a register holding a `T`
+#[derive(Debug)]
+pub struct Reg<T> {
+    index: usize,
+    value_type: PhantomData<T>,
+}
+
+// Derived impls would only apply when `T` is `Copy` too, which isn't known for every node's output
+impl<T> Clone for Reg<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Reg<T> {}
+
+/// A type of value that registers can hold
+pub trait Value: Copy + Debug {
+    /// What a register holds before anything has been written to it
+    fn placeholder() -> Self;
+    fn file(registers: &Registers) -> &Vec<Self>;
+    fn file_mut(registers: &mut Registers) -> &mut Vec<Self>;
+}
+
+/// One register file per type of value, which each thread computing cells needs its own copy of
+#[derive(Clone, Default)]
+pub struct Registers {
+    angle: Vec<Angle>,
+    sn_float: Vec<SNFloat>,
+    un_float: Vec<UNFloat>,
+    boolean: Vec<Boolean>,
+    nibble: Vec<Nibble>,
+    byte: Vec<Byte>,
+    float_color: Vec<FloatColor>,
+    bit_color: Vec<BitColor>,
+    byte_color: Vec<ByteColor>,
+    sn_point: Vec<SNPoint>,
+    coordinate_set: Vec<CoordinateSet>,
+}
+
+impl Registers {
+    pub fn get<T: Value>(&self, reg: Reg<T>) -> T {
+        T::file(self)[reg.index]
+    }
+
+    pub fn set<T: Value>(&mut self, reg: Reg<T>, value: T) {
+        T::file_mut(self)[reg.index] = value;
+    }
+
+    fn alloc<T: Value>(&mut self) -> Reg<T> {
+        let file = T::file_mut(self);
+        file.push(T::placeholder());
+
+        Reg {
+            index: file.len() - 1,
+            value_type: PhantomData,
+        }
+    }
+}
+
+impl Value for Angle {
+    fn placeholder() -> Self {
+        Angle::new(0.0)
+    }
+
+    fn file(registers: &Registers) -> &Vec<Self> {
+        &registers.angle
+    }
+
+    fn file_mut(registers: &mut Registers) -> &mut Vec<Self> {
+        &mut registers.angle
+    }
+}
+
+impl Value for SNFloat {
+    fn placeholder() -> Self {
+        SNFloat::new(0.0)
+    }
+
+    fn file(registers: &Registers) -> &Vec<Self> {
+        &registers.sn_float
+    }
+
+    fn file_mut(registers: &mut Registers) -> &mut Vec<Self> {
+        &mut registers.sn_float
+    }
+}
+
+impl Value for UNFloat {
+    fn placeholder() -> Self {
+        UNFloat::new(0.0)
+    }
+
+    fn file(registers: &Registers) -> &Vec<Self> {
+        &registers.un_float
+    }
+
+    fn file_mut(registers: &mut Registers) -> &mut Vec<Self> {
+        &mut registers.un_float
+    }
+}
+
+impl Value for Boolean {
+    fn placeholder() -> Self {
+        Boolean { value: false }
+    }
+
+    fn file(registers: &Registers) -> &Vec<Self> {
+        &registers.boolean
+    }
+
+    fn file_mut(registers: &mut Registers) -> &mut Vec<Self> {
+        &mut registers.boolean
+    }
+}
+
+impl Value for Nibble {
+    fn placeholder() -> Self {
+        Nibble::new(0)
+    }
+
+    fn file(registers: &Registers) -> &Vec<Self> {
+        &registers.nibble
+    }
+
+    fn file_mut(registers: &mut Registers) -> &mut Vec<Self> {
+        &mut registers.nibble
+    }
+}
+
+impl Value for Byte {
+    fn placeholder() -> Self {
+        Byte::new(0)
+    }
+
+    fn file(registers: &Registers) -> &Vec<Self> {
+        &registers.byte
+    }
+
+    fn file_mut(registers: &mut Registers) -> &mut Vec<Self> {
+        &mut registers.byte
+    }
+}
+
+impl Value for FloatColor {
+    fn placeholder() -> Self {
+        FloatColor {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+            a: 0.0,
+        }
+    }
+
+    fn file(registers: &Registers) -> &Vec<Self> {
+        &registers.float_color
+    }
+
+    fn file_mut(registers: &mut Registers) -> &mut Vec<Self> {
+        &mut registers.float_color
+    }
+}
+
+impl Value for BitColor {
+    fn placeholder() -> Self {
+        BitColor::Black
+    }
+
+    fn file(registers: &Registers) -> &Vec<Self> {
+        &registers.bit_color
+    }
+
+    fn file_mut(registers: &mut Registers) -> &mut Vec<Self> {
+        &mut registers.bit_color
+    }
+}
+
+impl Value for ByteColor {
+    fn placeholder() -> Self {
+        ByteColor {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 0,
+        }
+    }
+
+    fn file(registers: &Registers) -> &Vec<Self> {
+        &registers.byte_color
+    }
+
+    fn file_mut(registers: &mut Registers) -> &mut Vec<Self> {
+        &mut registers.byte_color
+    }
+}
+
+impl Value for SNPoint {
+    fn placeholder() -> Self {
+        SNPoint::zero()
+    }
+
+    fn file(registers: &Registers) -> &Vec<Self> {
+        &registers.sn_point
+    }
+
+    fn file_mut(registers: &mut Registers) -> &mut Vec<Self> {
+        &mut registers.sn_point
+    }
+}
+
+impl Value for CoordinateSet {
+    fn placeholder() -> Self {
+        CoordinateSet {
+            x: SNFloat::new(0.0),
+            y: SNFloat::new(0.0),
+            t: 0.0,
+        }
+    }
+
+    fn file(registers: &Registers) -> &Vec<Self> {
+        &registers.coordinate_set
+    }
+
+    fn file_mut(registers: &mut Registers) -> &mut Vec<Self> {
+        &mut registers.coordinate_set
+    }
+}
+
+/// The operation a single node performs once its children have been computed, reading them from registers
+pub trait Op: Sized {
+    type Output: Value;
+
+    fn execute(&self, registers: &Registers, state: UpdateState) -> Self::Output;
+
+    /// Updates the node a leaf op computes directly, as updating the tree it was compiled from would
+    fn update(&mut self, state: mutagen::State);
+
+    fn instruction(step: Step<Self>) -> Instruction;
+}
+
+/// An operation along with the registers it reads its coordinates from and writes its output to
+#[derive(Debug)]
+pub struct Step<O: Op> {
+    op: O,
+    coords: Reg<CoordinateSet>,
+    out: Reg<O::Output>,
+}
+
+impl<O: Op> Step<O> {
+    fn update(&mut self, state: mutagen::State) {
+        self.op.update(state);
+    }
+
+    fn run(&self, registers: &mut Registers, history: &History) {
+        let state = UpdateState {
+            coordinate_set: registers.get(self.coords),
+            history,
+        };
+        let value = self.op.execute(registers, state);
+
+        registers.set(self.out, value);
+    }
+}
+
+#[derive(Debug)]
+pub enum Instruction {
+    Angle(Step<AngleOp>),
+    SNFloat(Step<SNFloatOp>),
+    UNFloat(Step<UNFloatOp>),
+    CoordMap(Step<CoordMapOp>),
+    Boolean(Step<BooleanOp>),
+    Nibble(Step<NibbleOp>),
+    Byte(Step<ByteOp>),
+    FloatColor(Step<FloatColorOp>),
+    ColorBlend(Step<ColorBlendOp>),
+    BitColor(Step<BitColorOp>),
+    ByteColor(Step<ByteColorOp>),
+    SNPoint(Step<SNPointOp>),
+    /// Continues from `target` if the predicate is false
+    JumpUnless {
+        predicate: Reg<Boolean>,
+        target: usize,
+    },
+    Jump {
+        target: usize,
+    },
+}
+
+/// Builds up the instructions and registers of a [Program]
+#[derive(Default)]
+pub struct Compiler {
+    instructions: Vec<Instruction>,
+    registers: Registers,
+}
+
+impl Compiler {
+    pub fn emit<O: Op>(&mut self, op: O, coords: Reg<CoordinateSet>, out: Reg<O::Output>) {
+        self.instructions
+            .push(O::instruction(Step { op, coords, out }));
+    }
+
+    /// Compiles a child node into a new register, returning the register
+    pub fn input<N: Compile>(&mut self, node: &N, coords: Reg<CoordinateSet>) -> Reg<N::Output>
+    where
+        N::Output: Value,
+    {
+        let reg = self.registers.alloc();
+        node.compile(self, coords, reg);
+        reg
+    }
+
+    /// Compiles `child` to read its coordinates from the output of `child_state`
+    pub fn modify_state<N: Compile>(
+        &mut self,
+        child: &N,
+        child_state: &CoordMapNodes,
+        coords: Reg<CoordinateSet>,
+        out: Reg<N::Output>,
+    ) {
+        let child_coords = self.input(child_state, coords);
+        child.compile(self, child_coords, out);
+    }
+
+    /// Compiles both branches to write to the same register, jumping over whichever one isn't taken
+    pub fn if_else<N: Compile>(
+        &mut self,
+        predicate: &BooleanNodes,
+        child_a: &N,
+        child_b: &N,
+        coords: Reg<CoordinateSet>,
+        out: Reg<N::Output>,
+    ) {
+        let predicate = self.input(predicate, coords);
+
+        let jump_to_b = self.instructions.len();
+        self.instructions.push(Instruction::JumpUnless {
+            predicate,
+            target: 0,
+        });
+        child_a.compile(self, coords, out);
+
+        let jump_to_end = self.instructions.len();
+        self.instructions.push(Instruction::Jump { target: 0 });
+        self.set_target(jump_to_b);
+        child_b.compile(self, coords, out);
+
+        self.set_target(jump_to_end);
+    }
+
+    /// Compiles `child_b` to only run when `child_a` is true, the same way `&&` skips its right hand side
+    pub fn and(
+        &mut self,
+        child_a: &BooleanNodes,
+        child_b: &BooleanNodes,
+        coords: Reg<CoordinateSet>,
+        out: Reg<Boolean>,
+    ) {
+        child_a.compile(self, coords, out);
+
+        let jump_to_end = self.instructions.len();
+        self.instructions.push(Instruction::JumpUnless {
+            predicate: out,
+            target: 0,
+        });
+        child_b.compile(self, coords, out);
+
+        self.set_target(jump_to_end);
+    }
+
+    /// Compiles `child_b` to only run when `child_a` is false, the same way `||` skips its right hand side
+    pub fn or(
+        &mut self,
+        child_a: &BooleanNodes,
+        child_b: &BooleanNodes,
+        coords: Reg<CoordinateSet>,
+        out: Reg<Boolean>,
+    ) {
+        child_a.compile(self, coords, out);
+
+        let jump_to_b = self.instructions.len();
+        self.instructions.push(Instruction::JumpUnless {
+            predicate: out,
+            target: 0,
+        });
+
+        let jump_to_end = self.instructions.len();
+        self.instructions.push(Instruction::Jump { target: 0 });
+        self.set_target(jump_to_b);
+        child_b.compile(self, coords, out);
+
+        self.set_target(jump_to_end);
+    }
+
+    /// Points the jump at `index` to the next instruction to be emitted
+    fn set_target(&mut self, index: usize) {
+        let next = self.instructions.len();
+
+        match &mut self.instructions[index] {
+            Instruction::JumpUnless { target, .. } | Instruction::Jump { target } => *target = next,
+            _ => unreachable!("Instruction {} isn't a jump", index),
+        }
+    }
+}
+
+/// A node tree compiled into a flat list of instructions
+pub struct Program<T> {
+    instructions: Vec<Instruction>,
+    registers: Registers,
+    coords: Reg<CoordinateSet>,
+    output: Reg<T>,
+}
+
+impl<T: Value> Program<T> {
+    pub fn compile<N: Compile<Output = T>>(root: &N) -> Self {
+        let mut compiler = Compiler::default();
+        let coords = compiler.registers.alloc();
+        let output = compiler.input(root, coords);
+
+        Self {
+            instructions: compiler.instructions,
+            registers: compiler.registers,
+            coords,
+            output,
+        }
+    }
+
+    pub fn instruction_count(&self) -> usize {
+        self.instructions.len()
+    }
+
+    /// Where the instructions live, which only changes when the program is compiled again
+    #[cfg(test)]
+    pub fn instructions_ptr(&self) -> *const Instruction {
+        self.instructions.as_ptr()
+    }
+
+    /// Moves on the state of every stateful node in the program, without compiling it again.
+    /// Any node that keeps state is compiled into a leaf whole, so this matches updating the tree it was compiled from.
+    pub fn update(&mut self, state: mutagen::State) {
+        for instruction in &mut self.instructions {
+            match instruction {
+                Instruction::Angle(step) => step.update(state),
+                Instruction::SNFloat(step) => step.update(state),
+                Instruction::UNFloat(step) => step.update(state),
+                Instruction::CoordMap(step) => step.update(state),
+                Instruction::Boolean(step) => step.update(state),
+                Instruction::Nibble(step) => step.update(state),
+                Instruction::Byte(step) => step.update(state),
+                Instruction::FloatColor(step) => step.update(state),
+                Instruction::ColorBlend(step) => step.update(state),
+                Instruction::BitColor(step) => step.update(state),
+                Instruction::ByteColor(step) => step.update(state),
+                Instruction::SNPoint(step) => step.update(state),
+                Instruction::JumpUnless { .. } | Instruction::Jump { .. } => {}
+            }
+        }
+    }
+
+    /// A fresh set of registers to run the program with
+    pub fn registers(&self) -> Registers {
+        self.registers.clone()
+    }
+
+    /// Computes the same output as the compiled tree would for `state`,
+    /// using `registers` as scratch space so that they can be reused between cells
+    pub fn run(&self, registers: &mut Registers, state: UpdateState) -> T {
+        registers.set(self.coords, state.coordinate_set);
+
+        let mut next = 0;
+
+        while let Some(instruction) = self.instructions.get(next) {
+            next += 1;
+
+            match instruction {
+                Instruction::Angle(step) => step.run(registers, state.history),
+                Instruction::SNFloat(step) => step.run(registers, state.history),
+                Instruction::UNFloat(step) => step.run(registers, state.history),
+                Instruction::CoordMap(step) => step.run(registers, state.history),
+                Instruction::Boolean(step) => step.run(registers, state.history),
+                Instruction::Nibble(step) => step.run(registers, state.history),
+                Instruction::Byte(step) => step.run(registers, state.history),
+                Instruction::FloatColor(step) => step.run(registers, state.history),
+                Instruction::ColorBlend(step) => step.run(registers, state.history),
+                Instruction::BitColor(step) => step.run(registers, state.history),
+                Instruction::ByteColor(step) => step.run(registers, state.history),
+                Instruction::SNPoint(step) => step.run(registers, state.history),
+                Instruction::JumpUnless { predicate, target } => {
+                    if !registers.get(*predicate).into_inner() {
+                        next = *target;
+                    }
+                }
+                Instruction::Jump { target } => next = *target,
+            }
+        }
+
+        registers.get(self.output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mutagen::Generatable;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{constants::*, dsl, util::DeterministicRng};
+
+    const SEED: u64 = 0xc0de;
+    const TREES: usize = 30;
+    /// Samples per axis, so the grid includes both edges of the coordinate range
+    const SAMPLES: usize = 9;
+    const SAMPLE_TICS: [f32; 3] = [0.0, 7.0, 300.0];
+
+    /// Checks that a program computes exactly what its tree does, reusing the registers between samples like the simulation does
+    fn assert_same_output<T>(tree: &T, history: &History)
+    where
+        T: Compile + Debug,
+        T::Output: Value,
+    {
+        let program = Program::compile(tree);
+        let mut registers = program.registers();
+
+        for &t in SAMPLE_TICS.iter() {
+            for x in 0..SAMPLES {
+                for y in 0..SAMPLES {
+                    let state = UpdateState {
+                        coordinate_set: CoordinateSet {
+                            x: SNFloat::new(x as f32 / (SAMPLES - 1) as f32 * 2.0 - 1.0),
+                            y: SNFloat::new(y as f32 / (SAMPLES - 1) as f32 * 2.0 - 1.0),
+                            t,
+                        },
+                        history,
+                    };
+
+                    // Not every output type can be compared, but they can all be printed
+                    let expected = format!("{:?}", tree.compute(state));
+                    let actual = format!("{:?}", program.run(&mut registers, state));
+
+                    assert_eq!(
+                        expected, actual,
+                        "compiling {:?} changed its output at {:?}",
+                        tree, state.coordinate_set
+                    );
+                }
+            }
+        }
+    }
+
+    fn assert_equivalent<T>(history: &History)
+    where
+        T: Generatable + Compile + Debug,
+        T::Output: Value,
+    {
+        let mut rng = DeterministicRng::seed_from_u64(SEED);
+
+        for _ in 0..TREES {
+            assert_same_output(
+                &T::generate_rng(&mut rng, mutagen::State::default()),
+                history,
+            );
+        }
+    }
+
+    fn history() -> History {
+        History::new(
+            CONSTS.cell_array_width,
+            CONSTS.cell_array_height,
+            CONSTS.cell_array_history_length,
+        )
+    }
+
+    #[test]
+    fn test_compiled_output() {
+        let history = history();
+
+        assert_equivalent::<FloatColorNodes>(&history);
+        assert_equivalent::<ColorBlendNodes>(&history);
+        assert_equivalent::<BitColorNodes>(&history);
+        assert_equivalent::<ByteColorNodes>(&history);
+        assert_equivalent::<AngleNodes>(&history);
+        assert_equivalent::<SNFloatNodes>(&history);
+        assert_equivalent::<UNFloatNodes>(&history);
+        assert_equivalent::<CoordMapNodes>(&history);
+        assert_equivalent::<BooleanNodes>(&history);
+        assert_equivalent::<NibbleNodes>(&history);
+        assert_equivalent::<ByteNodes>(&history);
+        assert_equivalent::<SNPointNodes>(&history);
+    }
+
+    #[test]
+    fn test_compiled_branches() {
+        // Every way control flow can go, including the right hand sides that `And` and `Or` skip
+        let tree: FloatColorNodes = dsl::from_str(
+            "IfElse(
+                predicate: Or(
+                    child_a: SNFloatLess(child_a: XRatio, child_b: YRatio),
+                    child_b: And(
+                        child_a: SNFloatSign(child: XRatio),
+                        child_b: Not(child: SNFloatSign(child: YRatio)),
+                    ),
+                ),
+                child_a: ModifyState(
+                    child: Grayscale(child: FromSNFloat(child: XRatio)),
+                    child_state: Rotation(angle: FromCoordinate),
+                ),
+                child_b: IfElse(
+                    predicate: SNFloatMore(child_a: YRatio, child_b: Constant(value: 0.5)),
+                    child_a: Gray,
+                    child_b: Grayscale(child: FromSNFloat(child: YRatio)),
+                ),
+            )",
+        )
+        .unwrap();
+
+        assert_same_output(&tree, &history());
+    }
+}
@@ -7,11 +7,12 @@ use quote::{format_ident, quote, ToTokens};
 use syn::{
     parenthesized,
     parse::{Parse, ParseStream},
-    parse2, parse_macro_input,
+    parse2, parse_macro_input, parse_quote,
     punctuated::Punctuated,
     spanned::Spanned,
     token::Paren,
-    Attribute, Data, DataEnum, DataStruct, Error, Field, Fields, Ident, LitFloat, Result, Token,
+    Attribute, Data, DataEnum, DataStruct, Error, Expr, Field, Fields, Generics, Ident, LitFloat,
+    Result, Token,
 };
 
 mod a {
@@ -22,11 +23,15 @@ mod a {
     pub const GEN_WEIGHT: &str = "gen_weight";
     pub const MUT_REROLL: &str = "mut_reroll";
     pub const MUT_WEIGHT: &str = "mut_weight";
+    pub const CROSS_SWAP: &str = "cross_swap";
+    pub const CROSS_RECURSE: &str = "cross_recurse";
+    pub const RANGE: &str = "range";
+    pub const UPDATE: &str = "update";
 
     // Allowed keys for each item
-    pub const ENUM: &[&str] = &[MUT_REROLL];
-    pub const ENUM_VARIANT: &[&str] = &[GEN_WEIGHT, MUT_REROLL];
-    pub const FIELD: &[&str] = &[MUT_WEIGHT];
+    pub const ENUM: &[&str] = &[MUT_REROLL, CROSS_SWAP];
+    pub const ENUM_VARIANT: &[&str] = &[GEN_WEIGHT, MUT_REROLL, CROSS_SWAP, UPDATE];
+    pub const FIELD: &[&str] = &[MUT_WEIGHT, CROSS_RECURSE, RANGE];
 }
 
 #[proc_macro_derive(Generatable, attributes(mutagen))]
@@ -41,13 +46,30 @@ pub fn derive_generatable(input: proc_macro::TokenStream) -> proc_macro::TokenSt
     }
     .unwrap_or_else(|e| e.to_compile_error());
 
+    // Every variant is listed, even those that are never generated, so a profile naming them is still valid
+    let variant_names = match &input.data {
+        Data::Enum(e) => {
+            let names = e.variants.iter().map(|variant| variant.ident.to_string());
+            quote! {
+                fn variant_names() -> &'static [&'static str] {
+                    &[#(#names),*]
+                }
+            }
+        }
+        _ => TokenStream2::new(),
+    };
+
     let ident = input.ident;
+    let generics = bounded_generics(&input.generics, &[quote!(::mutagen::Generatable)]);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let output: TokenStream2 = quote! {
-        impl ::mutagen::Generatable for #ident {
+        impl #impl_generics ::mutagen::Generatable for #ident #ty_generics #where_clause {
             fn generate_rng<R: ::mutagen::rand::Rng + ?Sized>(rng: &mut R, state: ::mutagen::State) -> Self {
                 #body
             }
+
+            #variant_names
         }
     };
 
@@ -60,7 +82,7 @@ fn generatable_struct(
     _attrs: &[Attribute],
     _span: Span,
 ) -> Result<TokenStream2> {
-    let fields = generatable_fields(&s.fields);
+    let fields = generatable_fields(&s.fields, &ident.to_string())?;
 
     Ok(quote! {
         #ident #fields
@@ -83,51 +105,79 @@ fn generatable_enum(
         ));
     }
 
+    let enum_name = enum_ident.to_string();
+
     roll(
         &e.variants.iter().collect::<Vec<_>>(),
         |variant| {
-            Ok(parse_attrs(&variant.attrs, a::ENUM_VARIANT)?
+            let weight = parse_attrs(&variant.attrs, a::ENUM_VARIANT)?
                 .get(a::GEN_WEIGHT)
                 .cloned()
-                .unwrap_or(Value::None))
+                .unwrap_or(Value::None)
+                .to_weight()?;
+            let variant_name = variant.ident.to_string();
+
+            Ok(weight.map(|weight| {
+                quote! {
+                    state.gen_weight(#enum_name, #variant_name, #weight)
+                }
+            }))
         },
         |variant, _| {
             let ident = &variant.ident;
-            let fields = generatable_fields(&variant.fields);
-            quote! {
+            let fields =
+                generatable_fields(&variant.fields, &format!("{}::{}", enum_ident, ident))?;
+            Ok(quote! {
+                    state.count_node();
                     return #enum_ident::#ident #fields;
-            }
+            })
         },
         &format!("Generation for {}", enum_ident),
     )
 }
 
-fn generatable_fields(fields: &Fields) -> TokenStream2 {
-    match fields {
+/// Generates each field one level deeper, as a child of `parent`
+fn generatable_fields(fields: &Fields, parent: &str) -> Result<TokenStream2> {
+    let items = flatten_fields(fields)
+        .into_iter()
+        .map(|field| {
+            Ok(match field_range(field)? {
+                Some(range) => quote! {
+                    ::mutagen::GeneratableIn::generate_in(rng, state.deepen().with_parent(#parent), &(#range))
+                },
+                None => quote! {
+                    ::mutagen::Generatable::generate_rng(rng, state.deepen().with_parent(#parent))
+                },
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(match fields {
         Fields::Named(f) => {
             let name = f.named.iter().map(|f| &f.ident);
 
             quote! {
                 {
-                    #(#name: ::mutagen::Generatable::generate_rng(rng, state.deepen())),*
+                    #(#name: #items),*
                 }
             }
         }
-        Fields::Unnamed(f) => {
-            let item: Vec<TokenStream2> = f
-                .unnamed
-                .iter()
-                .map(|_| quote! { ::mutagen::Generatable::generate_rng(rng, state.deepen()) })
-                .collect();
-
-            quote! {
-                (
-                    #(#item),*
-                )
-            }
-        }
+        Fields::Unnamed(_) => quote! {
+            (
+                #(#items),*
+            )
+        },
         Fields::Unit => TokenStream2::new(),
-    }
+    })
+}
+
+/// The range given by a field's attribute, if it has one
+fn field_range(field: &Field) -> Result<Option<TokenStream2>> {
+    parse_attrs(&field.attrs, a::FIELD)?
+        .get(a::RANGE)
+        .cloned()
+        .unwrap_or(Value::None)
+        .to_range()
 }
 
 #[proc_macro_derive(Mutatable, attributes(mutagen))]
@@ -143,10 +193,21 @@ pub fn derive_mutatable(input: proc_macro::TokenStream) -> proc_macro::TokenStre
     .unwrap_or_else(|e| e.to_compile_error());
 
     let ident = input.ident;
+    // Enums can reroll into a newly generated variant, so their fields have to be generatable as well
+    let bounds = match &input.data {
+        Data::Enum(_) => vec![quote!(::mutagen::Mutatable), quote!(::mutagen::Generatable)],
+        _ => vec![quote!(::mutagen::Mutatable)],
+    };
+    let generics = bounded_generics(&input.generics, &bounds);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let output: TokenStream2 = quote! {
-        impl ::mutagen::Mutatable for #ident {
+        impl #impl_generics ::mutagen::Mutatable for #ident #ty_generics #where_clause {
             fn mutate_rng<R: ::mutagen::rand::Rng + ?Sized>(&mut self, rng: &mut R, state: ::mutagen::State) {
+                if state.is_frozen() {
+                    return;
+                }
+
                 #body
             }
         }
@@ -191,6 +252,8 @@ fn mutatable_enum(
         .unwrap_or(Value::None)
         .to_prob()?;
 
+    let enum_name = enum_ident.to_string();
+
     let variants: Vec<_> = e
         .variants
         .iter()
@@ -211,9 +274,14 @@ fn mutatable_enum(
             )?;
 
             let out: TokenStream2 = if let Some(mut_reroll) = mut_reroll {
+                let variant_name = ident.to_string();
+
                 quote! {
                     #enum_ident::#ident #bindings => {
-                        if rng.sample(::rand::distributions::Bernoulli::new(#mut_reroll).unwrap()) {
+                        let mut_reroll = state.mut_reroll(#enum_name, #variant_name, #mut_reroll);
+
+                        // Rerolling would replace anything frozen further down along with the rest
+                        if rng.sample(::rand::distributions::Bernoulli::new(mut_reroll).unwrap()) && !state.contains_frozen() {
                             *self = ::mutagen::Generatable::generate_rng(rng, state);
                         } else {
                             #fields_body
@@ -259,28 +327,68 @@ fn fields_bindings(fields: &Fields) -> TokenStream2 {
     }
 }
 
+/// Mutates one of the fields of `path`, the name of the struct or enum variant they belong to
 fn mutatable_fields(fields: &[&Field], path: &str, _span: Span) -> Result<TokenStream2> {
     if fields.is_empty() {
         return Ok(TokenStream2::new());
     }
 
-    roll(
+    // Fields that are never mutated are still bound by the caller's pattern, so they're marked as used here
+    let unmutated: TokenStream2 = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let ident = field_ident(field, i);
+
+            Ok(match mut_weight(field)? {
+                Some(_) => TokenStream2::new(),
+                None => quote! { let _ = #ident; },
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    let body = roll(
         fields,
-        |field| {
-            Ok(parse_attrs(&field.attrs, a::FIELD)?
-                .get(a::MUT_WEIGHT)
-                .cloned()
-                .unwrap_or(Value::None))
-        },
+        |field| mut_weight(field),
         |field, i| {
             let ident = field_ident(field, i);
-            quote! {
-                ::mutagen::Mutatable::mutate_rng(#ident, rng, state.deepen());
+            let name = field_name(field, i);
+            let mutate = match field_range(field)? {
+                Some(range) => quote! {
+                    ::mutagen::MutatableIn::mutate_in(#ident, rng, state.deepen().with_parent(#path), &(#range));
+                },
+                None => quote! {
+                    ::mutagen::Mutatable::mutate_rng(#ident, rng, state.deepen().with_parent(#path));
+                },
+            };
+
+            Ok(quote! {
+                let state = state.enter(#name);
+
+                // Checked here as well as in derived impls, since hand written impls don't know about frozen subtrees
+                if !state.is_frozen() {
+                    #mutate
+                }
+
                 return;
-            }
+            })
         },
         &format!("mutation for {}", path),
-    )
+    )?;
+
+    Ok(quote! {
+        #unmutated
+        #body
+    })
+}
+
+/// The weight of mutating a field, or `None` if it's never mutated
+fn mut_weight(field: &Field) -> Result<Option<TokenStream2>> {
+    parse_attrs(&field.attrs, a::FIELD)?
+        .get(a::MUT_WEIGHT)
+        .cloned()
+        .unwrap_or(Value::None)
+        .to_weight()
 }
 
 #[proc_macro_derive(Updatable, attributes(mutagen))]
@@ -288,20 +396,26 @@ pub fn derive_updatable(input: proc_macro::TokenStream) -> proc_macro::TokenStre
     let input = parse_macro_input!(input as syn::DeriveInput);
     let span = input.span();
 
-    let body = match &input.data {
+    let (body, stateful) = match &input.data {
         Data::Struct(s) => updatable_struct(&input.ident, s, &input.attrs, span),
         Data::Enum(e) => updatable_enum(&input.ident, e, &input.attrs, span),
         Data::Union(_) => panic!("#[derive(Updatable)] is not implemented for unions"),
     }
-    .unwrap_or_else(|e| e.to_compile_error());
+    .unwrap_or_else(|e| (e.to_compile_error(), TokenStream2::new()));
 
     let ident = input.ident;
+    let generics = bounded_generics(&input.generics, &[quote!(::mutagen::Updatable)]);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let output: TokenStream2 = quote! {
-        impl ::mutagen::Updatable for #ident {
+        impl #impl_generics ::mutagen::Updatable for #ident #ty_generics #where_clause {
             fn update(&mut self, state: ::mutagen::State) {
                 #body
             }
+
+            fn is_stateful(&self) -> bool {
+                #stateful
+            }
         }
     };
 
@@ -313,18 +427,22 @@ fn updatable_struct(
     s: &DataStruct,
     _attrs: &[Attribute],
     _span: Span,
-) -> Result<TokenStream2> {
+) -> Result<(TokenStream2, TokenStream2)> {
     let bindings = fields_bindings(&s.fields);
-    let body = updatable_fields(
-        &flatten_fields(&s.fields),
-        &ident.to_string(),
-        s.fields.span(),
-    )?;
-
-    Ok(quote! {
-        let #ident #bindings = self;
-        #body
-    })
+    let fields = flatten_fields(&s.fields);
+    let body = updatable_fields(&fields, &ident.to_string(), s.fields.span())?;
+    let stateful = stateful_fields(&fields);
+
+    Ok((
+        quote! {
+            let #ident #bindings = self;
+            #body
+        },
+        quote! {
+            let #ident #bindings = self;
+            #stateful
+        },
+    ))
 }
 
 fn updatable_enum(
@@ -332,36 +450,77 @@ fn updatable_enum(
     e: &DataEnum,
     _attrs: &[Attribute],
     _span: Span,
-) -> Result<TokenStream2> {
+) -> Result<(TokenStream2, TokenStream2)> {
     if e.variants.is_empty() {
         panic!("Cannot derive Mutatable for enum with no variants");
     }
 
-    let variants: Vec<_> = e
+    let (variants, stateful_variants): (Vec<_>, Vec<_>) = e
         .variants
         .iter()
         .map(|variant| {
             let ident = &variant.ident;
             let bindings = fields_bindings(&variant.fields);
+            let fields = flatten_fields(&variant.fields);
             let fields_body = updatable_fields(
-                &flatten_fields(&variant.fields),
+                &fields,
                 &format!("{}::{}", &enum_ident, &variant.ident),
                 variant.fields.span(),
             )?;
 
+            // The fields' borrows of self have ended by the time the hook is called
+            let hook = parse_attrs(&variant.attrs, a::ENUM_VARIANT)?
+                .get(a::UPDATE)
+                .map(Value::to_fn)
+                .transpose()?
+                .map(|hook| quote! { #hook(self, state); });
+
+            // A variant with a hook is stateful whatever its fields are, so they're left unbound
+            let stateful = if hook.is_some() {
+                let bindings = ignored_fields_bindings(&variant.fields);
+                quote! { #enum_ident::#ident #bindings => true, }
+            } else {
+                let fields_stateful = stateful_fields(&fields);
+                quote! { #enum_ident::#ident #bindings => { #fields_stateful } }
+            };
+
             let out: TokenStream2 = quote! {
-                #enum_ident::#ident #bindings => { #fields_body }
+                #enum_ident::#ident #bindings => {
+                    #fields_body
+                    #hook
+                }
             };
 
-            Ok(out)
+            Ok((out, stateful))
         })
-        .collect::<Result<_>>()?;
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
 
-    Ok(quote! {
-        match self {
-            #( #variants )*
-        }
-    })
+    Ok((
+        quote! {
+            match self {
+                #( #variants )*
+            }
+        },
+        quote! {
+            match self {
+                #( #stateful_variants )*
+            }
+        },
+    ))
+}
+
+/// Whether any of the fields, already bound by the caller's pattern, is stateful
+fn stateful_fields(fields: &[&Field]) -> TokenStream2 {
+    let checks = fields.iter().enumerate().map(|(i, field)| {
+        let ident = field_ident(field, i);
+        quote! { ::mutagen::Updatable::is_stateful(#ident) }
+    });
+
+    quote! {
+        false #( || #checks )*
+    }
 }
 
 fn updatable_fields(fields: &[&Field], _path: &str, _span: Span) -> Result<TokenStream2> {
@@ -381,25 +540,382 @@ fn updatable_fields(fields: &[&Field], _path: &str, _span: Span) -> Result<Token
         .collect())
 }
 
+#[proc_macro_derive(Traversable, attributes(mutagen))]
+pub fn derive_traversable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    let span = input.span();
+
+    let (name, children) = match &input.data {
+        Data::Struct(s) => traversable_struct(&input.ident, s, &input.attrs, span),
+        Data::Enum(e) => traversable_enum(&input.ident, e, &input.attrs, span),
+        Data::Union(_) => panic!("#[derive(Traversable)] is not implemented for unions"),
+    }
+    .unwrap_or_else(|e| (e.to_compile_error(), TokenStream2::new()));
+
+    let ident = input.ident;
+    let generics = bounded_generics(&input.generics, &[quote!(::mutagen::Traversable)]);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let output: TokenStream2 = quote! {
+        impl #impl_generics ::mutagen::Traversable for #ident #ty_generics #where_clause {
+            fn node_name(&self) -> ::std::option::Option<&'static str> {
+                #name
+            }
+
+            fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn ::mutagen::Traversable)) {
+                #children
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(output)
+}
+
+fn traversable_struct(
+    ident: &Ident,
+    s: &DataStruct,
+    _attrs: &[Attribute],
+    _span: Span,
+) -> Result<(TokenStream2, TokenStream2)> {
+    let name = ident.to_string();
+    let bindings = fields_bindings(&s.fields);
+    let body = traversable_fields(&flatten_fields(&s.fields));
+
+    Ok((
+        quote! {
+            ::std::option::Option::Some(#name)
+        },
+        quote! {
+            let #ident #bindings = self;
+            #body
+        },
+    ))
+}
+
+fn traversable_enum(
+    enum_ident: &Ident,
+    e: &DataEnum,
+    _attrs: &[Attribute],
+    _span: Span,
+) -> Result<(TokenStream2, TokenStream2)> {
+    if e.variants.is_empty() {
+        panic!("Cannot derive Traversable for enum with no variants");
+    }
+
+    let names = e.variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let bindings = ignored_fields_bindings(&variant.fields);
+        let name = format!("{}::{}", enum_ident, ident);
+
+        quote! {
+            #enum_ident::#ident #bindings => ::std::option::Option::Some(#name),
+        }
+    });
+
+    let children = e.variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let bindings = fields_bindings(&variant.fields);
+        let body = traversable_fields(&flatten_fields(&variant.fields));
+
+        quote! {
+            #enum_ident::#ident #bindings => { #body }
+        }
+    });
+
+    Ok((
+        quote! {
+            match self {
+                #( #names )*
+            }
+        },
+        quote! {
+            match self {
+                #( #children )*
+            }
+        },
+    ))
+}
+
+fn traversable_fields(fields: &[&Field]) -> TokenStream2 {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let ident = field_ident(field, i);
+            let name = field_name(field, i);
+
+            quote! {
+                f(#name, #ident);
+            }
+        })
+        .collect()
+}
+
+/// Matches any fields without binding them
+fn ignored_fields_bindings(fields: &Fields) -> TokenStream2 {
+    match fields {
+        Fields::Named(_) => quote! { { .. } },
+        Fields::Unnamed(_) => quote! { (..) },
+        Fields::Unit => TokenStream2::new(),
+    }
+}
+
+#[proc_macro_derive(Crossover, attributes(mutagen))]
+pub fn derive_crossover(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    let span = input.span();
+
+    let body = match &input.data {
+        Data::Struct(s) => crossover_struct(&input.ident, s, &input.attrs, span),
+        Data::Enum(e) => crossover_enum(&input.ident, e, &input.attrs, span),
+        Data::Union(_) => panic!("#[derive(Crossover)] is not implemented for unions"),
+    }
+    .unwrap_or_else(|e| e.to_compile_error());
+
+    let ident = input.ident;
+    let generics = bounded_generics(&input.generics, &[quote!(::mutagen::Crossover)]);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let output: TokenStream2 = quote! {
+        impl #impl_generics ::mutagen::Crossover for #ident #ty_generics #where_clause {
+            fn crossover_rng<R: ::mutagen::rand::Rng + ?Sized>(self, other: Self, rng: &mut R, state: ::mutagen::State) -> Self {
+                #body
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(output)
+}
+
+fn crossover_struct(
+    ident: &Ident,
+    s: &DataStruct,
+    _attrs: &[Attribute],
+    _span: Span,
+) -> Result<TokenStream2> {
+    let self_bindings = prefixed_fields_bindings(&s.fields, "self");
+    let other_bindings = prefixed_fields_bindings(&s.fields, "other");
+    let fields = crossover_fields(&s.fields)?;
+
+    Ok(quote! {
+        let #ident #self_bindings = self;
+        let #ident #other_bindings = other;
+        #ident #fields
+    })
+}
+
+fn crossover_enum(
+    enum_ident: &Ident,
+    e: &DataEnum,
+    attrs: &[Attribute],
+    _span: Span,
+) -> Result<TokenStream2> {
+    if e.variants.is_empty() {
+        panic!("Cannot derive Crossover for enum with no variants");
+    }
+
+    let attrs = parse_attrs(attrs, a::ENUM)?;
+    let cross_swap_enum = attrs
+        .get(a::CROSS_SWAP)
+        .cloned()
+        .unwrap_or(Value::None)
+        .to_prob()?;
+
+    let variants: Vec<_> = e
+        .variants
+        .iter()
+        .map(|variant| {
+            let variant_attrs = parse_attrs(&variant.attrs, a::ENUM_VARIANT)?;
+            let cross_swap = if let Some(v) = variant_attrs.get(a::CROSS_SWAP) {
+                v.to_prob()?
+            } else {
+                cross_swap_enum.clone()
+            };
+
+            let ident = &variant.ident;
+            let self_bindings = prefixed_fields_bindings(&variant.fields, "self");
+            let other_bindings = prefixed_fields_bindings(&variant.fields, "other");
+            let fields = crossover_fields(&variant.fields)?;
+
+            let body = match cross_swap {
+                Some(cross_swap) if !variant.fields.is_empty() => {
+                    let self_fields = prefixed_fields_values(&variant.fields, "self");
+                    let other_fields = prefixed_fields_values(&variant.fields, "other");
+
+                    quote! {
+                        if rng.sample(::mutagen::rand::distributions::Bernoulli::new(#cross_swap).unwrap()) {
+                            if rng.gen::<bool>() {
+                                #enum_ident::#ident #self_fields
+                            } else {
+                                #enum_ident::#ident #other_fields
+                            }
+                        } else {
+                            #enum_ident::#ident #fields
+                        }
+                    }
+                }
+                _ => quote! {
+                    #enum_ident::#ident #fields
+                },
+            };
+
+            Ok(quote! {
+                (#enum_ident::#ident #self_bindings, #enum_ident::#ident #other_bindings) => { #body }
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    // Only reachable when the parents are different variants, which is impossible with a single variant
+    let mismatched = if e.variants.len() > 1 {
+        quote! {
+            (self_variant, other_variant) => {
+                if rng.gen::<bool>() {
+                    self_variant
+                } else {
+                    other_variant
+                }
+            }
+        }
+    } else {
+        TokenStream2::new()
+    };
+
+    Ok(quote! {
+        match (self, other) {
+            #( #variants )*
+            #mismatched
+        }
+    })
+}
+
+fn crossover_fields(fields: &Fields) -> Result<TokenStream2> {
+    let values: Vec<TokenStream2> = flatten_fields(fields)
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let cross_recurse = parse_attrs(&field.attrs, a::FIELD)?
+                .get(a::CROSS_RECURSE)
+                .cloned()
+                .unwrap_or(Value::None)
+                .to_prob()?;
+
+            let self_ident = prefixed_field_ident(field, i, "self");
+            let other_ident = prefixed_field_ident(field, i, "other");
+
+            let pick = quote! {
+                if rng.gen::<bool>() {
+                    #self_ident
+                } else {
+                    #other_ident
+                }
+            };
+
+            Ok(if let Some(cross_recurse) = cross_recurse {
+                quote! {
+                    if rng.sample(::mutagen::rand::distributions::Bernoulli::new(#cross_recurse).unwrap()) {
+                        ::mutagen::Crossover::crossover_rng(#self_ident, #other_ident, rng, state.deepen())
+                    } else {
+                        #pick
+                    }
+                }
+            } else {
+                pick
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(fields_values(fields, &values))
+}
+
+/// Binds every field to an identifier starting with `prefix`, so that two values can be destructured side by side
+fn prefixed_fields_bindings(fields: &Fields, prefix: &str) -> TokenStream2 {
+    match fields {
+        Fields::Named(f) => {
+            let names = f.named.iter().map(|f| &f.ident);
+            let idents = f
+                .named
+                .iter()
+                .enumerate()
+                .map(|(i, field)| prefixed_field_ident(field, i, prefix));
+
+            quote! {
+                { #(#names: #idents),* }
+            }
+        }
+        Fields::Unnamed(f) => {
+            let idents = f
+                .unnamed
+                .iter()
+                .enumerate()
+                .map(|(i, field)| prefixed_field_ident(field, i, prefix));
+
+            quote! {
+                ( #(#idents),* )
+            }
+        }
+        Fields::Unit => TokenStream2::new(),
+    }
+}
+
+/// Rebuilds fields from the identifiers bound by [prefixed_fields_bindings]
+fn prefixed_fields_values(fields: &Fields, prefix: &str) -> TokenStream2 {
+    let values: Vec<TokenStream2> = flatten_fields(fields)
+        .iter()
+        .enumerate()
+        .map(|(i, field)| prefixed_field_ident(field, i, prefix).into_token_stream())
+        .collect();
+
+    fields_values(fields, &values)
+}
+
+fn fields_values(fields: &Fields, values: &[TokenStream2]) -> TokenStream2 {
+    match fields {
+        Fields::Named(f) => {
+            let names = f.named.iter().map(|f| &f.ident);
+
+            quote! {
+                { #(#names: #values),* }
+            }
+        }
+        Fields::Unnamed(_) => quote! {
+            ( #(#values),* )
+        },
+        Fields::Unit => TokenStream2::new(),
+    }
+}
+
+fn prefixed_field_ident(field: &Field, i: usize, prefix: &str) -> Ident {
+    format_ident!("{}{}", prefix, field_ident(field, i).as_ref())
+}
+
+/// Adds `bounds` to every type parameter, so that the derived impl can call the trait on fields of those types
+fn bounded_generics(generics: &Generics, bounds: &[TokenStream2]) -> Generics {
+    let mut generics = generics.clone();
+
+    for param in generics.type_params_mut() {
+        for bound in bounds {
+            param.bounds.push(parse_quote!(#bound));
+        }
+    }
+
+    generics
+}
+
 fn roll<T, Wf, Bf>(choices: &[T], weight_fn: Wf, body_fn: Bf, err: &str) -> Result<TokenStream2>
 where
-    Wf: Fn(&T) -> Result<Value>,
-    Bf: Fn(&T, usize) -> TokenStream2,
+    Wf: Fn(&T) -> Result<Option<TokenStream2>>,
+    Bf: Fn(&T, usize) -> Result<TokenStream2>,
 {
     let n = choices.len();
 
     if n == 0 {
         panic!("roll was called with 0 choices");
     } else if n == 1 {
-        return Ok(body_fn(&choices[0], 0));
+        return body_fn(&choices[0], 0);
     }
 
-    let weight_values: Vec<_> = choices.iter().map(weight_fn).collect::<Result<_>>()?;
-
-    let weights_opt: Vec<Option<TokenStream2>> = weight_values
-        .iter()
-        .map(Value::to_weight)
-        .collect::<Result<_>>()?;
+    let weights_opt: Vec<Option<TokenStream2>> =
+        choices.iter().map(weight_fn).collect::<Result<_>>()?;
 
     let weights: Vec<TokenStream2> = weights_opt
         .iter()
@@ -428,10 +944,10 @@ where
         .enumerate()
         .filter(|(_, (_, weight))| weight.is_some())
         .map(|(i, (choice, _))| {
-            let body = body_fn(choice, i);
-            quote! { if roll < cumul_weights[#i] { #body }}
+            let body = body_fn(choice, i)?;
+            Ok(quote! { if roll < cumul_weights[#i] { #body }})
         })
-        .collect();
+        .collect::<Result<_>>()?;
 
     Ok(quote! {
         let weights: [f64; #n] = [
@@ -472,6 +988,14 @@ fn field_ident(field: &Field, i: usize) -> Cow<'_, Ident> {
     }
 }
 
+/// The name a field is visited under by Traversable, and entered by in frozen paths
+fn field_name(field: &Field, i: usize) -> String {
+    field
+        .ident
+        .as_ref()
+        .map_or_else(|| i.to_string(), Ident::to_string)
+}
+
 fn tuple_field_ident(i: usize) -> Ident {
     Ident::new(&format!("_{}", i), Span::call_site())
 }
@@ -499,11 +1023,20 @@ struct KeyValue {
 
 impl Parse for KeyValue {
     fn parse(input: ParseStream) -> Result<Self> {
-        Ok(Self {
-            key: input.parse()?,
-            _eq: input.parse()?,
-            value: input.parse()?,
-        })
+        let key: Ident = input.parse()?;
+        let _eq = input.parse()?;
+
+        // Ranges are expressions, which can refer to constants, rather than a single literal or function
+        let value = if key == a::RANGE {
+            match input.parse()? {
+                Expr::Range(range) => Value::Range(range.to_token_stream()),
+                expr => return Err(Error::new(expr.span(), "Expected a range such as 0.0..1.0")),
+            }
+        } else {
+            input.parse()?
+        };
+
+        Ok(Self { key, _eq, value })
     }
 }
 
@@ -511,6 +1044,7 @@ impl Parse for KeyValue {
 enum Value {
     Lit(LitFloat),
     FnIdent(Ident),
+    Range(TokenStream2),
     None,
 }
 
@@ -539,6 +1073,7 @@ impl Value {
                 }
                 }))
             }
+            Value::Range(range) => Err(Error::new(range.span(), "Expected a weight")),
             Value::None => Ok(Some(quote!(1.0))),
         }
     }
@@ -566,9 +1101,28 @@ impl Value {
                     }
                 }))
             }
+            Value::Range(range) => Err(Error::new(range.span(), "Expected a probability")),
             Value::None => Ok(Some(quote!(0.5))),
         }
     }
+
+    fn to_fn(&self) -> Result<TokenStream2> {
+        match self {
+            Value::FnIdent(ident) => Ok(ident.to_token_stream()),
+            Value::Lit(lit) => Err(Error::new(lit.span(), "Expected a function")),
+            Value::Range(range) => Err(Error::new(range.span(), "Expected a function")),
+            Value::None => Err(Error::new(Span::call_site(), "Expected a function")),
+        }
+    }
+
+    fn to_range(&self) -> Result<Option<TokenStream2>> {
+        match self {
+            Value::Range(range) => Ok(Some(range.clone())),
+            Value::Lit(lit) => Err(Error::new(lit.span(), "Expected a range")),
+            Value::FnIdent(ident) => Err(Error::new(ident.span(), "Expected a range")),
+            Value::None => Ok(None),
+        }
+    }
 }
 
 impl Parse for Value {
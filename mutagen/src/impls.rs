@@ -0,0 +1,362 @@
+//! Impls for types from the standard library, so they can be used as fields of derived types.
+//!
+//! Containers are transparent, in that moving into one doesn't deepen the state, the same as for `Box`.
+//! Their elements are named by index in paths, like tuple fields.
+
+use std::ops::{Bound, RangeBounds};
+
+use rand::{
+    distributions::uniform::{SampleUniform, Uniform},
+    Rng,
+};
+
+use crate::{
+    Crossover, Generatable, GeneratableIn, Mutatable, MutatableIn, State, Traversable, Updatable,
+};
+
+/// A distribution over a range with both ends given, such as `0.0..1.0` or `1..=4`
+fn uniform<X: SampleUniform + Copy, B: RangeBounds<X>>(range: &B) -> Uniform<X> {
+    match (range.start_bound(), range.end_bound()) {
+        (Bound::Included(&low), Bound::Excluded(&high)) => Uniform::new(low, high),
+        (Bound::Included(&low), Bound::Included(&high)) => Uniform::new_inclusive(low, high),
+        _ => panic!("Ranges for generation need both a start and an end"),
+    }
+}
+
+/// Mutates one element, unless it's frozen
+fn mutate_element<T: Mutatable, R: Rng + ?Sized>(elements: &mut [T], rng: &mut R, state: State) {
+    if elements.is_empty() {
+        return;
+    }
+
+    let index = rng.gen_range(0, elements.len());
+    let state = state.enter(&index.to_string());
+
+    if !state.is_frozen() {
+        elements[index].mutate_rng(rng, state);
+    }
+}
+
+// Primitives are plain data, generated from rand's standard distribution.
+// That covers every value of an integer, but only 0 to 1 for a float, so floats usually want a range.
+macro_rules! impl_primitive {
+    ($($t:ty),*) => {
+        $(
+            impl Generatable for $t {
+                fn generate_rng<R: Rng + ?Sized>(rng: &mut R, _state: State) -> Self {
+                    rng.gen()
+                }
+            }
+
+            impl Mutatable for $t {
+                fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: State) {
+                    *self = Self::generate_rng(rng, state);
+                }
+            }
+
+            impl Traversable for $t {
+                fn data_value(&self) -> Option<String> {
+                    Some(self.to_string())
+                }
+            }
+        )*
+
+        impl_leaf_crossover!($($t),*);
+        impl_leaf_updatable!($($t),*);
+    };
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {
+        impl_primitive!($($t),*);
+
+        $(
+            impl GeneratableIn for $t {
+                type Bound = Self;
+
+                fn generate_in<R: Rng + ?Sized, B: RangeBounds<Self>>(
+                    rng: &mut R,
+                    _state: State,
+                    range: &B,
+                ) -> Self {
+                    rng.sample(uniform(range))
+                }
+            }
+
+            impl MutatableIn for $t {
+                type Bound = Self;
+
+                fn mutate_in<R: Rng + ?Sized, B: RangeBounds<Self>>(
+                    &mut self,
+                    rng: &mut R,
+                    state: State,
+                    range: &B,
+                ) {
+                    *self = Self::generate_in(rng, state, range);
+                }
+            }
+        )*
+    };
+}
+
+impl_primitive!(bool);
+impl_number!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+/// Either a value or nothing, with even odds
+impl<T: Generatable> Generatable for Option<T> {
+    fn generate_rng<R: Rng + ?Sized>(rng: &mut R, state: State) -> Self {
+        if rng.gen() {
+            Some(T::generate_rng(rng, state))
+        } else {
+            None
+        }
+    }
+}
+
+/// Fills in a missing value, or either mutates or drops an existing one with even odds
+impl<T: Generatable + Mutatable> Mutatable for Option<T> {
+    fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: State) {
+        match self {
+            // Dropping the value would take anything frozen inside it along with it
+            Some(value) if state.contains_frozen() || rng.gen() => value.mutate_rng(rng, state),
+            Some(_) => *self = None,
+            None => *self = Some(T::generate_rng(rng, state)),
+        }
+    }
+}
+
+impl<T: Crossover> Crossover for Option<T> {
+    fn crossover_rng<R: Rng + ?Sized>(self, other: Self, rng: &mut R, state: State) -> Self {
+        match (self, other) {
+            (Some(a), Some(b)) => Some(a.crossover_rng(b, rng, state)),
+            (a, b) => {
+                if rng.gen() {
+                    a
+                } else {
+                    b
+                }
+            }
+        }
+    }
+}
+
+impl<T: Updatable> Updatable for Option<T> {
+    fn update(&mut self, state: State) {
+        if let Some(value) = self {
+            value.update(state);
+        }
+    }
+
+    fn is_stateful(&self) -> bool {
+        matches!(self, Some(value) if value.is_stateful())
+    }
+}
+
+impl<T: Traversable> Traversable for Option<T> {
+    fn node_name(&self) -> Option<&'static str> {
+        self.as_ref().and_then(T::node_name)
+    }
+
+    fn data_value(&self) -> Option<String> {
+        self.as_ref().and_then(T::data_value)
+    }
+
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        if let Some(value) = self {
+            value.visit_children(f);
+        }
+    }
+}
+
+/// A `Vec` can only be generated with a range for its length, since there's no good length to default to
+impl<T: Generatable> GeneratableIn for Vec<T> {
+    type Bound = usize;
+
+    fn generate_in<R: Rng + ?Sized, B: RangeBounds<usize>>(
+        rng: &mut R,
+        state: State,
+        range: &B,
+    ) -> Self {
+        let len = rng.sample(uniform(range));
+        (0..len).map(|_| T::generate_rng(rng, state)).collect()
+    }
+}
+
+/// Mutates one element, keeping the length the same
+impl<T: Mutatable> Mutatable for Vec<T> {
+    fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: State) {
+        mutate_element(self, rng, state);
+    }
+}
+
+/// Inserts, removes or mutates an element, as long as the length stays in range
+impl<T: Generatable + Mutatable> MutatableIn for Vec<T> {
+    type Bound = usize;
+
+    fn mutate_in<R: Rng + ?Sized, B: RangeBounds<usize>>(
+        &mut self,
+        rng: &mut R,
+        state: State,
+        range: &B,
+    ) {
+        // Inserting or removing would move the elements that frozen paths point to
+        let resizable = !state.contains_frozen();
+        let can_grow = resizable && range.contains(&(self.len() + 1));
+        let can_shrink = resizable && !self.is_empty() && range.contains(&(self.len() - 1));
+
+        match rng.gen_range(0, 3) {
+            0 if can_grow => {
+                let index = rng.gen_range(0, self.len() + 1);
+                self.insert(index, T::generate_rng(rng, state));
+            }
+            1 if can_shrink => {
+                let index = rng.gen_range(0, self.len());
+                self.remove(index);
+            }
+            _ => mutate_element(self, rng, state),
+        }
+    }
+}
+
+/// Crosses over the elements both parents have, and keeps the length of either one
+impl<T: Crossover> Crossover for Vec<T> {
+    fn crossover_rng<R: Rng + ?Sized>(self, other: Self, rng: &mut R, state: State) -> Self {
+        let len = if rng.gen() { self.len() } else { other.len() };
+        let mut a = self.into_iter();
+        let mut b = other.into_iter();
+
+        (0..len)
+            .map(|_| match (a.next(), b.next()) {
+                (Some(a), Some(b)) => a.crossover_rng(b, rng, state),
+                (Some(value), None) | (None, Some(value)) => value,
+                (None, None) => unreachable!(),
+            })
+            .collect()
+    }
+}
+
+impl<T: Updatable> Updatable for Vec<T> {
+    fn update(&mut self, state: State) {
+        for value in self {
+            value.update(state);
+        }
+    }
+
+    fn is_stateful(&self) -> bool {
+        self.iter().any(T::is_stateful)
+    }
+}
+
+impl<T: Traversable> Traversable for Vec<T> {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        for (i, value) in self.iter().enumerate() {
+            f(&i.to_string(), value);
+        }
+    }
+}
+
+impl<T: Generatable, const N: usize> Generatable for [T; N] {
+    fn generate_rng<R: Rng + ?Sized>(rng: &mut R, state: State) -> Self {
+        [(); N].map(|_| T::generate_rng(rng, state))
+    }
+}
+
+/// Mutates one element
+impl<T: Mutatable, const N: usize> Mutatable for [T; N] {
+    fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: State) {
+        mutate_element(self, rng, state);
+    }
+}
+
+/// Crosses over each pair of elements
+impl<T: Crossover, const N: usize> Crossover for [T; N] {
+    fn crossover_rng<R: Rng + ?Sized>(self, other: Self, rng: &mut R, state: State) -> Self {
+        let mut other = IntoIterator::into_iter(other);
+        self.map(|value| value.crossover_rng(other.next().unwrap(), rng, state))
+    }
+}
+
+impl<T: Updatable, const N: usize> Updatable for [T; N] {
+    fn update(&mut self, state: State) {
+        for value in self {
+            value.update(state);
+        }
+    }
+
+    fn is_stateful(&self) -> bool {
+        self.iter().any(T::is_stateful)
+    }
+}
+
+impl<T: Traversable, const N: usize> Traversable for [T; N] {
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        for (i, value) in self.iter().enumerate() {
+            f(&i.to_string(), value);
+        }
+    }
+}
+
+// Tuples work like tuple structs, except that they aren't nodes themselves
+macro_rules! impl_tuple {
+    ($(($($t:ident $i:tt),+))*) => {
+        $(
+            impl<$($t: Generatable),+> Generatable for ($($t,)+) {
+                fn generate_rng<R: Rng + ?Sized>(rng: &mut R, state: State) -> Self {
+                    ($($t::generate_rng(rng, state),)+)
+                }
+            }
+
+            /// Mutates one element, unless it's frozen
+            impl<$($t: Mutatable),+> Mutatable for ($($t,)+) {
+                fn mutate_rng<R: Rng + ?Sized>(&mut self, rng: &mut R, state: State) {
+                    let len = [$(stringify!($i)),+].len();
+
+                    match rng.gen_range(0, len) {
+                        $(
+                            $i => {
+                                let state = state.enter(stringify!($i));
+
+                                if !state.is_frozen() {
+                                    self.$i.mutate_rng(rng, state);
+                                }
+                            }
+                        )+
+                        _ => unreachable!(),
+                    }
+                }
+            }
+
+            /// Crosses over each pair of elements
+            impl<$($t: Crossover),+> Crossover for ($($t,)+) {
+                fn crossover_rng<R: Rng + ?Sized>(self, other: Self, rng: &mut R, state: State) -> Self {
+                    ($(self.$i.crossover_rng(other.$i, rng, state),)+)
+                }
+            }
+
+            impl<$($t: Updatable),+> Updatable for ($($t,)+) {
+                fn update(&mut self, state: State) {
+                    $(self.$i.update(state);)+
+                }
+
+                fn is_stateful(&self) -> bool {
+                    $(self.$i.is_stateful())||+
+                }
+            }
+
+            impl<$($t: Traversable),+> Traversable for ($($t,)+) {
+                fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+                    $(f(stringify!($i), &self.$i);)+
+                }
+            }
+        )*
+    };
+}
+
+impl_tuple! {
+    (A 0)
+    (A 0, B 1)
+    (A 0, B 1, C 2)
+    (A 0, B 1, C 2, D 3)
+    (A 0, B 1, C 2, D 3, E 4)
+    (A 0, B 1, C 2, D 3, E 4, F 5)
+}
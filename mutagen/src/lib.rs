@@ -6,6 +6,10 @@
 //!
 //! When derived on an enum, it will choose a variant at random and recursively generate its fields.
 //!
+//! Every variant it generates is counted against the [NodeBudget] in the [State], if there is one,
+//! so weight functions can stop growing a tree once it's big enough.
+//! Structs aren't counted, since choosing a variant is where a tree grows, while structs are often plain data.
+//!
 //! # Mutatable
 //!
 //! When derived on a struct, it will pick a field at random
@@ -13,6 +17,39 @@
 //! When derived on an enum, it requires [Generatable] to also be implemented for all fields, unless mut_reroll is 0.
 //! It will then choose whether to re-roll a new variant with probability mut_reroll, or to mutate its current variant.
 //!
+//! Subtrees can be frozen at runtime by passing a [Frozen] set in the [State], which the derived impls never mutate.
+//! A variant with anything frozen below it is never re-rolled either, so its frozen fields survive.
+//!
+//! # Crossover
+//!
+//! When derived on a struct, it will take each field from either parent, or recursively cross over the two parents' fields.
+//!
+//! When derived on an enum, it will recursively cross over the fields of two parents that share a variant,
+//! or swap in the whole variant from one parent with probability cross_swap.
+//! When the parents are different variants, one of them is picked as a whole.
+//!
+//! # Traversable
+//!
+//! When derived on a struct or an enum, it will name the node after the struct or the current variant,
+//! and visit each of its fields as a child.
+//!
+//! # Updatable
+//!
+//! When derived on a struct, it will update each field, and when derived on an enum, each field of the current variant.
+//!
+//! A variant that keeps state of its own can name a function to update it with, as in `#[mutagen(update = step)]`.
+//! It's called with the whole value once the variant's fields have been updated, and its signature should be
+//! `fn(&mut Self, mutagen::State)`. Only values with such a variant somewhere inside them are
+//! [stateful](Updatable::is_stateful).
+//!
+//! # Generics and fields
+//!
+//! Every derive works on generic types, and requires each type parameter to implement the trait being derived.
+//!
+//! Besides `Box` and the other derived types, fields can be `Option`s, `Vec`s, arrays, tuples of up to 6 elements,
+//! `bool`s or primitive numbers. Numbers are generated from rand's standard distribution unless they're given
+//! a range, and a `Vec` can only be generated with a range for its length.
+//!
 //! # Attributes
 //!
 //! This crate makes extensive use of key-value pairs in attributes to customize the behaviour of its derive macros.
@@ -84,6 +121,7 @@
 //!
 //! When applied to an enum variant, it affects how often that variant is generated.
 //! By default, all variants have weight 1.
+//! A [Profile] passed in the [State] can scale it further at runtime.
 //!
 //! Note that when an enum variant has a weight of 0, it will never be generated, so the derived impl
 //! will not expect its fields to implement Generatable.
@@ -100,6 +138,28 @@
 //!
 //! When applied to an enum, it sets the probability that an enum variant will be rerolled.
 //! When applied to an enum variant, it overrides the value set on the enum for that particular variant.
+//! A [Profile] passed in the [State] can replace it at runtime.
+//!
+//! **`#[mutagen(cross_swap = 0.5)]`**
+//!
+//! When applied to an enum, it sets the probability that crossing over two parents of the same variant
+//! takes that variant as a whole from one of them, instead of crossing over its fields.
+//! When applied to an enum variant, it overrides the value set on the enum for that particular variant.
+//!
+//! **`#[mutagen(cross_recurse = 0.5)]`**
+//!
+//! When applied to a struct field, it sets the probability that the field is crossed over recursively,
+//! instead of being taken as a whole from one of the parents.
+//!
+//! Note that when a field has a probability of 0, it will never be crossed over, so the derived impl
+//! will not expect its fields to implement Crossover.
+//!
+//! **`#[mutagen(range = 0.0..1.0)]`**
+//!
+//! When applied to a struct field, it sets the range that the field is generated and mutated within,
+//! using [GeneratableIn] and [MutatableIn] instead of [Generatable] and [Mutatable].
+//! Ranges can be exclusive or inclusive, but need both ends, and can be any expression.
+//! For a number it bounds the value, and for a `Vec` it bounds the length.
 
 #[doc(no_inline)]
 /// The `rand` dependency, re-exported for ease of access
@@ -108,22 +168,308 @@ pub use rand;
 #[doc(hidden)]
 pub use mutagen_derive::*;
 
-use std::{ops::DerefMut, rc::Rc, sync::Arc};
+/// Implements [Crossover] for plain data that can't be recombined, by taking it whole from either parent.
+///
+/// ```rust
+/// # use mutagen::impl_leaf_crossover;
+/// #[derive(Clone)]
+/// struct Seed(u32);
+///
+/// impl_leaf_crossover!(Seed);
+/// ```
+#[macro_export]
+macro_rules! impl_leaf_crossover {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl $crate::Crossover for $t {
+                fn crossover_rng<R: $crate::rand::Rng + ?Sized>(
+                    self,
+                    other: Self,
+                    rng: &mut R,
+                    _state: $crate::State,
+                ) -> Self {
+                    if $crate::rand::Rng::gen(rng) {
+                        self
+                    } else {
+                        other
+                    }
+                }
+            }
+        )*
+    };
+}
+
+/// Implements [Updatable] for plain data that keeps no state, so updating it does nothing
+#[macro_export]
+macro_rules! impl_leaf_updatable {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl $crate::Updatable for $t {
+                fn update(&mut self, _state: $crate::State) {}
+            }
+        )*
+    };
+}
+
+mod impls;
+
+// Lets the derive macros refer to `::mutagen` from within this crate's own tests
+#[cfg(test)]
+extern crate self as mutagen;
+
+use std::{
+    any::Any,
+    cell::Cell,
+    collections::{BTreeMap, HashMap},
+    ops::{DerefMut, RangeBounds},
+    rc::Rc,
+    sync::Arc,
+};
 
 use rand::Rng;
 
+/// Context passed down through a tree while it's generated, mutated, crossed over or updated
 #[derive(Clone, Copy, Default)]
-pub struct State {
+pub struct State<'a> {
     pub depth: usize,
+    /// The struct or enum variant whose field is being generated or mutated, named as by [Traversable::node_name]
+    pub parent: Option<&'static str>,
+    // The nodes counted so far in the tree being generated or mutated, and how many it's allowed
+    budget: Option<&'a NodeBudget>,
+    // The part of the frozen set below the node being visited, if there's any of it left
+    frozen: Option<&'a Frozen>,
+    profile: Option<&'a Profile>,
+    // Whatever the program passes down for nodes to update against, such as the world they're in
+    context: Option<&'a dyn Any>,
 }
 
-impl State {
+impl<'a> State<'a> {
     pub fn deepen(self) -> Self {
         Self {
             depth: self.depth + 1,
             ..self
         }
     }
+
+    /// Records that the next values are fields of `parent`
+    pub fn with_parent(self, parent: &'static str) -> Self {
+        Self {
+            parent: Some(parent),
+            ..self
+        }
+    }
+
+    /// Counts every node generated from now on against `budget`
+    pub fn with_budget(self, budget: &'a NodeBudget) -> Self {
+        Self {
+            budget: Some(budget),
+            ..self
+        }
+    }
+
+    /// Counts a newly generated node against the budget, if there is one.
+    /// Called by the derived impls for every enum variant they generate.
+    pub fn count_node(&self) {
+        if let Some(budget) = self.budget {
+            budget.used.set(budget.used.get() + 1);
+        }
+    }
+
+    /// The number of nodes counted against the budget so far, or 0 if there isn't one
+    pub fn node_count(&self) -> usize {
+        self.budget.map_or(0, NodeBudget::used)
+    }
+
+    /// The most nodes the tree is allowed, if there's a limit
+    pub fn node_budget(&self) -> Option<usize> {
+        self.budget.map(NodeBudget::limit)
+    }
+
+    /// Whether the tree has used up its budget, after which weight functions should only allow leaves
+    pub fn budget_exhausted(&self) -> bool {
+        matches!(self.budget, Some(budget) if budget.used() >= budget.limit())
+    }
+
+    /// Leaves the subtrees in `frozen` alone when mutating, with paths starting from the value this state is passed to
+    pub fn with_frozen(self, frozen: &'a Frozen) -> Self {
+        Self {
+            frozen: Some(frozen),
+            ..self
+        }
+    }
+
+    /// Looks up the weights of enum variants in `profile` as well as in their attributes
+    pub fn with_profile(self, profile: &'a Profile) -> Self {
+        Self {
+            profile: Some(profile),
+            ..self
+        }
+    }
+
+    /// Passes `context` down to every value updated with this state, for [context](State::context) to get back
+    pub fn with_context(self, context: &'a dyn Any) -> Self {
+        Self {
+            context: Some(context),
+            ..self
+        }
+    }
+
+    /// The context passed to [with_context](State::with_context), if there is one and it's a `T`
+    pub fn context<T: Any>(&self) -> Option<&'a T> {
+        self.context.and_then(|context| context.downcast_ref())
+    }
+
+    /// The weight of generating a variant, which is its gen_weight attribute scaled by the profile.
+    /// Called by the derived impls.
+    pub fn gen_weight(&self, type_name: &str, variant: &str, weight: f64) -> f64 {
+        self.profile
+            .and_then(|profile| profile.gen_weights.get(type_name))
+            .and_then(|variants| variants.get(variant))
+            .map_or(weight, |scale| weight * scale)
+    }
+
+    /// The probability of rerolling a variant when mutating, which the profile replaces if it has one.
+    /// Called by the derived impls.
+    pub fn mut_reroll(&self, type_name: &str, variant: &str, probability: f64) -> f64 {
+        self.profile
+            .and_then(|profile| profile.mut_rerolls.get(type_name))
+            .and_then(|variants| variants.get(variant))
+            .copied()
+            .unwrap_or(probability)
+    }
+
+    /// Moves down into the field called `name`, as named by [Traversable::visit_children].
+    /// This only follows the path, so derived impls also call [deepen](State::deepen) when moving down a level.
+    pub fn enter(self, name: &str) -> Self {
+        Self {
+            frozen: self.frozen.and_then(|frozen| {
+                if frozen.whole {
+                    Some(frozen)
+                } else {
+                    frozen.children.get(name)
+                }
+            }),
+            ..self
+        }
+    }
+
+    /// Whether the current value is inside a frozen subtree, and so shouldn't be mutated at all
+    pub fn is_frozen(&self) -> bool {
+        matches!(self.frozen, Some(frozen) if frozen.whole)
+    }
+
+    /// Whether anything at or below the current value is frozen, and so it can't be replaced as a whole
+    pub fn contains_frozen(&self) -> bool {
+        self.frozen.is_some()
+    }
+}
+
+/// A limit on the number of nodes in a tree, shared by every [State] generating or mutating it.
+///
+/// Nodes are only counted as they're generated, so weight functions have to check
+/// [State::budget_exhausted] and stop branching for the limit to hold. Even then, the branches
+/// that are already open still need their leaves, so a tree can end up a little over the limit.
+#[derive(Debug)]
+pub struct NodeBudget {
+    limit: usize,
+    used: Cell<usize>,
+}
+
+impl NodeBudget {
+    pub fn new(limit: usize) -> Self {
+        Self::with_used(limit, 0)
+    }
+
+    /// A budget for mutating a tree, which counts the nodes it already has.
+    /// Nodes replaced by rerolling aren't taken back off, so mutation errs on the side of stopping early.
+    pub fn with_used(limit: usize, used: usize) -> Self {
+        Self {
+            limit,
+            used: Cell::new(used),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+}
+
+/// Weights for enum variants that can be changed at runtime, looked up by the names of the enum and the variant.
+///
+/// Variants whose gen_weight or mut_reroll attribute is 0 are left out of the derived impls entirely,
+/// since their fields don't have to support it, so a profile can't bring them back.
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    gen_weights: HashMap<String, HashMap<String, f64>>,
+    mut_rerolls: HashMap<String, HashMap<String, f64>>,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Multiplies the weight of generating `variant` of `type_name` by `scale`, on top of its gen_weight attribute.
+    /// Panics if `scale` is negative.
+    pub fn set_gen_weight(&mut self, type_name: &str, variant: &str, scale: f64) {
+        assert!(scale >= 0.0, "Invalid gen_weight scale {}", scale);
+
+        self.gen_weights
+            .entry(type_name.to_owned())
+            .or_default()
+            .insert(variant.to_owned(), scale);
+    }
+
+    /// Replaces the probability of rerolling `variant` of `type_name` when mutating.
+    /// Panics if `probability` isn't between 0 and 1.
+    pub fn set_mut_reroll(&mut self, type_name: &str, variant: &str, probability: f64) {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "Invalid mut_reroll probability {}",
+            probability
+        );
+
+        self.mut_rerolls
+            .entry(type_name.to_owned())
+            .or_default()
+            .insert(variant.to_owned(), probability);
+    }
+}
+
+/// A set of subtrees for mutation to leave alone, each named by the path of field names leading to it.
+///
+/// Paths are written with the field names separated by dots, such as `child_a.child.0`,
+/// where tuple fields are named by their index.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Frozen {
+    // Whether everything from here down is frozen
+    whole: bool,
+    children: BTreeMap<String, Frozen>,
+}
+
+impl Frozen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Freezes the subtree at a dot separated path, where an empty path freezes everything
+    pub fn insert(&mut self, path: &str) {
+        let mut frozen = self;
+
+        for name in path.split('.').filter(|name| !name.is_empty()) {
+            frozen = frozen.children.entry(name.to_owned()).or_default();
+        }
+
+        frozen.whole = true;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.whole && self.children.is_empty()
+    }
 }
 
 /// A trait denoting that the type may be randomly generated
@@ -137,6 +483,11 @@ pub trait Generatable: Sized {
 
     /// The main required method for generation
     fn generate_rng<R: Rng + ?Sized>(rng: &mut R, state: State) -> Self;
+
+    /// The names of the variants a [Profile] can weight, which the derived impl lists for enums, and is empty otherwise
+    fn variant_names() -> &'static [&'static str] {
+        &[]
+    }
 }
 
 impl<T: Generatable> Generatable for Box<T> {
@@ -163,6 +514,19 @@ impl Generatable for () {
     }
 }
 
+/// A trait denoting that the type may be randomly generated within a range,
+/// which the derive macros use for fields with a `range` attribute
+pub trait GeneratableIn: Sized {
+    /// What the range is made of, which is the type itself for numbers and the length for a `Vec`
+    type Bound;
+
+    fn generate_in<R: Rng + ?Sized, B: RangeBounds<Self::Bound>>(
+        rng: &mut R,
+        state: State,
+        range: &B,
+    ) -> Self;
+}
+
 /// A trait denoting that the type may be randomly mutated
 ///
 /// # Derive
@@ -192,26 +556,183 @@ impl Mutatable for () {
     fn mutate_rng<R: Rng + ?Sized>(&mut self, _rng: &mut R, _state: State) {}
 }
 
+/// A trait denoting that the type may be randomly mutated without leaving a range,
+/// which the derive macros use for fields with a `range` attribute
+pub trait MutatableIn {
+    /// What the range is made of, as in [GeneratableIn::Bound]
+    type Bound;
+
+    fn mutate_in<R: Rng + ?Sized, B: RangeBounds<Self::Bound>>(
+        &mut self,
+        rng: &mut R,
+        state: State,
+        range: &B,
+    );
+}
+
 /// A trait denoting that the type may be updated.
 ///
 /// # Derive
 /// When derived on a struct, it will call each field's [`update()`](crate::Updatable::update)
 ///
 /// When derived on an enum, it will call the current variant's [`update()`](crate::Updatable::update)
+///
+/// Anything a type needs to update against, beyond its own fields, can be passed down with [State::with_context].
 pub trait Updatable {
     fn update(&mut self, state: State);
+
+    /// Whether updating can change anything, so that callers can skip the work that only state needs.
+    /// The derived impl checks for variants with an `update` hook, and impls that keep state by hand should return true.
+    fn is_stateful(&self) -> bool {
+        false
+    }
 }
 
 impl<T: Updatable> Updatable for Box<T> {
     fn update(&mut self, state: State) {
         self.deref_mut().update(state)
     }
+
+    fn is_stateful(&self) -> bool {
+        (**self).is_stateful()
+    }
 }
 
 impl Updatable for () {
     fn update(&mut self, _state: State) {}
 }
 
+/// A trait denoting that the type is a node in a tree that can be inspected.
+///
+/// Plain data stored in a tree, such as constants, should implement it with the default methods,
+/// so that it's visited as a child but isn't counted as a node.
+///
+/// # Derive
+/// When derived on a struct, it will be named after the struct and visit each field
+///
+/// When derived on an enum, it will be named after the current variant and visit each of its fields
+pub trait Traversable {
+    /// The name of this node, or `None` if it's plain data rather than a node
+    fn node_name(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// A short rendering of the value held by plain data, such as a constant
+    fn data_value(&self) -> Option<String> {
+        None
+    }
+
+    /// Calls `f` with the name and value of each field directly below this one
+    fn visit_children(&self, _f: &mut dyn FnMut(&str, &dyn Traversable)) {}
+
+    /// The number of nodes in the tree, including this one
+    fn node_count(&self) -> usize {
+        let mut count = if self.node_name().is_some() { 1 } else { 0 };
+        self.visit_children(&mut |_, child| count += child.node_count());
+        count
+    }
+
+    /// The number of nodes on the longest path from this node down to a leaf
+    fn max_depth(&self) -> usize {
+        let mut depth = 0;
+        self.visit_children(&mut |_, child| depth = depth.max(child.max_depth()));
+
+        if self.node_name().is_some() {
+            depth + 1
+        } else {
+            depth
+        }
+    }
+
+    /// How many times each kind of node appears in the tree, keyed by name
+    fn node_histogram(&self) -> BTreeMap<&'static str, usize> {
+        let mut histogram = BTreeMap::new();
+        add_to_histogram(self, &mut histogram);
+        histogram
+    }
+
+    /// The path to every node in the tree, written as [Frozen] reads them, along with the node's name.
+    /// The path to this node is empty.
+    fn node_paths(&self) -> Vec<(String, &'static str)> {
+        let mut paths = Vec::new();
+        add_to_paths(self, "", &mut paths);
+        paths
+    }
+}
+
+fn add_to_histogram<T: Traversable + ?Sized>(
+    node: &T,
+    histogram: &mut BTreeMap<&'static str, usize>,
+) {
+    if let Some(name) = node.node_name() {
+        *histogram.entry(name).or_insert(0) += 1;
+    }
+
+    node.visit_children(&mut |_, child| add_to_histogram(child, histogram));
+}
+
+fn add_to_paths<T: Traversable + ?Sized>(
+    node: &T,
+    path: &str,
+    paths: &mut Vec<(String, &'static str)>,
+) {
+    if let Some(name) = node.node_name() {
+        paths.push((path.to_owned(), name));
+    }
+
+    node.visit_children(&mut |name, child| {
+        let child_path = if path.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}.{}", path, name)
+        };
+
+        add_to_paths(child, &child_path, paths);
+    });
+}
+
+impl<T: Traversable + ?Sized> Traversable for Box<T> {
+    fn node_name(&self) -> Option<&'static str> {
+        (**self).node_name()
+    }
+
+    fn data_value(&self) -> Option<String> {
+        (**self).data_value()
+    }
+
+    fn visit_children(&self, f: &mut dyn FnMut(&str, &dyn Traversable)) {
+        (**self).visit_children(f)
+    }
+}
+
+impl Traversable for () {}
+
+/// A trait denoting that two values of the type may be recombined into a child
+///
+/// # Derive
+/// When derived on a struct, it will pick each field from either parent, or call that field's [`crossover()`](crate::Crossover::crossover)
+///
+/// When derived on an enum, it will cross over the fields of two parents of the same variant, or take a whole variant from either parent.
+pub trait Crossover: Sized {
+    /// Convenience shorthand for `self.crossover_rng(other, &mut rand::thread_rng(), State::default())`
+    fn crossover(self, other: Self) -> Self {
+        self.crossover_rng(other, &mut rand::thread_rng(), State::default())
+    }
+
+    /// The main required method for crossover
+    fn crossover_rng<R: Rng + ?Sized>(self, other: Self, rng: &mut R, state: State) -> Self;
+}
+
+impl<T: Crossover> Crossover for Box<T> {
+    fn crossover_rng<R: Rng + ?Sized>(self, other: Self, rng: &mut R, state: State) -> Self {
+        Box::new((*self).crossover_rng(*other, rng, state))
+    }
+}
+
+impl Crossover for () {
+    fn crossover_rng<R: Rng + ?Sized>(self, _other: Self, _rng: &mut R, _state: State) -> Self {}
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -245,4 +766,453 @@ mod test {
 
     #[derive(Generatable, Mutatable, Updatable)]
     struct Bap(Bar, Bar);
+
+    #[derive(Crossover, Clone, Copy, PartialEq, Debug)]
+    struct Gene(#[mutagen(cross_recurse = 0.0)] u8);
+
+    #[derive(Crossover, Clone, Copy, PartialEq, Debug)]
+    struct Pair {
+        #[mutagen(cross_recurse = 0.0)]
+        left: Gene,
+        #[mutagen(cross_recurse = 0.0)]
+        right: Gene,
+    }
+
+    #[derive(Crossover, Clone, Copy, PartialEq, Debug)]
+    #[mutagen(cross_swap = 0.0)]
+    enum Tree {
+        #[mutagen(cross_swap = 1.0)]
+        Leaf(#[mutagen(cross_recurse = 0.0)] Gene),
+        Branch(Pair),
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Counter(u32);
+
+    impl Generatable for Counter {
+        fn generate_rng<R: Rng + ?Sized>(_rng: &mut R, _state: State) -> Self {
+            Counter(0)
+        }
+    }
+
+    impl Mutatable for Counter {
+        fn mutate_rng<R: Rng + ?Sized>(&mut self, _rng: &mut R, _state: State) {
+            self.0 += 1;
+        }
+    }
+
+    #[derive(Generatable, Mutatable)]
+    struct Counters {
+        a: Counter,
+        b: Counter,
+    }
+
+    #[derive(Generatable, Mutatable)]
+    #[mutagen(mut_reroll = 1.0)]
+    enum Rerolled {
+        Counted(Counters),
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Clock(u32);
+
+    impl Updatable for Clock {
+        fn update(&mut self, state: State) {
+            self.0 += state.context::<u32>().copied().unwrap_or(0);
+        }
+
+        fn is_stateful(&self) -> bool {
+            true
+        }
+    }
+
+    #[derive(Updatable)]
+    enum Clocks {
+        Pair {
+            a: Clock,
+            b: Box<Clock>,
+        },
+        #[mutagen(update = read_clock)]
+        Reading {
+            clock: Clock,
+            reading: u32,
+        },
+    }
+
+    fn read_clock(clocks: &mut Clocks, _state: State) {
+        if let Clocks::Reading { clock, reading } = clocks {
+            *reading = clock.0;
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct ParentName(Option<&'static str>);
+
+    impl Generatable for ParentName {
+        fn generate_rng<R: Rng + ?Sized>(_rng: &mut R, state: State) -> Self {
+            ParentName(state.parent)
+        }
+    }
+
+    #[derive(Generatable)]
+    struct Named {
+        name: ParentName,
+    }
+
+    #[derive(Generatable, Debug, PartialEq)]
+    enum Chain {
+        #[mutagen(gen_weight = when_exhausted)]
+        End(ParentName),
+        #[mutagen(gen_weight = until_exhausted)]
+        Link(Box<Chain>),
+    }
+
+    fn when_exhausted(state: &State) -> f64 {
+        if state.budget_exhausted() {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    fn until_exhausted(state: &State) -> f64 {
+        1.0 - when_exhausted(state)
+    }
+
+    impl Chain {
+        fn len(&self) -> usize {
+            match self {
+                Chain::End(_) => 1,
+                Chain::Link(next) => 1 + next.len(),
+            }
+        }
+    }
+
+    #[derive(Traversable)]
+    enum Expr {
+        Leaf(Bar),
+        Add { a: Box<Expr>, b: Box<Expr> },
+        Neg(Box<Expr>),
+    }
+
+    impl Traversable for Bar {}
+
+    #[derive(Generatable, Mutatable, Traversable, Debug)]
+    struct Ranged {
+        #[mutagen(range = 0.25..0.5)]
+        float: f32,
+        #[mutagen(range = 1..=3)]
+        list: Vec<u8>,
+        pair: (bool, [u8; 2]),
+        maybe: Option<u8>,
+    }
+
+    #[derive(Generatable, Mutatable, Traversable, Debug)]
+    enum Wrapper<T> {
+        Empty,
+        Full(T),
+    }
+
+    #[derive(Traversable)]
+    struct Forest {
+        trees: Vec<Expr>,
+        pair: (Expr, Option<Expr>),
+    }
+
+    #[test]
+    fn traversable_stats() {
+        use Expr::*;
+
+        let tree = Add {
+            a: Box::new(Neg(Box::new(Leaf(Bar)))),
+            b: Box::new(Leaf(Bar)),
+        };
+
+        assert_eq!(tree.node_name(), Some("Expr::Add"));
+        assert_eq!(tree.node_count(), 4);
+        assert_eq!(tree.max_depth(), 3);
+
+        let histogram = tree.node_histogram();
+        assert_eq!(histogram["Expr::Leaf"], 2);
+        assert_eq!(histogram["Expr::Neg"], 1);
+        assert_eq!(histogram["Expr::Add"], 1);
+
+        let mut names = Vec::new();
+        tree.visit_children(&mut |name, _| names.push(name.to_owned()));
+        assert_eq!(names, ["a", "b"]);
+    }
+
+    #[test]
+    fn traversable_paths() {
+        use Expr::*;
+
+        let tree = Add {
+            a: Box::new(Neg(Box::new(Leaf(Bar)))),
+            b: Box::new(Leaf(Bar)),
+        };
+
+        assert_eq!(
+            tree.node_paths(),
+            [
+                (String::new(), "Expr::Add"),
+                ("a".to_owned(), "Expr::Neg"),
+                ("a.0".to_owned(), "Expr::Leaf"),
+                ("b".to_owned(), "Expr::Leaf"),
+            ]
+        );
+    }
+
+    #[test]
+    fn mutation_skips_frozen_fields() {
+        let mut rng = rand::thread_rng();
+        let mut frozen = Frozen::new();
+        frozen.insert("a");
+
+        let mut counters = Counters::generate();
+
+        for _ in 0..100 {
+            counters.mutate_rng(&mut rng, State::default().with_frozen(&frozen));
+        }
+
+        // Picking the frozen field wastes the mutation rather than mutating the other one instead
+        assert_eq!(counters.a, Counter(0));
+        assert!(counters.b.0 > 0);
+
+        let mutations = counters.b.0;
+        frozen.insert("");
+        counters.mutate_rng(&mut rng, State::default().with_frozen(&frozen));
+        assert_eq!(counters.b, Counter(mutations));
+    }
+
+    #[test]
+    fn mutation_keeps_variants_above_frozen_fields() {
+        let mut rng = rand::thread_rng();
+        let mut frozen = Frozen::new();
+        frozen.insert("0.a");
+
+        let mut rerolled = Rerolled::generate();
+
+        for _ in 0..100 {
+            rerolled.mutate_rng(&mut rng, State::default().with_frozen(&frozen));
+        }
+
+        // Every mutation would have rerolled the variant and reset both counters if nothing was frozen
+        let Rerolled::Counted(counters) = &rerolled;
+        assert_eq!(counters.a, Counter(0));
+        assert!(counters.b.0 > 0);
+    }
+
+    #[test]
+    fn profile_changes_weights() {
+        let mut rng = rand::thread_rng();
+
+        assert_eq!(Baz::variant_names(), &["Boz", "Bop", "Bof", "Bob"]);
+
+        let mut only_bob = Profile::new();
+        for variant in Baz::variant_names()
+            .iter()
+            .filter(|&&variant| variant != "Bob")
+        {
+            only_bob.set_gen_weight("Baz", variant, 0.0);
+        }
+
+        let mut never_reroll_bob = Profile::new();
+        never_reroll_bob.set_mut_reroll("Baz", "Bob", 0.0);
+
+        let is_bob = |baz: &Baz| matches!(baz, Baz::Bob { .. });
+
+        for _ in 0..100 {
+            let mut baz = Baz::generate_rng(&mut rng, State::default().with_profile(&only_bob));
+            assert!(is_bob(&baz));
+
+            // Rerolling would usually pick Boz, which has the most weight without the profile
+            baz.mutate_rng(&mut rng, State::default().with_profile(&never_reroll_bob));
+            assert!(is_bob(&baz));
+        }
+    }
+
+    #[test]
+    fn generation_counts_nodes_against_budget() {
+        let mut rng = rand::thread_rng();
+
+        for &limit in &[1, 2, 10] {
+            let budget = NodeBudget::new(limit);
+            let chain = Chain::generate_rng(&mut rng, State::default().with_budget(&budget));
+
+            // The last link still needs its end, which takes the chain one node over the limit
+            assert_eq!(chain.len(), limit + 1);
+            assert_eq!(budget.used(), limit + 1);
+        }
+
+        // A budget that's already spent only leaves room for a leaf
+        let budget = NodeBudget::with_used(5, 5);
+        let state = State::default().with_budget(&budget);
+        assert!(state.budget_exhausted());
+        assert_eq!(Chain::generate_rng(&mut rng, state).len(), 1);
+        assert_eq!(state.node_count(), 6);
+        assert_eq!(state.node_budget(), Some(5));
+
+        assert!(!State::default().budget_exhausted());
+        assert_eq!(State::default().node_budget(), None);
+    }
+
+    #[test]
+    fn generation_records_parents() {
+        let mut rng = rand::thread_rng();
+        let budget = NodeBudget::new(3);
+
+        assert_eq!(State::default().parent, None);
+        assert_eq!(Named::generate().name, ParentName(Some("Named")));
+
+        let mut chain = Chain::generate_rng(&mut rng, State::default().with_budget(&budget));
+        while let Chain::Link(next) = chain {
+            chain = *next;
+        }
+        assert_eq!(chain, Chain::End(ParentName(Some("Chain::End"))));
+    }
+
+    #[test]
+    fn update_passes_context_down() {
+        let mut clocks = Clocks::Pair {
+            a: Clock(0),
+            b: Box::new(Clock(1)),
+        };
+
+        clocks.update(State::default().with_context(&5u32));
+        // Anything of another type isn't found
+        clocks.update(State::default().with_context(&5u64));
+
+        match &clocks {
+            Clocks::Pair { a, b } => {
+                assert_eq!(*a, Clock(5));
+                assert_eq!(**b, Clock(6));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn update_calls_hooks_after_fields() {
+        let mut clocks = Clocks::Reading {
+            clock: Clock(1),
+            reading: 0,
+        };
+
+        clocks.update(State::default().with_context(&5u32));
+
+        // The hook reads the clock after it's moved on
+        match clocks {
+            Clocks::Reading { reading, .. } => assert_eq!(reading, 6),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn update_finds_state() {
+        let reading = Clocks::Reading {
+            clock: Clock(0),
+            reading: 0,
+        };
+        let pair = Clocks::Pair {
+            a: Clock(0),
+            b: Box::new(Clock(0)),
+        };
+
+        assert!(reading.is_stateful());
+        assert!(pair.is_stateful());
+        assert!(!Foo::generate().is_stateful());
+        assert!(!Some(Baz::Boz).is_stateful());
+    }
+
+    #[test]
+    fn ranged_fields_stay_in_range() {
+        let mut rng = rand::thread_rng();
+        let mut ranged = Ranged::generate();
+
+        for _ in 0..100 {
+            ranged.mutate_rng(&mut rng, State::default());
+
+            assert!((0.25..0.5).contains(&ranged.float), "{:?}", ranged);
+            assert!((1..=3).contains(&ranged.list.len()), "{:?}", ranged);
+        }
+    }
+
+    #[test]
+    fn generic_types_derive() {
+        let mut rng = rand::thread_rng();
+        let mut wrapper: Wrapper<Ranged> = Wrapper::generate();
+
+        for _ in 0..100 {
+            wrapper.mutate_rng(&mut rng, State::default());
+
+            if let Wrapper::Full(ranged) = &wrapper {
+                assert!((0.25..0.5).contains(&ranged.float));
+            }
+        }
+
+        assert_eq!(Wrapper::Full(1u8).node_name(), Some("Wrapper::Full"));
+        assert_eq!(Wrapper::<u8>::Empty.node_count(), 1);
+    }
+
+    #[test]
+    fn containers_have_paths() {
+        use Expr::*;
+
+        let forest = Forest {
+            trees: vec![Leaf(Bar), Neg(Box::new(Leaf(Bar)))],
+            pair: (Leaf(Bar), None),
+        };
+
+        assert_eq!(
+            forest.node_paths(),
+            [
+                (String::new(), "Forest"),
+                ("trees.0".to_owned(), "Expr::Leaf"),
+                ("trees.1".to_owned(), "Expr::Neg"),
+                ("trees.1.0".to_owned(), "Expr::Leaf"),
+                ("pair.0".to_owned(), "Expr::Leaf"),
+            ]
+        );
+    }
+
+    #[test]
+    fn crossover_struct_picks_fields_from_parents() {
+        let mut rng = rand::thread_rng();
+        let a = Pair {
+            left: Gene(1),
+            right: Gene(2),
+        };
+        let b = Pair {
+            left: Gene(3),
+            right: Gene(4),
+        };
+
+        for _ in 0..100 {
+            let child = a.crossover_rng(b, &mut rng, State::default());
+            assert!(child.left == a.left || child.left == b.left);
+            assert!(child.right == a.right || child.right == b.right);
+        }
+    }
+
+    #[test]
+    fn crossover_enum_keeps_variants() {
+        let mut rng = rand::thread_rng();
+        let leaf_a = Tree::Leaf(Gene(1));
+        let leaf_b = Tree::Leaf(Gene(2));
+        let branch = Tree::Branch(Pair {
+            left: Gene(3),
+            right: Gene(4),
+        });
+
+        for _ in 0..100 {
+            let child = leaf_a.crossover_rng(leaf_b, &mut rng, State::default());
+            assert!(child == leaf_a || child == leaf_b);
+
+            let child = leaf_a.crossover_rng(branch, &mut rng, State::default());
+            assert!(child == leaf_a || child == branch);
+
+            let child = branch.crossover_rng(branch, &mut rng, State::default());
+            assert_eq!(child, branch);
+        }
+    }
 }